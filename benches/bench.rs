@@ -5,7 +5,10 @@ use models::*;
 use std::collections::BTreeMap;
 
 use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
-use sweep_bptree::{BPlusTree, BPlusTreeMap, NodeStoreVec};
+use sweep_bptree::{
+    tree::{BinarySearch, KeySearch, LinearSearch},
+    BPlusTree, BPlusTreeMap, NodeStoreVec,
+};
 
 const COUNTS: [usize; 3] = [1000, 10000, 10_0000];
 const RAND_SEED: u64 = 123;
@@ -402,8 +405,60 @@ fn bench_cursor<K: TestKey>(c: &mut Criterion) {
     }
 }
 
+/// Binary against linear node search, through random gets and inserts of the whole tree
+fn bench_key_search<K: TestKey>(c: &mut Criterion) {
+    fn bench_with<K: TestKey, Se: KeySearch>(
+        group: &mut criterion::BenchmarkGroup<'_, criterion::measurement::WallTime>,
+        name: &str,
+        count: usize,
+    ) {
+        let mut r = StdRng::seed_from_u64(RAND_SEED);
+        let mut keys = (0..count).map(K::from_i).collect::<Vec<_>>();
+        keys.shuffle(&mut r);
+
+        group.bench_with_input(
+            BenchmarkId::new(format!("{name}_get"), count),
+            &count,
+            |b, count| {
+                let tree = create_bptree_with::<K, Se>(*count);
+                b.iter(|| {
+                    for k in keys.iter() {
+                        assert!(tree.get(k).is_some());
+                    }
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new(format!("{name}_insert"), count),
+            &count,
+            |b, _| {
+                b.iter(|| {
+                    let mut tree = BPlusTree::new(NodeStoreVec::<K, Value, (), Se>::new());
+                    for k in keys.iter() {
+                        tree.insert(k.clone(), Value::default());
+                    }
+                    black_box(tree)
+                });
+            },
+        );
+    }
+
+    let mut group = c.benchmark_group(format!("key_search/{}", K::name()));
+    for count in COUNTS {
+        bench_with::<K, BinarySearch>(&mut group, "binary", count);
+        bench_with::<K, LinearSearch>(&mut group, "linear", count);
+    }
+}
+
 fn create_bptree<K: TestKey>(count: usize) -> BPlusTree<NodeStoreBench<K>> {
-    let node_store = NodeStoreBench::new();
+    create_bptree_with::<K, BinarySearch>(count)
+}
+
+fn create_bptree_with<K: TestKey, Se: KeySearch>(
+    count: usize,
+) -> BPlusTree<NodeStoreVec<K, Value, (), Se>> {
+    let node_store = NodeStoreVec::new();
     let mut tree = BPlusTree::new(node_store);
 
     let mut keys = (0..count).collect::<Vec<_>>();
@@ -458,5 +513,7 @@ criterion_group!(
     bench_random_get<std::rc::Rc<String>>,
     bench_cursor<Point>,
     bench_cursor<String>,
+    bench_key_search<u64>,
+    bench_key_search<u32>,
 );
 criterion_main!(benches);
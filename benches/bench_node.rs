@@ -1,9 +1,12 @@
 mod models;
 use models::*;
 
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
-use sweep_bptree::tree::{InnerNode, InnerNodeId, LeafNode, NodeId};
+use sweep_bptree::tree::{
+    linear_search, linear_search_child, BinarySearch, InnerNode, InnerNodeId, KeySearch, LeafNode,
+    NodeId,
+};
 
 fn bench_inner<K: TestKey, const N: usize, const C: usize>(c: &mut Criterion) {
     let mut group = c.benchmark_group(format!("inner_node_{}_{N}", K::name()));
@@ -18,7 +21,7 @@ fn bench_inner<K: TestKey, const N: usize, const C: usize>(c: &mut Criterion) {
         b.iter(|| {
             for i in 0..128 {
                 let key = K::from_i(i);
-                node.locate_child(&key);
+                black_box(node.locate_child::<BinarySearch, _>(&key));
             }
         })
     });
@@ -47,17 +50,76 @@ fn bench_leaf<K: TestKey, V: Default>(c: &mut Criterion) {
 fn bench_leaf_inner<K: TestKey, V: Default>(keys: &[K], node: &LeafNode<K, V>) {
     let mut c = 0;
     for key in keys {
-        match node.locate_slot(&key) {
-            Ok(_) => c += 1,
-            Err(_) => {}
+        if node.locate_slot::<BinarySearch, _>(key).is_ok() {
+            c += 1;
         }
     }
     assert!(c > 0);
 }
 
+/// `BinarySearch` against `slice::binary_search` and linear search, over a full node of 64
+/// keys
+fn bench_key_search<K: TestKey>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("key_search_{}", K::name()));
+
+    // half of the queries hit, in an order branch predictors can't learn
+    let keys = (0..64).map(|i| K::from_i(i * 2)).collect::<Vec<_>>();
+    let queries = (0..128)
+        .map(|i| K::from_i((i * 37 + 11) % 128))
+        .collect::<Vec<_>>();
+
+    group.bench_function("search", |b| {
+        b.iter(|| {
+            for q in queries.iter() {
+                black_box(BinarySearch::search(&keys, q)).ok();
+            }
+        })
+    });
+
+    group.bench_function("binary_search", |b| {
+        b.iter(|| {
+            for q in queries.iter() {
+                black_box(keys.binary_search(q)).ok();
+            }
+        })
+    });
+
+    group.bench_function("linear_search", |b| {
+        b.iter(|| {
+            for q in queries.iter() {
+                black_box(linear_search(&keys, q)).ok();
+            }
+        })
+    });
+
+    group.bench_function("search_child", |b| {
+        b.iter(|| {
+            for q in queries.iter() {
+                black_box(BinarySearch::search_child(&keys, q));
+            }
+        })
+    });
+
+    group.bench_function("linear_search_child", |b| {
+        b.iter(|| {
+            for q in queries.iter() {
+                black_box(linear_search_child(&keys, q));
+            }
+        })
+    });
+
+    group.finish();
+}
+
 criterion_group!(benches,
     bench_inner<Point, 32, 33>,
     bench_leaf<Point, Value>,
     bench_inner<Point, 64, 65>,
+    bench_inner<u64, 64, 65>,
+    bench_leaf<u64, Value>,
+    bench_key_search<u32>,
+    bench_key_search<u64>,
+    bench_key_search<i64>,
+    bench_key_search<Point>,
 );
 criterion_main!(benches);
@@ -13,6 +13,8 @@ impl PartialEq for Point {
 }
 impl Eq for Point {}
 
+// keeps partial_cmp returning None for NaN coordinates
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for Point {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match self.x.partial_cmp(&other.x) {
@@ -29,6 +31,8 @@ impl Ord for Point {
     }
 }
 
+// only constructed by some of the benches
+#[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub struct Point3<const N: usize> {
     pub x: f64,
@@ -48,6 +52,7 @@ impl<const N: usize> PartialEq for Point3<N> {
 }
 impl<const N: usize> Eq for Point3<N> {}
 
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl<const N: usize> PartialOrd for Point3<N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match self.x.partial_cmp(&other.x) {
@@ -127,3 +132,21 @@ impl TestKey for Rc<String> {
         "Rc<String>".into()
     }
 }
+
+macro_rules! int_test_key {
+    ($($t:ty),*) => {
+        $(
+            impl TestKey for $t {
+                fn from_i(i: usize) -> Self {
+                    i as $t
+                }
+
+                fn name() -> String {
+                    stringify!($t).into()
+                }
+            }
+        )*
+    };
+}
+
+int_test_key!(u32, u64, i64);
@@ -1,9 +1,9 @@
 //! This examples shows how to use Augmentation to make date based statistics easy
-use std::cmp::Ordering;
+use std::{cmp::Ordering, convert::Infallible};
 
 use sweep_bptree::{
     augment::Augmentation,
-    tree::visit::{DescendVisit, DescendVisitResult},
+    tree::visit::{DescendVisit, DescendVisitResult, VisitContext},
     BPlusTreeMap,
 };
 
@@ -42,12 +42,14 @@ impl CountForYear {
 
 impl<V> DescendVisit<Date, V, DateStatistic> for CountForYear {
     type Result = usize;
+    type Error = Infallible;
 
     fn visit_inner(
         &mut self,
         _keys: &[Date],
         augmentations: &[DateStatistic],
-    ) -> DescendVisitResult<Self::Result> {
+        _ctx: VisitContext<'_, Date>,
+    ) -> Result<DescendVisitResult<Self::Result>, Infallible> {
         // Need to handle following cases:
         // 1. if the year is at min boundary, then we can return it
         // 2. if the year is larger than min, lower than max, then go down
@@ -63,7 +65,7 @@ impl<V> DescendVisit<Date, V, DateStatistic> for CountForYear {
                         continue;
                     }
                     Ordering::Greater => {
-                        return DescendVisitResult::GoDown(idx);
+                        return Ok(DescendVisitResult::GoDown(idx));
                     }
                 },
                 Ordering::Equal => match a.max.date.year.cmp(&self.year) {
@@ -77,24 +79,29 @@ impl<V> DescendVisit<Date, V, DateStatistic> for CountForYear {
                     }
                     Ordering::Greater => {
                         self.count += a.min.counter.year;
-                        return DescendVisitResult::Complete(self.count);
+                        return Ok(DescendVisitResult::Complete(self.count));
                     }
                 },
-                Ordering::Greater => return DescendVisitResult::Complete(self.count),
+                Ordering::Greater => return Ok(DescendVisitResult::Complete(self.count)),
             }
         }
 
-        DescendVisitResult::Complete(self.count)
+        Ok(DescendVisitResult::Complete(self.count))
     }
 
-    fn visit_leaf(&mut self, keys: &[Date], _values: &[V]) -> Option<Self::Result> {
+    fn visit_leaf(
+        &mut self,
+        keys: &[Date],
+        _values: &[V],
+        _ctx: VisitContext<'_, Date>,
+    ) -> Result<Option<Self::Result>, Infallible> {
         for k in keys {
             if k.year == self.year {
                 self.count += 1
             }
         }
 
-        Some(self.count)
+        Ok(Some(self.count))
     }
 }
 
@@ -187,7 +194,7 @@ impl Augmentation<Date> for DateStatistic {
         let first_key = keys_iter.next().unwrap();
 
         let mut min = DateStatisticEntry {
-            date: first_key.clone(),
+            date: *first_key,
             counter: Counter {
                 year: 1,
                 month: 1,
@@ -197,7 +204,7 @@ impl Augmentation<Date> for DateStatistic {
         let mut max = min.clone();
 
         for key in keys_iter {
-            match min.date.compare(&key) {
+            match min.date.compare(key) {
                 DateCompare::SameDay => {
                     min.counter.day += 1;
                     min.counter.month += 1;
@@ -212,26 +219,26 @@ impl Augmentation<Date> for DateStatistic {
                 }
                 DateCompare::DifferentYear => {}
             }
-            match max.date.compare(&key) {
+            match max.date.compare(key) {
                 DateCompare::SameDay => {
                     max.counter.day += 1;
                     max.counter.month += 1;
                     max.counter.year += 1;
                 }
                 DateCompare::SameMonth => {
-                    max.date = key.clone();
+                    max.date = *key;
                     max.counter.day = 1;
                     max.counter.month += 1;
                     max.counter.year += 1;
                 }
                 DateCompare::SameYear => {
-                    max.date = key.clone();
+                    max.date = *key;
                     max.counter.day = 1;
                     max.counter.month = 1;
                     max.counter.year += 1;
                 }
                 DateCompare::DifferentYear => {
-                    max.date = key.clone();
+                    max.date = *key;
                     max.counter = Counter {
                         year: 1,
                         month: 1,
@@ -268,12 +275,14 @@ fn main() {
         println!(
             "date item at {year} is {}",
             tree.descend_visit(CountForYear::new(year))
+                .unwrap()
                 .unwrap_or_default()
         );
     }
 
     assert_eq!(
         tree.descend_visit(CountForYear::new(2011))
+            .unwrap()
             .unwrap_or_default(),
         365
     );
@@ -285,6 +294,7 @@ fn main() {
     }
     assert_eq!(
         tree.descend_visit(CountForYear::new(2011))
+            .unwrap()
             .unwrap_or_default(),
         362
     );
@@ -292,6 +302,8 @@ fn main() {
 
 /// a function check whether a year is leap
 /// https://en.wikipedia.org/wiki/Leap_year#Algorithm
+// `is_multiple_of` is newer than the toolchains this crate supports
+#[allow(clippy::manual_is_multiple_of)]
 fn is_leap_year(year: usize) -> bool {
     if year % 4 != 0 {
         return false;
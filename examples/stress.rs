@@ -80,7 +80,7 @@ fn find_all_btree(tree: &BTreeMap<StringWrapper, Value>, keys: &[StringWrapper])
 
 #[inline(never)]
 fn delete_btree(tree: &mut BTreeMap<StringWrapper, Value>) {
-    let mut keys = tree.iter().map(|(k, _)| k).cloned().collect::<Vec<_>>();
+    let mut keys = tree.keys().cloned().collect::<Vec<_>>();
     let mut r = StdRng::seed_from_u64(RAND_SEED);
     keys.shuffle(&mut r);
 
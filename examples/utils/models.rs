@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
 
+// not every example using this module constructs a Point
+#[allow(dead_code)]
 #[derive(Clone, Copy, Debug)]
 pub struct Point {
     pub x: f64,
@@ -13,6 +15,8 @@ impl PartialEq for Point {
 }
 impl Eq for Point {}
 
+// keeps partial_cmp returning None for NaN coordinates
+#[allow(clippy::non_canonical_partial_ord_impl)]
 impl PartialOrd for Point {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match self.x.partial_cmp(&other.x) {
@@ -1,31 +1,37 @@
-use sweep_bptree::{tree::visit::DescendVisit, BPlusTreeMap};
+use std::convert::Infallible;
 
-/// This is a dummy visitor that counts the depth of the tree
+use sweep_bptree::{
+    tree::visit::{DescendVisit, DescendVisitResult, VisitContext},
+    BPlusTreeMap,
+};
+
+/// This is a dummy visitor that returns the depth of the tree
 #[derive(Default)]
-struct GetTreeDepth {
-    depth: usize,
-}
+struct GetTreeDepth;
 
 impl<K, V, A> DescendVisit<K, V, A> for GetTreeDepth {
     type Result = usize;
+    type Error = Infallible;
 
     fn visit_inner(
         &mut self,
         _keys: &[K],
         _augmentations: &[A],
-    ) -> sweep_bptree::tree::visit::DescendVisitResult<Self::Result> {
-        self.depth += 1;
-
+        _ctx: VisitContext<'_, K>,
+    ) -> Result<DescendVisitResult<Self::Result>, Infallible> {
         // always down to first child, b+tree is balanced, so it doesn't matter
         // which child we choose
-        sweep_bptree::tree::visit::DescendVisitResult::GoDown(0)
+        Ok(DescendVisitResult::GoDown(0))
     }
 
-    fn visit_leaf(&mut self, _keys: &[K], _values: &[V]) -> Option<Self::Result> {
-        self.depth += 1;
-
-        // leaf is the last layer, return the depth
-        Some(self.depth)
+    fn visit_leaf(
+        &mut self,
+        _keys: &[K],
+        _values: &[V],
+        ctx: VisitContext<'_, K>,
+    ) -> Result<Option<Self::Result>, Infallible> {
+        // leaf is the last layer, its depth starts from 0
+        Ok(Some(ctx.depth + 1))
     }
 }
 
@@ -37,5 +43,5 @@ fn main() {
     println!("tree size: {}", tree.len());
 
     // ceil(log(10000, 64)) == 3
-    assert_eq!(tree.descend_visit(GetTreeDepth::default()), Some(3));
+    assert_eq!(tree.descend_visit(GetTreeDepth), Ok(Some(3)));
 }
@@ -1,10 +1,10 @@
 use crate::Key;
 
-use super::{Augmentation, RankAugmentation, SearchAugmentation};
+use super::{Monoid, RankAugmentation, SearchAugmentation};
 
 /// This augmentation keeps track of the number of elements in the child.
 /// Basicly, it turns the tree to [Order Statistic Tree](https://en.wikipedia.org/wiki/Order_statistic_tree)
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Count(usize);
 
 impl Count {
@@ -14,12 +14,26 @@ impl Count {
     }
 }
 
-impl<K: Key> Augmentation<K> for Count {
-    fn from_leaf(keys: &[K]) -> Self {
+impl<K: Key> Monoid<K> for Count {
+    const COMMUTATIVE: bool = true;
+
+    fn identity() -> Self {
+        Self(0)
+    }
+
+    fn lift(_key: &K) -> Self {
+        Self(1)
+    }
+
+    fn combine(left: &Self, right: &Self) -> Self {
+        Self(left.0 + right.0)
+    }
+
+    fn lift_all(keys: &[K]) -> Self {
         Self(keys.len())
     }
 
-    fn from_inner(_keys: &[K], counts: &[Self]) -> Self {
+    fn combine_all(counts: &[Self]) -> Self {
         Self(counts.iter().map(|a| a.0).sum())
     }
 }
@@ -82,7 +96,7 @@ impl<K: Key> RankAugmentation<K> for Count {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{BPlusTree, NodeStoreVec};
+    use crate::{augment::Augmentation, BPlusTree, NodeStoreVec};
 
     #[test]
     fn test_element_count() {
@@ -2,15 +2,16 @@ use std::{borrow::Cow, cmp::Ordering};
 
 use crate::Key;
 
-use super::{Augmentation, RankAugmentation, SearchAugmentation};
+use super::{Monoid, RankAugmentation, SearchAugmentation};
 
 /// Augmentation to count the number of groups in a set of keys
 /// Note, the group must be ordered
 /// This Augmentation basically provides two capabilities:
 /// 1. Get the group count
 /// 2. Query inside group by offset
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum GroupCount<G> {
+    #[default]
     Zero,
     One(G, usize),
     Multiple {
@@ -21,12 +22,6 @@ pub enum GroupCount<G> {
     },
 }
 
-impl<G: Clone + Ord> Default for GroupCount<G> {
-    fn default() -> Self {
-        Self::Zero
-    }
-}
-
 impl<G: Clone + Ord> GroupCount<G> {
     pub fn is_zero(&self) -> bool {
         matches!(self, GroupCount::Zero)
@@ -148,12 +143,26 @@ pub trait FromRef<T> {
     fn from_ref(input: &T) -> Self;
 }
 
-impl<K, G> Augmentation<K> for GroupCount<G>
+impl<K, G> Monoid<K> for GroupCount<G>
 where
     K: Key,
     G: FromRef<K> + Clone + Ord + std::fmt::Debug,
 {
-    fn from_leaf(keys: &[K]) -> Self {
+    fn identity() -> Self {
+        Self::Zero
+    }
+
+    fn lift(key: &K) -> Self {
+        Self::One(G::from_ref(key), 1)
+    }
+
+    fn combine(left: &Self, right: &Self) -> Self {
+        let mut merged = left.clone();
+        merged.merge_with(right);
+        merged
+    }
+
+    fn lift_all(keys: &[K]) -> Self {
         let mut keys_iter = keys.iter();
 
         let first_group = G::from_ref(match keys_iter.next() {
@@ -192,7 +201,7 @@ where
         }
     }
 
-    fn from_inner(_keys: &[K], group_counts: &[Self]) -> Self {
+    fn combine_all(group_counts: &[Self]) -> Self {
         if group_counts.is_empty() {
             return Self::Zero;
         }
@@ -361,7 +370,9 @@ pub use visit::ExtractGroupSize;
 
 mod visit {
     use super::*;
-    use crate::tree::visit::{DescendVisit, DescendVisitResult};
+    use std::convert::Infallible;
+
+    use crate::tree::visit::{DescendVisit, DescendVisitResult, VisitContext};
 
     /// This visit returns element count for one group.
     pub struct ExtractGroupSize<G, K> {
@@ -385,12 +396,14 @@ mod visit {
     {
         /// The group's total count
         type Result = usize;
+        type Error = Infallible;
 
         fn visit_inner(
             &mut self,
             _keys: &[K],
             group_counts: &[GroupCount<G>],
-        ) -> crate::tree::visit::DescendVisitResult<usize> {
+            _ctx: VisitContext<'_, K>,
+        ) -> Result<DescendVisitResult<usize>, Infallible> {
             let mut child_idx = 0;
             let mut prev_group_count = 0;
             for (idx, a) in group_counts.iter().enumerate() {
@@ -409,14 +422,19 @@ mod visit {
                             break;
                         }
                     },
-                    None => return DescendVisitResult::Cancel,
+                    None => return Ok(DescendVisitResult::Cancel),
                 }
             }
 
-            crate::tree::visit::DescendVisitResult::GoDown(child_idx)
+            Ok(DescendVisitResult::GoDown(child_idx))
         }
 
-        fn visit_leaf(&mut self, keys: &[K], _values: &[V]) -> Option<Self::Result> {
+        fn visit_leaf(
+            &mut self,
+            keys: &[K],
+            _values: &[V],
+            _ctx: VisitContext<'_, K>,
+        ) -> Result<Option<Self::Result>, Infallible> {
             for k in keys {
                 match G::from_ref(k).cmp(&self.group) {
                     Ordering::Less => continue,
@@ -425,7 +443,7 @@ mod visit {
                 }
             }
 
-            Some(self.element_count)
+            Ok(Some(self.element_count))
         }
     }
 }
@@ -468,7 +486,7 @@ mod tests {
         // get group size for Tuple2(1)
         assert_eq!(
             tree.descend_visit(ExtractGroupSize::new(Tuple2(1))),
-            Some(2)
+            Ok(Some(2))
         );
 
         // get (k, v) by (group, offset)
@@ -547,18 +565,18 @@ mod tests {
 
         assert_eq!(
             tree.descend_visit(ExtractGroupSize::new(Tuple2(0))),
-            Some(500)
+            Ok(Some(500))
         );
         assert_eq!(
             tree.descend_visit(ExtractGroupSize::new(Tuple2(1))),
-            Some(500)
+            Ok(Some(500))
         );
         assert_eq!(
             tree.descend_visit(ExtractGroupSize::new(Tuple2(2))),
-            Some(50)
+            Ok(Some(50))
         );
 
-        let mut keys = tree.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>();
+        let mut keys = tree.iter().map(|(k, _)| *k).collect::<Vec<_>>();
         keys.shuffle(&mut rand::thread_rng());
 
         // test remove
@@ -566,9 +584,13 @@ mod tests {
             let g = Tuple2::from_ref(&k);
             let prev_count = tree
                 .descend_visit(ExtractGroupSize::new(g.clone()))
+                .unwrap()
                 .unwrap();
             assert!(tree.remove(&k).is_some());
-            let new_count = tree.descend_visit(ExtractGroupSize::new(g)).unwrap();
+            let new_count = tree
+                .descend_visit(ExtractGroupSize::new(g))
+                .unwrap()
+                .unwrap();
             assert_eq!(new_count + 1, prev_count);
         }
     }
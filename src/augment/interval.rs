@@ -0,0 +1,299 @@
+use std::ops::{Bound, Range};
+
+use crate::tree::{InnerNodeId, LeafNode, NodeId};
+use crate::{BPlusTree, Key, NodeStore};
+
+use super::{group::FromRef, Monoid};
+
+/// This augmentation keeps track of the max interval end in the child, which turns the tree
+/// into an interval tree. Intervals are stored in keys, `E` projects the end from key.
+/// Intervals are half open, and keys must be ordered by interval's start first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalMax<E>(Option<E>);
+
+impl<E> Default for IntervalMax<E> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<E> IntervalMax<E> {
+    /// Get the max end, None for empty tree
+    pub fn max_end(&self) -> Option<&E> {
+        self.0.as_ref()
+    }
+}
+
+impl<K: Key, E: FromRef<K> + Ord + Clone> Monoid<K> for IntervalMax<E> {
+    const COMMUTATIVE: bool = true;
+
+    fn identity() -> Self {
+        Self(None)
+    }
+
+    fn lift(key: &K) -> Self {
+        Self(Some(E::from_ref(key)))
+    }
+
+    fn combine(left: &Self, right: &Self) -> Self {
+        Self(left.0.as_ref().max(right.0.as_ref()).cloned())
+    }
+
+    fn lift_all(keys: &[K]) -> Self {
+        Self(keys.iter().map(E::from_ref).max())
+    }
+}
+
+/// Point of intervals stored in key. `FromRef` projects the interval's end,
+/// and `start` projects the interval's start.
+pub trait IntervalPoint<K>: FromRef<K> + Ord + Clone {
+    fn start(key: &K) -> Self;
+}
+
+/// Interval point for `(start, end)` keys
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Endpoint<T>(pub T);
+
+impl<T: Clone> FromRef<(T, T)> for Endpoint<T> {
+    fn from_ref(input: &(T, T)) -> Self {
+        Endpoint(input.1.clone())
+    }
+}
+
+impl<T: Clone + Ord> IntervalPoint<(T, T)> for Endpoint<T> {
+    fn start(key: &(T, T)) -> Self {
+        Endpoint(key.0.clone())
+    }
+}
+
+/// Iterator over intervals intersecting a query, created by `BPlusTree::stab` or
+/// `BPlusTree::overlapping`. Subtrees whose max end is not after the query's start are
+/// skipped, and iteration stops at the first interval starting after the query.
+pub struct IntervalIter<'a, S: NodeStore, E> {
+    tree: &'a BPlusTree<S>,
+    /// intervals must end after this point
+    after: E,
+    /// intervals must start before this bound
+    before: Bound<E>,
+    /// inner nodes being visited, with the next child index to visit
+    stack: Vec<(InnerNodeId, usize)>,
+    leaf: Option<(&'a LeafNode<S::K, S::V>, usize)>,
+}
+
+impl<'a, S, E> IntervalIter<'a, S, E>
+where
+    S: NodeStore<Augmentation = IntervalMax<E>>,
+    E: IntervalPoint<S::K>,
+{
+    fn new(tree: &'a BPlusTree<S>, after: E, before: Bound<E>) -> Self {
+        let mut me = Self::empty(tree, after, before);
+        if me.ends_after(tree.root_augmentation()) {
+            me.enter(tree.root_id());
+        }
+        me
+    }
+
+    /// An iterator yields nothing
+    fn empty(tree: &'a BPlusTree<S>, after: E, before: Bound<E>) -> Self {
+        Self {
+            tree,
+            after,
+            before,
+            stack: Vec::new(),
+            leaf: None,
+        }
+    }
+
+    fn ends_after(&self, augmentation: &IntervalMax<E>) -> bool {
+        matches!(augmentation.max_end(), Some(end) if *end > self.after)
+    }
+
+    fn starts_before(&self, key: &S::K) -> bool {
+        match &self.before {
+            Bound::Included(p) => E::start(key) <= *p,
+            Bound::Excluded(p) => E::start(key) < *p,
+            Bound::Unbounded => true,
+        }
+    }
+
+    fn enter(&mut self, node_id: NodeId) {
+        match node_id {
+            NodeId::Inner(id) => self.stack.push((id, 0)),
+            NodeId::Leaf(id) => self.leaf = Some((self.tree.node_store().get_leaf(id), 0)),
+        }
+    }
+
+    fn finish(&mut self) {
+        self.stack.clear();
+        self.leaf = None;
+    }
+}
+
+impl<'a, S, E> Iterator for IntervalIter<'a, S, E>
+where
+    S: NodeStore<Augmentation = IntervalMax<E>>,
+    E: IntervalPoint<S::K>,
+{
+    type Item = (&'a S::K, &'a S::V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((leaf, offset)) = self.leaf.take() {
+                for idx in offset..leaf.len() {
+                    let (k, v) = leaf.data_at(idx);
+                    if !self.starts_before(k) {
+                        self.finish();
+                        return None;
+                    }
+                    if E::from_ref(k) > self.after {
+                        self.leaf = Some((leaf, idx + 1));
+                        return Some((k, v));
+                    }
+                }
+            }
+
+            let (id, child_idx) = self.stack.last_mut()?;
+            let inner = self.tree.node_store().get_inner(*id);
+            let child_idx = std::mem::replace(child_idx, *child_idx + 1);
+            if child_idx > inner.len() {
+                self.stack.pop();
+                continue;
+            }
+
+            // all keys in child are not less than the slot key
+            if child_idx > 0 && !self.starts_before(inner.key(child_idx - 1)) {
+                self.finish();
+                return None;
+            }
+
+            if self.ends_after(&inner.augmentations()[child_idx]) {
+                self.enter(inner.child_id(child_idx));
+            }
+        }
+    }
+}
+
+impl<S, E> BPlusTree<S>
+where
+    S: NodeStore<Augmentation = IntervalMax<E>>,
+    E: IntervalPoint<S::K>,
+{
+    /// Iterate intervals containing point `p`, in key order
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::interval::{Endpoint, IntervalMax};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<(u32, u32), &str, IntervalMax<Endpoint<u32>>>::new());
+    /// tree.insert((1, 5), "a");
+    /// tree.insert((2, 3), "b");
+    /// tree.insert((4, 8), "c");
+    ///
+    /// let values = tree.stab(Endpoint(4)).map(|(_, v)| *v).collect::<Vec<_>>();
+    /// assert_eq!(values, vec!["a", "c"]);
+    /// ```
+    pub fn stab(&self, p: E) -> IntervalIter<'_, S, E> {
+        IntervalIter::new(self, p.clone(), Bound::Included(p))
+    }
+
+    /// Iterate intervals overlapping `range`, in key order. An empty `range` overlaps nothing.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::interval::{Endpoint, IntervalMax};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<(u32, u32), &str, IntervalMax<Endpoint<u32>>>::new());
+    /// tree.insert((1, 5), "a");
+    /// tree.insert((2, 3), "b");
+    /// tree.insert((4, 8), "c");
+    ///
+    /// let values = tree
+    ///     .overlapping(Endpoint(3)..Endpoint(4))
+    ///     .map(|(_, v)| *v)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(values, vec!["a"]);
+    /// assert_eq!(tree.overlapping(Endpoint(4)..Endpoint(4)).count(), 0);
+    /// ```
+    pub fn overlapping(&self, range: Range<E>) -> IntervalIter<'_, S, E> {
+        if range.start >= range.end {
+            return IntervalIter::empty(self, range.start, Bound::Excluded(range.end));
+        }
+        IntervalIter::new(self, range.start, Bound::Excluded(range.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::NodeStoreVec;
+
+    type Tree = BPlusTree<NodeStoreVec<(u32, u32), usize, IntervalMax<Endpoint<u32>>>>;
+
+    #[test]
+    fn test_interval_max() {
+        let mut tree = Tree::new(NodeStoreVec::new());
+        assert!(tree.root_augmentation().max_end().is_none());
+
+        tree.insert((5, 10), 0);
+        tree.insert((1, 30), 1);
+        tree.insert((7, 8), 2);
+        assert_eq!(tree.root_augmentation().max_end(), Some(&Endpoint(30)));
+
+        tree.remove(&(1, 30));
+        assert_eq!(tree.root_augmentation().max_end(), Some(&Endpoint(10)));
+    }
+
+    #[test]
+    fn test_stab_and_overlapping() {
+        let mut rng = rand::thread_rng();
+        let mut tree = Tree::new(NodeStoreVec::new());
+        let mut intervals = vec![];
+        for i in 0..5000 {
+            let start = rng.gen_range(0..10000);
+            // mostly short intervals, with some long ones
+            let len = if i % 100 == 0 {
+                rng.gen_range(0..5000)
+            } else {
+                rng.gen_range(0..50)
+            };
+            let interval = (start, start + len);
+            if tree.insert(interval, i).is_none() {
+                intervals.push(interval);
+            }
+        }
+        intervals.sort();
+
+        for p in (0..15000).step_by(37) {
+            let expected = intervals
+                .iter()
+                .filter(|(s, e)| *s <= p && p < *e)
+                .collect::<Vec<_>>();
+            let stabbed = tree.stab(Endpoint(p)).map(|(k, _)| k).collect::<Vec<_>>();
+            assert_eq!(stabbed, expected);
+        }
+
+        for a in (0..15000).step_by(113) {
+            let b = a + rng.gen_range(0..100);
+            let expected = intervals
+                .iter()
+                .filter(|(s, e)| a < b && *s < b && a < *e)
+                .collect::<Vec<_>>();
+            let overlapped = tree
+                .overlapping(Endpoint(a)..Endpoint(b))
+                .map(|(k, _)| k)
+                .collect::<Vec<_>>();
+            assert_eq!(overlapped, expected);
+        }
+
+        // empty and reversed ranges overlap nothing
+        let mut tree = Tree::new(NodeStoreVec::new());
+        tree.insert((1, 10), 0);
+        assert_eq!(tree.overlapping(Endpoint(5)..Endpoint(5)).count(), 0);
+        assert_eq!(tree.overlapping(Endpoint(5)..Endpoint(3)).count(), 0);
+        assert_eq!(tree.overlapping(Endpoint(5)..Endpoint(6)).count(), 1);
+    }
+}
@@ -2,6 +2,8 @@ use crate::Key;
 
 pub mod count;
 pub mod group;
+pub mod interval;
+pub mod weighted;
 
 /// Augmentation trait, it is used to store augmentation, like 'size'
 /// NOTE: Since the lib has no control on how value changes, so augment only calculated from keys
@@ -22,6 +24,22 @@ pub trait Augmentation<K: Key>: Clone + Default {
 
     /// create a new Augmentation from leaf node's key
     fn from_leaf(keys: &[K]) -> Self;
+
+    /// Opt in to incremental updates on insert, returns the augmentation of `key` alone.
+    /// When it returns `Some`, inserting `key` merges it into the augmentation of every node
+    /// on the insert path with `apply_insert`, instead of recomputing them from children.
+    /// Only valid if the augmentation depends on the set of keys in the subtree, not on
+    /// their order or the tree shape.
+    fn insert_delta(_key: &K) -> Option<Self> {
+        None
+    }
+
+    /// Merge `delta` returned by `insert_delta` into the augmentation of a node whose
+    /// subtree gets the key. Returns false if not merged, then the augmentation is recomputed
+    /// from children, which is what the default does.
+    fn apply_insert(&mut self, _delta: &Self) -> bool {
+        false
+    }
 }
 
 /// Whether the augmentation able to locate element
@@ -65,15 +83,175 @@ pub trait RankAugmentation<K: Key>: Augmentation<K> {
     ) -> Result<Self::Rank, Self::Rank>;
 }
 
-/// () is a dummy augment that turns Augmented tree to a normal tree
-impl<K: Key> Augmentation<K> for () {
+/// Monoid style augmentation: lift each key, then combine left to right.
+///
+/// Most augmentations are a monoid, implementing this trait instead of `Augmentation`
+/// saves folding the slices by hand, `Augmentation` is provided by a blanket impl.
+/// It also enables range aggregation, see `BPlusTree::fold_range`.
+///
+/// `combine` must be associative and `identity` must be its neutral element, the
+/// combine order is always the key order, so `combine` needs not to be commutative.
+/// If it is, set `COMMUTATIVE` so inserts update augmentations incrementally.
+pub trait Monoid<K: Key>: Clone + Default {
+    /// Whether `combine(a, b) == combine(b, a)`. If so, an insert combines the lifted key
+    /// into each node on its path, instead of folding all children of those nodes.
+    const COMMUTATIVE: bool = false;
+
+    /// The neutral element, e.g: 0 for size
+    fn identity() -> Self;
+
+    /// Create augmentation for a single key
+    fn lift(key: &K) -> Self;
+
+    /// Combine `left` and `right`, all keys of `left` are less than `right`'s
+    fn combine(left: &Self, right: &Self) -> Self;
+
+    /// Lift and combine keys in order.
+    /// Override this if there is a faster way than lifting keys one by one
+    fn lift_all(keys: &[K]) -> Self {
+        keys.iter().fold(Self::identity(), |acc, k| {
+            Self::combine(&acc, &Self::lift(k))
+        })
+    }
+
+    /// Combine augmentations in order
+    fn combine_all(augmentations: &[Self]) -> Self {
+        augmentations
+            .iter()
+            .fold(Self::identity(), |acc, a| Self::combine(&acc, a))
+    }
+}
+
+impl<K: Key, M: Monoid<K>> Augmentation<K> for M {
     fn is_zst() -> bool {
+        std::mem::size_of::<M>() == 0
+    }
+
+    #[inline]
+    fn from_inner(_keys: &[K], augmentations: &[Self]) -> Self {
+        M::combine_all(augmentations)
+    }
+
+    #[inline]
+    fn from_leaf(keys: &[K]) -> Self {
+        M::lift_all(keys)
+    }
+
+    #[inline]
+    fn insert_delta(key: &K) -> Option<Self> {
+        M::COMMUTATIVE.then(|| M::lift(key))
+    }
+
+    #[inline]
+    fn apply_insert(&mut self, delta: &Self) -> bool {
+        *self = M::combine(self, delta);
         true
     }
+}
+
+/// () is a dummy augment that turns Augmented tree to a normal tree
+impl<K: Key> Monoid<K> for () {
+    const COMMUTATIVE: bool = true;
 
     #[inline(always)]
-    fn from_leaf(_: &[K]) -> Self {}
+    fn identity() -> Self {}
 
     #[inline(always)]
-    fn from_inner(_: &[K], _: &[Self]) -> Self {}
+    fn lift(_: &K) -> Self {}
+
+    #[inline(always)]
+    fn combine(_: &Self, _: &Self) -> Self {}
+
+    #[inline(always)]
+    fn lift_all(_: &[K]) -> Self {}
+
+    #[inline(always)]
+    fn combine_all(_: &[Self]) -> Self {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::{BPlusTree, NodeStoreVec};
+
+    thread_local! {
+        static LIFTS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Sum(u64);
+
+    impl Monoid<u64> for Sum {
+        const COMMUTATIVE: bool = true;
+
+        fn identity() -> Self {
+            Self(0)
+        }
+
+        fn lift(key: &u64) -> Self {
+            LIFTS.with(|l| l.set(l.get() + 1));
+            Self(*key)
+        }
+
+        fn combine(left: &Self, right: &Self) -> Self {
+            Self(left.0 + right.0)
+        }
+    }
+
+    #[test]
+    fn test_monoid_incremental_insert() {
+        let mut keys = (0..10_000).collect::<Vec<u64>>();
+        keys.shuffle(&mut rand::thread_rng());
+
+        let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64, Sum>::new());
+        for k in keys.iter() {
+            tree.insert(*k, *k);
+        }
+        // updates don't change keys
+        for k in keys.iter().take(1000) {
+            tree.insert(*k, 0);
+        }
+        // one lift per insert, plus recomputing split leaves, instead of a whole leaf per
+        // insert
+        let lifts = LIFTS.with(|l| l.get());
+        assert!(lifts < keys.len() * 4, "{lifts}");
+
+        tree.check_invariants().unwrap();
+        assert_eq!(tree.root_augmentation().0, keys.iter().sum::<u64>());
+    }
+
+    /// Opts in with `insert_delta` only, so inserts fall back to the default `apply_insert`
+    #[derive(Clone, Debug, Default)]
+    struct Max(Option<u64>);
+
+    impl Augmentation<u64> for Max {
+        fn from_inner(_keys: &[u64], augmentations: &[Self]) -> Self {
+            Self(augmentations.iter().filter_map(|a| a.0).max())
+        }
+
+        fn from_leaf(keys: &[u64]) -> Self {
+            Self(keys.iter().copied().max())
+        }
+
+        fn insert_delta(key: &u64) -> Option<Self> {
+            Some(Self(Some(*key)))
+        }
+    }
+
+    #[test]
+    fn test_default_apply_insert() {
+        let mut keys = (0..10_000).collect::<Vec<u64>>();
+        keys.shuffle(&mut rand::thread_rng());
+
+        let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64, Max>::new());
+        for k in keys.iter() {
+            tree.insert(*k, *k);
+        }
+
+        assert_eq!(tree.root_augmentation().0, Some(9_999));
+    }
 }
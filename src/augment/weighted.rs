@@ -0,0 +1,169 @@
+use crate::Key;
+
+use super::{Monoid, SearchAugmentation};
+
+/// Key with a weight, used by `WeightedSum`
+pub trait Weighted {
+    fn weight(&self) -> u64;
+}
+
+/// This augmentation keeps track of the total weight of the elements in the child.
+/// It can be searched by offset inside the total weight, which gives weighted sampling.
+///
+/// # Panics
+/// Panics if the total weight of the tree overflows `u64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeightedSum(u64);
+
+impl WeightedSum {
+    /// Get the total weight
+    pub fn sum(&self) -> u64 {
+        self.0
+    }
+}
+
+impl<K: Key + Weighted> Monoid<K> for WeightedSum {
+    const COMMUTATIVE: bool = true;
+
+    fn identity() -> Self {
+        Self(0)
+    }
+
+    fn lift(key: &K) -> Self {
+        Self(key.weight())
+    }
+
+    fn combine(left: &Self, right: &Self) -> Self {
+        Self(checked_sum(left.0, right.0))
+    }
+
+    fn combine_all(sums: &[Self]) -> Self {
+        Self(sums.iter().fold(0, |acc, a| checked_sum(acc, a.0)))
+    }
+}
+
+/// Adds weights, a wrapped sum would silently skew sampling
+fn checked_sum(a: u64, b: u64) -> u64 {
+    a.checked_add(b).expect("total weight overflows u64")
+}
+
+impl<K: Key + Weighted> SearchAugmentation<K> for WeightedSum {
+    /// Query is the offset inside total weight, the element whose weight range
+    /// covers the offset is located. Elements with zero weight are never located.
+    type Query = u64;
+
+    fn locate_in_leaf(mut offset: u64, keys: &[K]) -> Option<usize> {
+        for (idx, k) in keys.iter().enumerate() {
+            let weight = k.weight();
+            if offset < weight {
+                return Some(idx);
+            }
+            offset -= weight;
+        }
+
+        None
+    }
+
+    fn locate_in_inner(mut offset: u64, _keys: &[K], sums: &[Self]) -> Option<(usize, u64)> {
+        for (idx, a) in sums.iter().enumerate() {
+            if offset < a.0 {
+                return Some((idx, offset));
+            }
+            offset -= a.0;
+        }
+
+        // offset is larger than the total weight
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BPlusTreeMap;
+
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Server {
+        id: u32,
+        capacity: u64,
+    }
+
+    impl Weighted for Server {
+        fn weight(&self) -> u64 {
+            self.capacity
+        }
+    }
+
+    fn server(id: u32) -> Server {
+        Server {
+            id,
+            capacity: (id % 4) as u64,
+        }
+    }
+
+    #[test]
+    fn test_weighted_sum() {
+        let mut map = BPlusTreeMap::<Server, (), WeightedSum>::new();
+        for i in 0..1000 {
+            map.insert(server(i), ());
+        }
+
+        let total = (0..1000).map(|i| (i % 4) as u64).sum::<u64>();
+        assert_eq!(map.root_augmentation().sum(), total);
+
+        // walk all offsets, each server is located `capacity` times in order
+        let mut expected = (0..1000).flat_map(|i| std::iter::repeat(i).take(i as usize % 4));
+        for offset in 0..total {
+            let (s, _) = map.get_by_augmentation(offset).unwrap();
+            assert_eq!(s.id, expected.next().unwrap());
+        }
+        assert!(map.get_by_augmentation(total).is_none());
+
+        for i in (0..1000).step_by(2) {
+            map.remove(&server(i));
+        }
+        let total = (0..1000)
+            .filter(|i| i % 2 == 1)
+            .map(|i| (i % 4) as u64)
+            .sum::<u64>();
+        assert_eq!(map.root_augmentation().sum(), total);
+    }
+
+    #[test]
+    #[should_panic(expected = "total weight overflows u64")]
+    fn test_weighted_sum_overflow() {
+        let mut map = BPlusTreeMap::<Server, (), WeightedSum>::new();
+        for id in 0..3 {
+            map.insert(
+                Server {
+                    id,
+                    capacity: u64::MAX / 2,
+                },
+                (),
+            );
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_weighted() {
+        let mut map = BPlusTreeMap::<Server, (), WeightedSum>::new();
+        let mut rng = rand::thread_rng();
+        assert!(map.sample_weighted(&mut rng).is_none());
+
+        for i in 0..100 {
+            map.insert(server(i), ());
+        }
+
+        let mut hits = [0usize; 4];
+        for _ in 0..6000 {
+            let (s, _) = map.sample_weighted(&mut rng).unwrap();
+            hits[s.capacity as usize] += 1;
+        }
+
+        // zero weight is never sampled, others are proportional to capacity
+        assert_eq!(hits[0], 0);
+        assert!(hits[1] > 600 && hits[1] < 1400);
+        assert!(hits[3] > 2400 && hits[3] < 3600);
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    borrow::Borrow,
+    sync::{Arc, Mutex},
+};
+
+use arc_swap::ArcSwap;
+
+use crate::{tree::Augmentation, BPlusTree, Key, NodeStoreArc};
+
+type Tree<K, V, A> = BPlusTree<NodeStoreArc<K, V, A>>;
+
+/// A B+ tree map for concurrent readers and a single writer at a time.
+///
+/// The map is built on `NodeStoreArc`. Writers are serialized by a lock, and each write
+/// publishes an O(1) snapshot of the tree, readers load the latest published snapshot
+/// without locking, so they never block or get blocked by the writer.
+///
+/// Readers see each write as a whole, a snapshot obtained by `snapshot` stays unchanged
+/// while writes continue.
+///
+/// Available with the `concurrent` feature.
+///
+/// # Example
+/// ```rust
+/// use std::sync::Arc;
+/// use sweep_bptree::ConcurrentBPlusTreeMap;
+///
+/// let map = Arc::new(ConcurrentBPlusTreeMap::<u64, u64>::new());
+///
+/// let writer = {
+///     let map = map.clone();
+///     std::thread::spawn(move || {
+///         for i in 0..1000 {
+///             map.insert(i, i * 2);
+///         }
+///     })
+/// };
+///
+/// // readers are not blocked by the writer
+/// let snapshot = map.snapshot();
+/// assert!(snapshot.len() <= 1000);
+///
+/// writer.join().unwrap();
+/// assert_eq!(map.get(&10), Some(20));
+/// assert_eq!(map.snapshot().range(10..13).count(), 3);
+/// ```
+pub struct ConcurrentBPlusTreeMap<K: Key, V: Clone, A: Augmentation<K> = ()> {
+    published: ArcSwap<Tree<K, V, A>>,
+    writer: Mutex<Tree<K, V, A>>,
+}
+
+impl<K: Key, V: Clone, A: Augmentation<K>> Default for ConcurrentBPlusTreeMap<K, V, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Key, V: Clone, A: Augmentation<K>> ConcurrentBPlusTreeMap<K, V, A> {
+    /// Create a new ConcurrentBPlusTreeMap
+    pub fn new() -> Self {
+        let tree = BPlusTree::new(NodeStoreArc::new());
+        Self {
+            published: ArcSwap::from_pointee(tree.snapshot()),
+            writer: Mutex::new(tree),
+        }
+    }
+
+    /// Returns the latest published snapshot, lock free.
+    /// The snapshot provides all read methods of `BPlusTree`, e.g. `get`, `range` and
+    /// `root_augmentation`, and is not affected by later writes.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::ConcurrentBPlusTreeMap;
+    ///
+    /// let map = ConcurrentBPlusTreeMap::<u64, u64>::new();
+    /// map.insert(1, 1);
+    ///
+    /// let snapshot = map.snapshot();
+    /// map.insert(2, 2);
+    ///
+    /// assert_eq!(snapshot.range(..).count(), 1);
+    /// assert_eq!(map.snapshot().range(..).count(), 2);
+    /// ```
+    pub fn snapshot(&self) -> Arc<Tree<K, V, A>> {
+        self.published.load_full()
+    }
+
+    /// Returns item count of the latest published snapshot
+    pub fn len(&self) -> usize {
+        self.published.load().len()
+    }
+
+    /// Returns true if the latest published snapshot contains no item
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a copy of the value for `key` in the latest published snapshot, lock free
+    pub fn get<Q: ?Sized + Ord>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.published.load().get(key).cloned()
+    }
+
+    /// Returns a copy of the root augmentation of the latest published snapshot, lock free
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::ConcurrentBPlusTreeMap;
+    /// use sweep_bptree::augment::count::Count;
+    ///
+    /// let map = ConcurrentBPlusTreeMap::<u64, u64, Count>::new();
+    /// map.insert(1, 1);
+    /// map.insert(2, 2);
+    ///
+    /// assert_eq!(map.root_augmentation().count(), 2);
+    /// ```
+    pub fn root_augmentation(&self) -> A {
+        self.published.load().root_augmentation().clone()
+    }
+
+    /// Insert a key-value pair and publish the result
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.write(|tree| tree.insert(key, value))
+    }
+
+    /// Remove a key and publish the result
+    pub fn remove<Q: ?Sized + Ord>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.write(|tree| tree.remove(key))
+    }
+
+    /// Run `f` with exclusive access to the writer's tree, then publish the result.
+    /// Readers see either none or all of the updates made by `f`.
+    ///
+    /// If `f` panics, nothing is published and the writer's tree is restored to the latest
+    /// published snapshot.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::ConcurrentBPlusTreeMap;
+    ///
+    /// let map = ConcurrentBPlusTreeMap::<u64, u64>::new();
+    /// map.write(|tree| {
+    ///     for i in 0..100 {
+    ///         tree.insert(i, i);
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(map.len(), 100);
+    /// ```
+    pub fn write<R>(&self, f: impl FnOnce(&mut Tree<K, V, A>) -> R) -> R {
+        let mut tree = match self.writer.lock() {
+            Ok(tree) => tree,
+            Err(poisoned) => {
+                // previous writer panicked, its partial updates were never published
+                let mut tree = poisoned.into_inner();
+                *tree = self.published.load().snapshot();
+                self.writer.clear_poison();
+                tree
+            }
+        };
+
+        let r = f(&mut tree);
+        self.published.store(Arc::new(tree.snapshot()));
+        r
+    }
+}
+
+/// ensure ConcurrentBPlusTreeMap is sync for send + sync k v
+fn _ensure_sync<K: Key + Send + Sync, V: Clone + Send + Sync>() {
+    fn _assert_sync<T: Send + Sync>() {}
+    _assert_sync::<ConcurrentBPlusTreeMap<K, V>>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augment::count::Count;
+
+    #[test]
+    fn test_concurrent_readers() {
+        let map = Arc::new(ConcurrentBPlusTreeMap::<u64, u64, Count>::new());
+        let size = 20000;
+
+        let readers = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                std::thread::spawn(move || {
+                    let mut last_len = 0;
+                    while last_len < size as usize {
+                        // keys are inserted in order, so each snapshot holds a prefix
+                        let snapshot = map.snapshot();
+                        let len = snapshot.len();
+                        assert!(len >= last_len);
+                        assert_eq!(snapshot.root_augmentation().count(), len);
+                        assert!(snapshot.iter().map(|(k, _)| *k).eq(0..len as u64));
+                        last_len = len;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for i in 0..size {
+            map.insert(i, i);
+        }
+
+        for r in readers {
+            r.join().unwrap();
+        }
+        assert_eq!(map.get(&(size - 1)), Some(size - 1));
+    }
+
+    #[test]
+    fn test_write_panic() {
+        let map = ConcurrentBPlusTreeMap::<u64, u64>::new();
+        map.insert(1, 1);
+
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.write(|tree| {
+                tree.insert(2, 2);
+                panic!("abort write");
+            })
+        }));
+        assert!(r.is_err());
+        assert_eq!(map.len(), 1);
+
+        // the partial update is discarded
+        map.insert(3, 3);
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.len(), 2);
+    }
+}
@@ -0,0 +1,509 @@
+use std::{
+    borrow::Borrow,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    tree::{Augmentation, Checksum, Codec, SnapshotError},
+    BPlusTreeMap, Key,
+};
+
+const CHECKPOINT_FILE: &str = "checkpoint";
+const CHECKPOINT_TMP_FILE: &str = "checkpoint.tmp";
+const LOG_FILE: &str = "wal";
+
+const OP_INSERT: u8 = 1;
+const OP_REMOVE: u8 = 2;
+
+/// When to fsync the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// fsync after every operation, no acknowledged operation is lost on crash
+    Always,
+    /// fsync after every n operations, at most n - 1 acknowledged operations are lost on crash
+    EveryN(usize),
+    /// Never fsync, leave it to the OS. Call `sync` to fsync manually
+    Never,
+}
+
+/// Options for `DurableBPlusTreeMap`
+#[derive(Debug, Clone)]
+pub struct DurableOptions {
+    /// fsync policy of the log
+    pub sync: SyncPolicy,
+    /// Checkpoint after this many operations in log, `None` means checkpoint manually
+    pub checkpoint_every: Option<usize>,
+}
+
+impl Default for DurableOptions {
+    fn default() -> Self {
+        Self {
+            sync: SyncPolicy::Always,
+            checkpoint_every: Some(10_000),
+        }
+    }
+}
+
+/// Error of `DurableBPlusTreeMap::insert` and `remove`, it tells whether the operation is
+/// applied, so callers know whether retrying is safe.
+#[derive(Debug)]
+pub enum DurableError<V> {
+    /// The operation is not logged, and the map is not changed. Retrying is safe.
+    NotApplied(io::Error),
+    /// The operation is logged and applied to the map, but the following fsync or
+    /// checkpoint failed, so it may be lost on crash. `previous` is the result of the
+    /// operation. Don't retry, call `sync` or `checkpoint` to make it durable.
+    NotDurable {
+        previous: Option<V>,
+        error: io::Error,
+    },
+}
+
+impl<V> DurableError<V> {
+    /// The underlying io error
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            Self::NotApplied(error) | Self::NotDurable { error, .. } => error,
+        }
+    }
+}
+
+impl<V> fmt::Display for DurableError<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotApplied(e) => write!(f, "operation not applied: {e}"),
+            Self::NotDurable { error, .. } => {
+                write!(f, "operation applied but not durable: {error}")
+            }
+        }
+    }
+}
+
+impl<V: fmt::Debug> std::error::Error for DurableError<V> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+/// A `BPlusTreeMap` survives process crashes, backed by files in a directory.
+///
+/// Each insert and remove is appended to a write-ahead log before it is applied. A checkpoint
+/// writes the whole map in snapshot format and truncates the log, so the log never grows
+/// beyond `DurableOptions::checkpoint_every` operations. On open, the log is replayed onto
+/// the last checkpoint. A torn record at the log's tail, left by a crash in the middle of
+/// a write, is detected by its checksum and discarded. A bad record followed by others
+/// is corruption, and fails the open.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::DurableBPlusTreeMap;
+///
+/// let dir = std::env::temp_dir().join("sweep_bptree_durable_doc_example");
+/// # let _ = std::fs::remove_dir_all(&dir);
+/// {
+///     let mut map = DurableBPlusTreeMap::<u64, String>::open(&dir).unwrap();
+///     map.insert(1, "one".to_string()).unwrap();
+///     map.insert(2, "two".to_string()).unwrap();
+///     map.remove(&1).unwrap();
+/// }
+///
+/// let map = DurableBPlusTreeMap::<u64, String>::open(&dir).unwrap();
+/// assert_eq!(map.len(), 1);
+/// assert_eq!(map.get(&2).unwrap(), "two");
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct DurableBPlusTreeMap<K: Key + Codec, V: Codec, A: Augmentation<K> = ()> {
+    dir: PathBuf,
+    options: DurableOptions,
+    map: BPlusTreeMap<K, V, A>,
+    log: File,
+    /// byte length of valid records in log
+    log_len: u64,
+    /// operations in log since last checkpoint
+    log_ops: usize,
+    /// operations written since last fsync
+    unsynced_ops: usize,
+}
+
+impl<K: Key + Codec, V: Codec, A: Augmentation<K>> DurableBPlusTreeMap<K, V, A> {
+    /// Open or create the map in `dir` with default options
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        Self::open_with(dir, DurableOptions::default())
+    }
+
+    /// Open or create the map in `dir`. The last checkpoint is loaded and the log is replayed
+    /// onto it, a torn tail of the log is truncated.
+    pub fn open_with(
+        dir: impl AsRef<Path>,
+        options: DurableOptions,
+    ) -> Result<Self, SnapshotError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let mut map = match File::open(dir.join(CHECKPOINT_FILE)) {
+            Ok(file) => BPlusTreeMap::read_snapshot(BufReader::new(file))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BPlusTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut log = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(LOG_FILE))?;
+        let mut content = Vec::new();
+        log.read_to_end(&mut content)?;
+
+        let (log_ops, valid_len) = replay(&content, &mut map)?;
+        if valid_len < content.len() {
+            // torn tail, the operation was never acknowledged
+            log.set_len(valid_len as u64)?;
+            log.sync_data()?;
+        }
+
+        Ok(Self {
+            dir,
+            options,
+            map,
+            log,
+            log_len: valid_len as u64,
+            log_ops,
+            unsynced_ops: 0,
+        })
+    }
+
+    /// Returns the map
+    pub fn map(&self) -> &BPlusTreeMap<K, V, A> {
+        &self.map
+    }
+
+    /// Returns item count in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the map contains no item
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns a reference to the value for `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: ?Sized + Ord,
+        K: Borrow<Q>,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns operation count in log since last checkpoint
+    pub fn log_ops(&self) -> usize {
+        self.log_ops
+    }
+
+    /// Insert a key-value pair. The operation is logged before it is applied, see
+    /// `DurableError` for whether the map is changed on error.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, DurableError<V>> {
+        let mut record = vec![OP_INSERT];
+        key.encode(&mut record)
+            .and_then(|_| value.encode(&mut record))
+            .and_then(|_| self.append_log(&record))
+            .map_err(DurableError::NotApplied)?;
+
+        let previous = self.map.insert(key, value);
+        self.after_op(previous)
+    }
+
+    /// Remove a key. Nothing is logged if the key doesn't exist.
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>, DurableError<V>> {
+        if self.map.get(key).is_none() {
+            return Ok(None);
+        }
+
+        let mut record = vec![OP_REMOVE];
+        key.encode(&mut record)
+            .and_then(|_| self.append_log(&record))
+            .map_err(DurableError::NotApplied)?;
+
+        let previous = self.map.remove(key);
+        self.after_op(previous)
+    }
+
+    /// fsync the log
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.log.sync_data()?;
+        self.unsynced_ops = 0;
+        Ok(())
+    }
+
+    /// Write the map as a new checkpoint, then truncate the log.
+    ///
+    /// The checkpoint is written to a temp file and renamed, so a crash leaves either the old
+    /// or the new one. If crash happens after rename but before the log is truncated,
+    /// replaying the log onto the new checkpoint gives the same map.
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        let tmp_path = self.dir.join(CHECKPOINT_TMP_FILE);
+        let mut w = BufWriter::new(File::create(&tmp_path)?);
+        self.map.write_snapshot(&mut w)?;
+        let file = w.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(CHECKPOINT_FILE))?;
+        sync_dir(&self.dir)?;
+
+        self.log.set_len(0)?;
+        self.log.sync_all()?;
+        self.log_len = 0;
+        self.log_ops = 0;
+        self.unsynced_ops = 0;
+        Ok(())
+    }
+
+    /// Append a record, layout: body length u32, checksum u64 of body, body
+    fn append_log(&mut self, body: &[u8]) -> io::Result<()> {
+        let mut checksum = Checksum::new();
+        checksum.update(body);
+
+        let mut record = Vec::with_capacity(12 + body.len());
+        (body.len() as u32).encode(&mut record)?;
+        checksum.value().encode(&mut record)?;
+        record.extend_from_slice(body);
+        // single write, so a crash leaves at most one torn record
+        if let Err(e) = self.log.write_all(&record) {
+            // drop the partial record, otherwise it hides following records on replay
+            self.log.set_len(self.log_len)?;
+            return Err(e);
+        }
+        self.log_len += record.len() as u64;
+        Ok(())
+    }
+
+    /// Sync or checkpoint per options after an operation is applied
+    fn after_op(&mut self, previous: Option<V>) -> Result<Option<V>, DurableError<V>> {
+        self.log_ops += 1;
+        self.unsynced_ops += 1;
+
+        let result = match self.options.sync {
+            SyncPolicy::Always => self.sync(),
+            SyncPolicy::EveryN(n) if self.unsynced_ops >= n => self.sync(),
+            _ => Ok(()),
+        }
+        .and_then(|_| match self.options.checkpoint_every {
+            Some(n) if self.log_ops >= n => self.checkpoint(),
+            _ => Ok(()),
+        });
+
+        match result {
+            Ok(()) => Ok(previous),
+            Err(error) => Err(DurableError::NotDurable { previous, error }),
+        }
+    }
+}
+
+/// Replay log records onto `map`, returns the operation count and the length of valid
+/// records. Replay stops at a torn record at the tail, a bad record followed by more data
+/// is corruption.
+fn replay<K: Key + Codec, V: Codec, A: Augmentation<K>>(
+    mut content: &[u8],
+    map: &mut BPlusTreeMap<K, V, A>,
+) -> Result<(usize, usize), SnapshotError> {
+    let total = content.len();
+    let mut ops = 0;
+
+    loop {
+        let valid_len = total - content.len();
+        if content.len() < 12 {
+            return Ok((ops, valid_len));
+        }
+
+        let len = u32::decode(&mut content)? as usize;
+        let expected = u64::decode(&mut content)?;
+        if content.len() < len {
+            return Ok((ops, valid_len));
+        }
+
+        let (mut body, rest) = content.split_at(len);
+        let mut checksum = Checksum::new();
+        checksum.update(body);
+        if checksum.value() != expected {
+            // records are written one at a time, so only the last one can be torn
+            if rest.is_empty() {
+                return Ok((ops, valid_len));
+            }
+            return Err(SnapshotError::Corrupted("log record checksum mismatch"));
+        }
+
+        // checksum matches, so decode failures are real corruption
+        match u8::decode(&mut body)? {
+            OP_INSERT => {
+                let k = K::decode(&mut body)?;
+                let v = V::decode(&mut body)?;
+                map.insert(k, v);
+            }
+            OP_REMOVE => {
+                map.remove(&K::decode(&mut body)?);
+            }
+            _ => return Err(SnapshotError::Corrupted("unknown log operation")),
+        }
+
+        content = rest;
+        ops += 1;
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(checkpoint_every: Option<usize>) -> DurableOptions {
+        DurableOptions {
+            sync: SyncPolicy::Never,
+            checkpoint_every,
+        }
+    }
+
+    #[test]
+    fn test_durable_reopen_with_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut expected = std::collections::BTreeMap::new();
+
+        for round in 0..3u64 {
+            let mut map =
+                DurableBPlusTreeMap::<u64, String>::open_with(dir.path(), options(Some(100)))
+                    .unwrap();
+            assert!(map.map().iter().eq(expected.iter()));
+
+            for i in 0..250 {
+                let k = (i * 7 + round) % 120;
+                if i % 3 == 0 {
+                    assert_eq!(map.remove(&k).unwrap(), expected.remove(&k));
+                } else {
+                    let v = format!("{round}-{i}");
+                    assert_eq!(map.insert(k, v.clone()).unwrap(), expected.insert(k, v));
+                }
+                assert!(map.log_ops() < 100);
+            }
+        }
+
+        let map = DurableBPlusTreeMap::<u64, String>::open(dir.path()).unwrap();
+        assert!(map.map().iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn test_durable_torn_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut map =
+                DurableBPlusTreeMap::<u32, u32>::open_with(dir.path(), options(None)).unwrap();
+            for i in 0..10 {
+                map.insert(i, i).unwrap();
+            }
+            map.sync().unwrap();
+        }
+
+        // crash in the middle of writing the last record
+        let log_path = dir.path().join(LOG_FILE);
+        let len = fs::metadata(&log_path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&log_path)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        {
+            let mut map = DurableBPlusTreeMap::<u32, u32>::open(dir.path()).unwrap();
+            assert_eq!(map.log_ops(), 9);
+            assert!(map.map().iter().map(|(k, _)| *k).eq(0..9));
+            map.insert(100, 100).unwrap();
+        }
+
+        // garbage after valid records
+        let mut log = OpenOptions::new().append(true).open(&log_path).unwrap();
+        log.write_all(&[5, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 1, 2])
+            .unwrap();
+        drop(log);
+
+        let map = DurableBPlusTreeMap::<u32, u32>::open(dir.path()).unwrap();
+        assert_eq!(map.len(), 10);
+        assert_eq!(map.get(&100), Some(&100));
+        assert_eq!(map.log_ops(), 10);
+    }
+
+    #[test]
+    fn test_durable_corrupted_record_in_the_middle() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut map =
+                DurableBPlusTreeMap::<u32, u32>::open_with(dir.path(), options(None)).unwrap();
+            for i in 0..10 {
+                map.insert(i, i).unwrap();
+            }
+        }
+
+        // flip a byte in the first record's body, valid records follow it
+        let log_path = dir.path().join(LOG_FILE);
+        let mut log = fs::read(&log_path).unwrap();
+        log[13] ^= 1;
+        fs::write(&log_path, &log).unwrap();
+
+        assert!(matches!(
+            DurableBPlusTreeMap::<u32, u32>::open(dir.path()),
+            Err(SnapshotError::Corrupted(_))
+        ));
+        // nothing is truncated
+        assert_eq!(fs::read(&log_path).unwrap(), log);
+    }
+
+    #[test]
+    fn test_durable_error_tells_whether_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut map =
+            DurableBPlusTreeMap::<u32, String>::open_with(dir.path(), options(Some(2))).unwrap();
+        map.insert(1, "a".to_string()).unwrap();
+
+        // the checkpoint after the next insert fails
+        fs::create_dir(dir.path().join(CHECKPOINT_TMP_FILE)).unwrap();
+        match map.insert(1, "b".to_string()) {
+            Err(DurableError::NotDurable { previous, .. }) => {
+                assert_eq!(previous.as_deref(), Some("a"))
+            }
+            r => panic!("unexpected {r:?}"),
+        }
+        assert_eq!(map.get(&1).map(String::as_str), Some("b"));
+    }
+
+    #[test]
+    fn test_durable_replay_after_checkpoint_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join(LOG_FILE);
+
+        let mut map =
+            DurableBPlusTreeMap::<u32, u32>::open_with(dir.path(), options(None)).unwrap();
+        map.insert(1, 1).unwrap();
+        map.insert(2, 2).unwrap();
+        map.remove(&1).unwrap();
+        let log = fs::read(&log_path).unwrap();
+
+        // crash after checkpoint renamed, before log truncated
+        map.checkpoint().unwrap();
+        drop(map);
+        fs::write(&log_path, log).unwrap();
+
+        let map = DurableBPlusTreeMap::<u32, u32>::open(dir.path()).unwrap();
+        assert_eq!(map.log_ops(), 3);
+        assert!(map.map().iter().eq([(&2, &2)]));
+    }
+}
@@ -6,10 +6,32 @@ pub use set::*;
 mod map;
 pub use map::*;
 
+mod range_map;
+pub use range_map::*;
+
+#[cfg(feature = "concurrent")]
+mod concurrent;
+#[cfg(feature = "concurrent")]
+pub use concurrent::*;
+
+mod sharded;
+pub use sharded::*;
+
+mod durable;
+pub use durable::*;
+
+mod versioned;
+pub use versioned::*;
+
 pub mod augment;
 
 // core tree impl
 pub mod tree;
-pub use tree::{BPlusTree, Key, NodeStore, NodeStoreVec};
+pub use tree::{BPlusTree, Key, KeySearch, NodeStore, NodeStoreArc, NodeStoreVec};
+#[cfg(feature = "mmap")]
+pub use tree::{MmapBuilder, MmapTree, NodeStoreMmap, Pod};
 
 mod merge_iter;
+
+#[cfg(feature = "serde")]
+mod serde_impl;
@@ -1,11 +1,24 @@
 use std::borrow::Borrow;
 
 use crate::{
-    augment::RankAugmentation,
-    tree::{visit::DescendVisit, Augmentation, SearchAugmentation},
+    augment::{
+        interval::{IntervalIter, IntervalMax, IntervalPoint},
+        Monoid, RankAugmentation,
+    },
+    tree::{
+        visit::{DescendVisit, FoldStep, VisitError},
+        Augmentation, Codec, SearchAugmentation, SnapshotError, Transaction, TreeObserver,
+        ValueMut,
+    },
     BPlusTree, Key, NodeStoreVec,
 };
 
+#[cfg(feature = "rand")]
+use crate::augment::{
+    count::Count,
+    weighted::{Weighted, WeightedSum},
+};
+
 /// A B+ tree map implemented with `BPlusTree`
 pub struct BPlusTreeMap<K: Key, V, A: Augmentation<K> = ()> {
     inner: BPlusTree<NodeStoreVec<K, V, A>>,
@@ -96,6 +109,10 @@ impl<K: Key, V, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
 
     /// Returns a mut reference to the value corresponding to the key.
     ///
+    /// # Observers
+    /// Changes through the returned reference are NOT reported to the observer installed by
+    /// `set_observer`. Use `get_mut_observed` for that.
+    ///
     /// # Examples
     /// ```rust
     /// use sweep_bptree::BPlusTreeMap;
@@ -113,6 +130,16 @@ impl<K: Key, V, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
         self.inner.get_mut(key)
     }
 
+    /// Same as `get_mut`, but writes through the returned `ValueMut` are reported to the
+    /// observer, see `BPlusTree::get_mut_observed`.
+    #[inline]
+    pub fn get_mut_observed<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<ValueMut<'_, K, V>>
+    where
+        K: Borrow<Q>,
+    {
+        self.inner.get_mut_observed(key)
+    }
+
     /// Removes a key from the map, returning the value at the key if the key was previously in the map.
     ///
     /// # Examples
@@ -158,6 +185,32 @@ impl<K: Key, V, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
         }
     }
 
+    /// Returns an iterator over entries inside `range`
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    ///
+    /// let mut map = BPlusTreeMap::<i32, i32>::new();
+    /// map.insert(1, 2);
+    /// map.insert(2, 3);
+    /// map.insert(3, 4);
+    ///
+    /// let kvs = map.range(2..).map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+    /// assert_eq!(kvs, vec![(2, 3), (3, 4)]);
+    /// ```
+    pub fn range<Q, R>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = (&K, &V)> + std::iter::FusedIterator
+    where
+        Q: ?Sized + Ord,
+        K: Borrow<Q>,
+        R: std::ops::RangeBounds<Q>,
+    {
+        self.inner.range(range)
+    }
+
     /// Get root augment for the map
     pub fn root_augmentation(&self) -> &A {
         self.inner.root_augmentation()
@@ -191,6 +244,10 @@ impl<K: Key, V, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
 
     /// Get mut referece to value by augment's query
     ///
+    /// # Observers
+    /// Changes through the returned reference are NOT reported to the observer installed by
+    /// `set_observer`. Use `get_mut_by_augmentation_observed` for that.
+    ///
     /// # Example
     /// ```rust
     ///
@@ -214,6 +271,15 @@ impl<K: Key, V, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
         self.inner.get_mut_by_augmentation(query)
     }
 
+    /// Same as `get_mut_by_augmentation`, but writes through the returned `ValueMut` are
+    /// reported to the observer.
+    pub fn get_mut_by_augmentation_observed<Q>(&mut self, query: Q) -> Option<ValueMut<'_, K, V>>
+    where
+        A: SearchAugmentation<K, Query = Q>,
+    {
+        self.inner.get_mut_by_augmentation_observed(query)
+    }
+
     /// Remove by augment's query, returns deleted Key Value if exist
     ///
     /// # Example
@@ -273,15 +339,292 @@ impl<K: Key, V, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
         self.inner.rank_by_augmentation(k)
     }
 
+    /// Aggregate the augmentation of keys in `range`, takes O(log n)
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    /// use sweep_bptree::augment::count::Count;
+    ///
+    /// let mut map = BPlusTreeMap::<i32, i32, Count>::new();
+    /// map.insert(1, 2);
+    /// map.insert(2, 3);
+    /// map.insert(3, 4);
+    ///
+    /// assert_eq!(map.fold_range(2..).count(), 2);
+    /// assert_eq!(map.fold_range(..=1).count(), 1);
+    /// ```
+    pub fn fold_range<Q, R>(&self, range: R) -> A
+    where
+        A: Monoid<K>,
+        Q: ?Sized + Ord,
+        K: Borrow<Q>,
+        R: std::ops::RangeBounds<Q>,
+    {
+        self.inner.fold_range(range)
+    }
+
+    /// Search the first entry where the fold over augmentations decides to descend,
+    /// see `BPlusTree::search_by_fold`
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    /// use sweep_bptree::augment::count::Count;
+    /// use sweep_bptree::tree::visit::FoldStep;
+    ///
+    /// let mut map = BPlusTreeMap::<i32, i32, Count>::new();
+    /// map.insert(1, 2);
+    /// map.insert(2, 3);
+    /// map.insert(3, 4);
+    ///
+    /// // the entry with index 1
+    /// let entry = map.search_by_fold(0, |acc, c| {
+    ///     if acc + c.count() > 1 {
+    ///         FoldStep::Descend
+    ///     } else {
+    ///         FoldStep::Continue(acc + c.count())
+    ///     }
+    /// });
+    /// assert_eq!(entry, Some((&2, &3)));
+    /// ```
+    pub fn search_by_fold<Acc, F>(&self, init: Acc, f: F) -> Option<(&K, &V)>
+    where
+        F: FnMut(&Acc, &A) -> FoldStep<Acc>,
+    {
+        self.inner.search_entry_by_fold(init, f)
+    }
+
     /// Visit the tree's node with a visitor
-    /// Returns None if visitor cancelled
+    /// Returns None if visitor cancelled, error if visitor failed or went down to an invalid child
     /// Otherwise, returns visitor's result
-    pub fn descend_visit<VI, R>(&self, v: VI) -> Option<R>
+    pub fn descend_visit<VI, R, E>(&self, v: VI) -> Result<Option<R>, VisitError<E>>
     where
-        VI: DescendVisit<K, V, A, Result = R>,
+        VI: DescendVisit<K, V, A, Result = R, Error = E>,
     {
         self.inner.descend_visit(v)
     }
+
+    /// Split the map into two at `key`. Returns a new map with all items greater than or
+    /// equal to `key`, and leaves the rest in `self`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    ///
+    /// let mut map = BPlusTreeMap::<i32, i32>::new();
+    /// for i in 0..10 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// let right = map.split_off(&3);
+    /// assert_eq!(map.len(), 3);
+    /// assert_eq!(right.len(), 7);
+    /// ```
+    pub fn split_off<Q: ?Sized + Ord>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+    {
+        Self {
+            inner: self.inner.split_off(key),
+        }
+    }
+
+    /// Move all items from `other` into `self`, leaving `other` empty.
+    /// If a key exists in both, the value from `other` wins.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    ///
+    /// let mut a = BPlusTreeMap::<i32, i32>::new();
+    /// a.insert(1, 1);
+    /// let mut b = BPlusTreeMap::<i32, i32>::new();
+    /// b.insert(2, 2);
+    ///
+    /// a.append(&mut b);
+    /// assert_eq!(a.len(), 2);
+    /// assert!(b.is_empty());
+    /// ```
+    pub fn append(&mut self, other: &mut Self) {
+        self.inner.append(&mut other.inner)
+    }
+
+    /// Install an observer of all changes to the map, see `BPlusTree::set_observer`
+    ///
+    /// # Observers
+    /// Writes through `get_mut` and `get_mut_by_augmentation` are not reported, use
+    /// `get_mut_observed` and `get_mut_by_augmentation_observed` instead.
+    pub fn set_observer(&mut self, observer: impl TreeObserver<K, V> + Send + Sync + 'static) {
+        self.inner.set_observer(observer)
+    }
+
+    /// Remove the observer and returns it
+    pub fn take_observer(&mut self) -> Option<Box<dyn TreeObserver<K, V> + Send + Sync>> {
+        self.inner.take_observer()
+    }
+
+    /// Key of the `n`th item, see `BPlusTree::nth_key`
+    pub(crate) fn nth_key(&self, n: usize) -> Option<&K> {
+        self.inner.nth_key(n)
+    }
+
+    /// Run `f` as a transaction, changes are applied if `f` returns `Ok`, and discarded if
+    /// `f` returns `Err` or panics. See `BPlusTree::transaction`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    ///
+    /// let mut map = BPlusTreeMap::<i32, i32>::new();
+    /// map.insert(1, 1);
+    ///
+    /// let r = map.transaction(|tx| {
+    ///     tx.insert(2, 2);
+    ///     assert!(tx.contains_key(&2));
+    ///     Err::<(), _>("rejected")
+    /// });
+    /// assert!(r.is_err());
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn transaction<R, E>(
+        &mut self,
+        f: impl FnOnce(&mut Transaction<'_, NodeStoreVec<K, V, A>>) -> Result<R, E>,
+    ) -> Result<R, E> {
+        self.inner.transaction(f)
+    }
+}
+
+impl<K: Key, V, E: IntervalPoint<K>> BPlusTreeMap<K, V, IntervalMax<E>> {
+    /// Iterate intervals containing point `p`, in key order
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    /// use sweep_bptree::augment::interval::{Endpoint, IntervalMax};
+    ///
+    /// let mut map = BPlusTreeMap::<(u32, u32), &str, IntervalMax<Endpoint<u32>>>::new();
+    /// map.insert((1, 5), "a");
+    /// map.insert((2, 3), "b");
+    /// map.insert((4, 8), "c");
+    ///
+    /// let values = map.stab(Endpoint(2)).map(|(_, v)| *v).collect::<Vec<_>>();
+    /// assert_eq!(values, vec!["a", "b"]);
+    /// ```
+    pub fn stab(&self, p: E) -> IntervalIter<'_, NodeStoreVec<K, V, IntervalMax<E>>, E> {
+        self.inner.stab(p)
+    }
+
+    /// Iterate intervals overlapping `range`, in key order
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    /// use sweep_bptree::augment::interval::{Endpoint, IntervalMax};
+    ///
+    /// let mut map = BPlusTreeMap::<(u32, u32), &str, IntervalMax<Endpoint<u32>>>::new();
+    /// map.insert((1, 5), "a");
+    /// map.insert((2, 3), "b");
+    /// map.insert((4, 8), "c");
+    ///
+    /// let values = map
+    ///     .overlapping(Endpoint(5)..Endpoint(10))
+    ///     .map(|(_, v)| *v)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(values, vec!["c"]);
+    /// ```
+    pub fn overlapping(
+        &self,
+        range: std::ops::Range<E>,
+    ) -> IntervalIter<'_, NodeStoreVec<K, V, IntervalMax<E>>, E> {
+        self.inner.overlapping(range)
+    }
+}
+
+#[cfg(feature = "rand")]
+impl<K: Key + Weighted, V> BPlusTreeMap<K, V, WeightedSum> {
+    /// Sample an entry with probability proportional to its key's weight, takes O(log n).
+    /// Returns None if the total weight is 0
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    /// use sweep_bptree::augment::weighted::{Weighted, WeightedSum};
+    ///
+    /// #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+    /// struct Backend(&'static str, u64);
+    ///
+    /// impl Weighted for Backend {
+    ///     fn weight(&self) -> u64 {
+    ///         self.1
+    ///     }
+    /// }
+    ///
+    /// let mut map = BPlusTreeMap::<Backend, (), WeightedSum>::new();
+    /// map.insert(Backend("a", 0), ());
+    /// map.insert(Backend("b", 3), ());
+    ///
+    /// let (backend, _) = map.sample_weighted(&mut rand::thread_rng()).unwrap();
+    /// assert_eq!(backend.0, "b");
+    /// ```
+    pub fn sample_weighted<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<(&K, &V)> {
+        let total = self.root_augmentation().sum();
+        if total == 0 {
+            return None;
+        }
+        self.get_by_augmentation(rng.gen_range(0..total))
+    }
+}
+
+#[cfg(feature = "rand")]
+impl<K: Key, V> BPlusTreeMap<K, V, Count> {
+    /// Sample an entry uniformly, takes O(log n). Returns None if the map is empty
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    /// use sweep_bptree::augment::count::Count;
+    ///
+    /// let mut map = BPlusTreeMap::<i32, i32, Count>::new();
+    /// map.insert(1, 2);
+    /// map.insert(2, 3);
+    ///
+    /// let (k, _) = map.sample_uniform(&mut rand::thread_rng()).unwrap();
+    /// assert!(*k == 1 || *k == 2);
+    /// ```
+    pub fn sample_uniform<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Option<(&K, &V)> {
+        if self.is_empty() {
+            return None;
+        }
+        self.get_by_augmentation(rng.gen_range(0..self.len()))
+    }
+}
+
+impl<K: Key + Codec, V: Codec, A: Augmentation<K>> BPlusTreeMap<K, V, A> {
+    /// Write the map into `w` in snapshot format, see `BPlusTree::write_snapshot`
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::BPlusTreeMap;
+    ///
+    /// let map = (0..100u32).map(|i| (i, i.to_string())).collect::<BPlusTreeMap<_, _>>();
+    ///
+    /// let mut buf = Vec::new();
+    /// map.write_snapshot(&mut buf).unwrap();
+    ///
+    /// let loaded = BPlusTreeMap::<u32, String>::read_snapshot(buf.as_slice()).unwrap();
+    /// assert!(loaded.iter().eq(map.iter()));
+    /// ```
+    pub fn write_snapshot(&self, w: impl std::io::Write) -> std::io::Result<()> {
+        self.inner.write_snapshot(w)
+    }
+
+    /// Read a map written by `write_snapshot`
+    pub fn read_snapshot(r: impl std::io::Read) -> Result<Self, SnapshotError> {
+        Ok(Self {
+            inner: BPlusTree::read_snapshot(r)?,
+        })
+    }
 }
 
 impl<K: Key, V, A: Augmentation<K>> FromIterator<(K, V)> for BPlusTreeMap<K, V, A> {
@@ -306,6 +649,31 @@ impl<K: Key, V, A: Augmentation<K>> FromIterator<(K, V)> for BPlusTreeMap<K, V,
     }
 }
 
+#[cfg(feature = "serde")]
+impl<K, V, A> serde::Serialize for BPlusTreeMap<K, V, A>
+where
+    K: Key + serde::Serialize,
+    V: serde::Serialize,
+    A: Augmentation<K>,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, A> serde::Deserialize<'de> for BPlusTreeMap<K, V, A>
+where
+    K: Key + serde::Deserialize<'de>,
+    V: serde::Deserialize<'de>,
+    A: Augmentation<K>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let inner = deserializer.deserialize_map(crate::serde_impl::TreeMapVisitor::new())?;
+        Ok(Self { inner })
+    }
+}
+
 mod iter {
     use std::iter::FusedIterator;
 
@@ -1,6 +1,7 @@
 /// Copy MergeIter code from std lib
 /// We need same behavior for our Union etc Iterator
 use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::fmt::{self, Debug};
 use std::iter::FusedIterator;
 
@@ -106,3 +107,80 @@ impl<I: Iterator> MergeIterInner<I> {
         }
     }
 }
+
+/// Iterator merges the output of several ascending iterators of key-value pairs.
+/// Items with equal keys are returned in the order of their sources.
+pub struct KMergeIter<'a, K, V, I: Iterator<Item = (&'a K, &'a V)>> {
+    iters: Vec<I>,
+    /// head item of each non-exhausted source, the min head is on top
+    heap: BinaryHeap<Head<'a, K, V>>,
+}
+
+struct Head<'a, K, V> {
+    item: (&'a K, &'a V),
+    source: usize,
+}
+
+impl<'a, K: Ord, V> PartialEq for Head<'a, K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<'a, K: Ord, V> Eq for Head<'a, K, V> {}
+
+impl<'a, K: Ord, V> PartialOrd for Head<'a, K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, K: Ord, V> Ord for Head<'a, K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed, so the max heap pops the min item
+        (other.item.0, other.source).cmp(&(self.item.0, self.source))
+    }
+}
+
+impl<'a, K: Ord, V, I: Iterator<Item = (&'a K, &'a V)>> KMergeIter<'a, K, V, I> {
+    /// Creates a new iterator merging all sources
+    pub fn new(iters: impl IntoIterator<Item = I>) -> Self {
+        let mut iters = iters.into_iter().collect::<Vec<_>>();
+        let heap = iters
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(source, iter)| {
+                Some(Head {
+                    item: iter.next()?,
+                    source,
+                })
+            })
+            .collect();
+        Self { iters, heap }
+    }
+}
+
+impl<'a, K: Ord, V, I: Iterator<Item = (&'a K, &'a V)>> Iterator for KMergeIter<'a, K, V, I> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Head { item, source } = self.heap.pop()?;
+        if let Some(next) = self.iters[source].next() {
+            self.heap.push(Head { item: next, source });
+        }
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.iters.iter().map(|i| i.size_hint()).fold(
+            (self.heap.len(), Some(self.heap.len())),
+            |(l, u), (il, iu)| (l + il, u.zip(iu).map(|(u, iu)| u + iu)),
+        );
+        (lower, upper)
+    }
+}
+
+impl<'a, K: Ord, V, I: FusedIterator<Item = (&'a K, &'a V)>> FusedIterator
+    for KMergeIter<'a, K, V, I>
+{
+}
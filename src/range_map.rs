@@ -0,0 +1,596 @@
+use std::{
+    borrow::Borrow,
+    ops::{Add, Bound, Range},
+};
+
+use crate::{augment::Monoid, BPlusTree, NodeStoreVec};
+
+/// Point type of `RangeMap` and `RangeSet`, it measures the length between two points
+pub trait Measure: Ord + Clone {
+    type Len: Copy + Default + Add<Output = Self::Len>;
+
+    /// length from `start` to `end`, `start` is not greater than `end`
+    fn distance(start: &Self, end: &Self) -> Self::Len;
+}
+
+macro_rules! impl_measure {
+    ($($t:ty => $len:ty),*) => {
+        $(
+            impl Measure for $t {
+                type Len = $len;
+
+                #[inline]
+                fn distance(start: &Self, end: &Self) -> $len {
+                    end.abs_diff(*start)
+                }
+            }
+        )*
+    };
+}
+
+impl_measure!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize,
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize
+);
+
+/// The stored range, ordered by start only since ranges in map are disjoint
+#[derive(Clone, Debug)]
+struct Span<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Ord> PartialEq for Span<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start
+    }
+}
+
+impl<T: Ord> Eq for Span<T> {}
+
+impl<T: Ord> PartialOrd for Span<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Span<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.start.cmp(&other.start)
+    }
+}
+
+impl<T> Borrow<T> for Span<T> {
+    fn borrow(&self) -> &T {
+        &self.start
+    }
+}
+
+/// Total length covered by ranges
+#[derive(Clone, Copy, Debug, Default)]
+struct CoveredLen<L>(L);
+
+impl<T: Measure> Monoid<Span<T>> for CoveredLen<T::Len> {
+    const COMMUTATIVE: bool = true;
+
+    fn identity() -> Self {
+        Self(T::Len::default())
+    }
+
+    fn lift(span: &Span<T>) -> Self {
+        Self(T::distance(&span.start, &span.end))
+    }
+
+    fn combine(left: &Self, right: &Self) -> Self {
+        Self(left.0 + right.0)
+    }
+}
+
+type SpanStore<T, V> = NodeStoreVec<Span<T>, V, CoveredLen<<T as Measure>::Len>>;
+
+/// A map from disjoint half open ranges to values, built on `BPlusTree`.
+/// Adjacent or overlapping ranges with equal values are coalesced.
+pub struct RangeMap<T: Measure, V> {
+    tree: BPlusTree<SpanStore<T, V>>,
+}
+
+impl<T: Measure, V> Default for RangeMap<T, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Measure, V> RangeMap<T, V> {
+    /// Create a new RangeMap
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let map = RangeMap::<u32, char>::new();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            tree: BPlusTree::new(NodeStoreVec::new()),
+        }
+    }
+
+    /// Returns the count of disjoint ranges
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Returns true if the map contains no range
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Returns the total length covered by ranges, takes O(1)
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(0..10, 'a');
+    /// map.insert(20..25, 'b');
+    /// assert_eq!(map.covered_len(), 15);
+    /// ```
+    pub fn covered_len(&self) -> T::Len {
+        self.tree.root_augmentation().0
+    }
+
+    /// Returns the value of the range containing `point`
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(0..10, 'a');
+    /// assert_eq!(map.get(&9), Some(&'a'));
+    /// assert_eq!(map.get(&10), None);
+    /// ```
+    pub fn get(&self, point: &T) -> Option<&V> {
+        self.get_key_value(point).map(|(_, v)| v)
+    }
+
+    /// Returns the range containing `point` and its value
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(0..10, 'a');
+    /// assert_eq!(map.get_key_value(&5), Some((0..10, &'a')));
+    /// ```
+    pub fn get_key_value(&self, point: &T) -> Option<(Range<T>, &V)> {
+        let (span, v) = self.tree.range::<T, _>(..=point).next_back()?;
+        (*point < span.end).then(|| (span.start.clone()..span.end.clone(), v))
+    }
+
+    /// Returns true if any range contains `point`
+    pub fn contains(&self, point: &T) -> bool {
+        self.get(point).is_some()
+    }
+
+    /// Returns an iterator over ranges and values, in order
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(5..10, 'b');
+    /// map.insert(0..5, 'a');
+    ///
+    /// let items = map.iter().collect::<Vec<_>>();
+    /// assert_eq!(items, vec![(0..5, &'a'), (5..10, &'b')]);
+    /// ```
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Range<T>, &V)> {
+        self.tree
+            .iter()
+            .map(|(span, v)| (span.start.clone()..span.end.clone(), v))
+    }
+
+    /// Returns an iterator over the uncovered parts inside `range`, in order
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(0..10, 'a');
+    /// map.insert(20..25, 'b');
+    ///
+    /// let gaps = map.gaps(5..30).collect::<Vec<_>>();
+    /// assert_eq!(gaps, vec![10..20, 25..30]);
+    /// ```
+    pub fn gaps(&self, range: Range<T>) -> Gaps<'_, T, V> {
+        // the range starting before `range` may cover its head
+        let cursor = match self.tree.range::<T, _>(..=&range.start).next_back() {
+            Some((span, _)) if span.end > range.start => span.end.clone(),
+            _ => range.start.clone(),
+        };
+
+        Gaps {
+            spans: self
+                .tree
+                .range::<T, _>((Bound::Excluded(&range.start), Bound::Excluded(&range.end))),
+            cursor,
+            end: range.end,
+        }
+    }
+
+    /// Insert `range` with `value`. Overlapped parts of existing ranges are overwritten,
+    /// and ranges adjacent to or overlapping `range` with equal value are coalesced.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(0..10, 'a');
+    /// map.insert(5..15, 'b');
+    /// map.insert(15..20, 'b');
+    ///
+    /// let items = map.iter().collect::<Vec<_>>();
+    /// assert_eq!(items, vec![(0..5, &'a'), (5..20, &'b')]);
+    /// ```
+    pub fn insert(&mut self, range: Range<T>, value: V)
+    where
+        V: Clone + PartialEq,
+    {
+        if range.start >= range.end {
+            return;
+        }
+
+        let mut merged = Span {
+            start: range.start.clone(),
+            end: range.end.clone(),
+        };
+
+        // all ranges overlapping or adjacent to `range`
+        let lower = self.first_touching(&range.start, |span| span.end >= range.start);
+        let touching = lower
+            .into_iter()
+            .chain(
+                self.tree
+                    .range::<T, _>(&range.start..=&range.end)
+                    .map(|(span, _)| span.clone()),
+            )
+            .collect::<Vec<_>>();
+
+        for span in touching {
+            let overlaps = span.end > range.start && span.start < range.end;
+            if !overlaps && self.tree.get(&span.start) != Some(&value) {
+                continue;
+            }
+
+            let v = self.tree.remove(&span.start).unwrap();
+            if v == value {
+                merged.start = merged.start.min(span.start);
+                merged.end = merged.end.max(span.end);
+            } else {
+                self.restore_outside(span, v, &range);
+            }
+        }
+
+        self.tree.insert(merged, value);
+    }
+
+    /// Remove `range` from the map, ranges partially overlapping it are split
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeMap;
+    ///
+    /// let mut map = RangeMap::<u32, char>::new();
+    /// map.insert(0..10, 'a');
+    /// map.remove(3..5);
+    ///
+    /// let items = map.iter().collect::<Vec<_>>();
+    /// assert_eq!(items, vec![(0..3, &'a'), (5..10, &'a')]);
+    /// ```
+    pub fn remove(&mut self, range: Range<T>)
+    where
+        V: Clone,
+    {
+        if range.start >= range.end {
+            return;
+        }
+
+        let lower = self.first_touching(&range.start, |span| span.end > range.start);
+        let overlapping = lower
+            .into_iter()
+            .chain(
+                self.tree
+                    .range::<T, _>(&range.start..&range.end)
+                    .map(|(span, _)| span.clone()),
+            )
+            .collect::<Vec<_>>();
+
+        for span in overlapping {
+            let v = self.tree.remove(&span.start).unwrap();
+            self.restore_outside(span, v, &range);
+        }
+    }
+
+    /// The range starting before `start`, if it matches `f`
+    fn first_touching(&self, start: &T, f: impl Fn(&Span<T>) -> bool) -> Option<Span<T>> {
+        self.tree
+            .range::<T, _>(..start)
+            .next_back()
+            .map(|(span, _)| span)
+            .filter(|span| f(span))
+            .cloned()
+    }
+
+    /// Insert back the parts of removed `span` outside of `range`
+    fn restore_outside(&mut self, span: Span<T>, v: V, range: &Range<T>)
+    where
+        V: Clone,
+    {
+        if span.start < range.start {
+            let left = Span {
+                start: span.start.clone(),
+                end: range.start.clone(),
+            };
+            self.tree.insert(left, v.clone());
+        }
+        if span.end > range.end {
+            let right = Span {
+                start: range.end.clone(),
+                end: span.end,
+            };
+            self.tree.insert(right, v);
+        }
+    }
+}
+
+/// Iterator over uncovered parts of a range, created by `RangeMap::gaps` or `RangeSet::gaps`
+pub struct Gaps<'a, T: Measure, V> {
+    spans: crate::tree::Range<'a, SpanStore<T, V>>,
+    /// start of next gap candidate
+    cursor: T,
+    end: T,
+}
+
+impl<'a, T: Measure, V> Iterator for Gaps<'a, T, V> {
+    type Item = Range<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.cursor < self.end {
+            match self.spans.next() {
+                Some((span, _)) if span.start <= self.cursor => {
+                    self.cursor = self.cursor.clone().max(span.end.clone());
+                }
+                Some((span, _)) => {
+                    let gap = self.cursor.clone()..span.start.clone();
+                    self.cursor = span.end.clone();
+                    return Some(gap);
+                }
+                None => {
+                    let gap = self.cursor.clone()..self.end.clone();
+                    self.cursor = self.end.clone();
+                    return Some(gap);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// A set of disjoint half open ranges, built on `BPlusTree`.
+/// Adjacent or overlapping ranges are coalesced.
+pub struct RangeSet<T: Measure> {
+    map: RangeMap<T, ()>,
+}
+
+impl<T: Measure> Default for RangeSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Measure> RangeSet<T> {
+    /// Create a new RangeSet
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeSet;
+    ///
+    /// let set = RangeSet::<u32>::new();
+    /// assert!(set.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            map: RangeMap::new(),
+        }
+    }
+
+    /// Returns the count of disjoint ranges
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the set contains no range
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the total length covered by ranges, takes O(1)
+    pub fn covered_len(&self) -> T::Len {
+        self.map.covered_len()
+    }
+
+    /// Returns true if any range contains `point`
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeSet;
+    ///
+    /// let mut set = RangeSet::<u32>::new();
+    /// set.insert(0..10);
+    /// assert!(set.contains(&0));
+    /// assert!(!set.contains(&10));
+    /// ```
+    pub fn contains(&self, point: &T) -> bool {
+        self.map.contains(point)
+    }
+
+    /// Returns the range containing `point`
+    pub fn get(&self, point: &T) -> Option<Range<T>> {
+        self.map.get_key_value(point).map(|(r, _)| r)
+    }
+
+    /// Returns an iterator over ranges, in order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Range<T>> + '_ {
+        self.map.iter().map(|(r, _)| r)
+    }
+
+    /// Returns an iterator over the uncovered parts inside `range`, in order
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeSet;
+    ///
+    /// let mut set = RangeSet::<u32>::new();
+    /// set.insert(10..20);
+    ///
+    /// let gaps = set.gaps(0..30).collect::<Vec<_>>();
+    /// assert_eq!(gaps, vec![0..10, 20..30]);
+    /// ```
+    pub fn gaps(&self, range: Range<T>) -> Gaps<'_, T, ()> {
+        self.map.gaps(range)
+    }
+
+    /// Insert `range`, coalescing it with adjacent or overlapping ranges
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeSet;
+    ///
+    /// let mut set = RangeSet::<u32>::new();
+    /// set.insert(0..10);
+    /// set.insert(10..15);
+    /// set.insert(12..20);
+    ///
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![0..20]);
+    /// ```
+    pub fn insert(&mut self, range: Range<T>) {
+        self.map.insert(range, ());
+    }
+
+    /// Remove `range`, ranges partially overlapping it are split
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::RangeSet;
+    ///
+    /// let mut set = RangeSet::<u32>::new();
+    /// set.insert(0..20);
+    /// set.remove(5..10);
+    ///
+    /// assert_eq!(set.iter().collect::<Vec<_>>(), vec![0..5, 10..20]);
+    /// assert_eq!(set.covered_len(), 15);
+    /// ```
+    pub fn remove(&mut self, range: Range<T>) {
+        self.map.remove(range);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    const SIZE: usize = 2000;
+
+    /// check the map against the value of each point
+    fn check(map: &RangeMap<u32, u8>, points: &[Option<u8>]) {
+        let mut expected = Vec::<(Range<u32>, &u8)>::new();
+        for (p, v) in points.iter().enumerate() {
+            let p = p as u32;
+            let Some(v) = v else { continue };
+            match expected.last_mut() {
+                Some((r, last)) if r.end == p && *last == v => r.end = p + 1,
+                _ => expected.push((p..p + 1, v)),
+            }
+        }
+        assert_eq!(map.iter().collect::<Vec<_>>(), expected);
+
+        for (p, v) in points.iter().enumerate() {
+            assert_eq!(map.get(&(p as u32)), v.as_ref());
+        }
+
+        let covered = points.iter().filter(|v| v.is_some()).count();
+        assert_eq!(map.covered_len() as usize, covered);
+    }
+
+    #[test]
+    fn test_range_map_random() {
+        let mut rng = rand::thread_rng();
+        let mut map = RangeMap::<u32, u8>::new();
+        let mut points = vec![None; SIZE];
+
+        for i in 0..2000 {
+            let start = rng.gen_range(0..SIZE);
+            let end = (start + rng.gen_range(0..50)).min(SIZE);
+
+            if rng.gen_bool(0.7) {
+                let v = rng.gen_range(0..3);
+                map.insert(start as u32..end as u32, v);
+                points[start..end].iter_mut().for_each(|p| *p = Some(v));
+            } else {
+                map.remove(start as u32..end as u32);
+                points[start..end].iter_mut().for_each(|p| *p = None);
+            }
+
+            if i % 100 == 0 {
+                check(&map, &points);
+            }
+        }
+        check(&map, &points);
+    }
+
+    #[test]
+    fn test_range_set_gaps() {
+        let mut rng = rand::thread_rng();
+        let mut set = RangeSet::<u32>::new();
+        let mut points = vec![false; SIZE];
+
+        for _ in 0..300 {
+            let start = rng.gen_range(0..SIZE);
+            let end = (start + rng.gen_range(0..30)).min(SIZE);
+            set.insert(start as u32..end as u32);
+            points[start..end].iter_mut().for_each(|p| *p = true);
+        }
+
+        for _ in 0..100 {
+            let start = rng.gen_range(0..SIZE);
+            let end = rng.gen_range(start..=SIZE);
+
+            let mut expected = Vec::<Range<u32>>::new();
+            for p in (start..end).filter(|p| !points[*p]) {
+                let p = p as u32;
+                match expected.last_mut() {
+                    Some(r) if r.end == p => r.end = p + 1,
+                    _ => expected.push(p..p + 1),
+                }
+            }
+
+            let gaps = set.gaps(start as u32..end as u32).collect::<Vec<_>>();
+            assert_eq!(gaps, expected);
+        }
+
+        assert_eq!(
+            set.covered_len() as usize,
+            points.iter().filter(|p| **p).count()
+        );
+    }
+}
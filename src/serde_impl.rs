@@ -0,0 +1,124 @@
+//! Serde support for `BPlusTreeMap` and `BPlusTreeSet`.
+//! Maps are serialized as maps and sets as sequences, both in key order. Augmentations are
+//! not serialized, they are recomputed when the tree is rebuilt.
+
+use std::{fmt, marker::PhantomData};
+
+use serde::de::{MapAccess, SeqAccess, Visitor};
+
+use crate::{BPlusTree, NodeStore};
+
+/// Build the tree with `bulk_load` if `items` are sorted, otherwise insert them one by one.
+/// For duplicated keys, the last one wins in both cases.
+fn load_tree<S: NodeStore>(items: Vec<(S::K, S::V)>) -> BPlusTree<S> {
+    if items.windows(2).all(|w| w[0].0 <= w[1].0) {
+        return BPlusTree::bulk_load(items);
+    }
+
+    let mut tree = BPlusTree::new(S::default());
+    for (k, v) in items {
+        tree.insert(k, v);
+    }
+    tree
+}
+
+/// Visitor deserializes a map into `BPlusTree`
+pub(crate) struct TreeMapVisitor<S>(PhantomData<S>);
+
+impl<S> TreeMapVisitor<S> {
+    pub(crate) fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<'de, S> Visitor<'de> for TreeMapVisitor<S>
+where
+    S: NodeStore,
+    S::K: serde::Deserialize<'de>,
+    S::V: serde::Deserialize<'de>,
+{
+    type Value = BPlusTree<S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+        let mut items = Vec::with_capacity(access.size_hint().unwrap_or_default());
+        while let Some(item) = access.next_entry()? {
+            items.push(item);
+        }
+        Ok(load_tree(items))
+    }
+}
+
+/// Visitor deserializes a sequence of keys into `BPlusTree`
+pub(crate) struct TreeSetVisitor<S>(PhantomData<S>);
+
+impl<S> TreeSetVisitor<S> {
+    pub(crate) fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<'de, S> Visitor<'de> for TreeSetVisitor<S>
+where
+    S: NodeStore<V = ()>,
+    S::K: serde::Deserialize<'de>,
+{
+    type Value = BPlusTree<S>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut items = Vec::with_capacity(access.size_hint().unwrap_or_default());
+        while let Some(k) = access.next_element()? {
+            items.push((k, ()));
+        }
+        Ok(load_tree(items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{augment::count::Count, BPlusTreeMap, BPlusTreeSet};
+
+    #[test]
+    fn test_map_json_round_trip() {
+        let map = (0..1000)
+            .map(|i| (i, i.to_string()))
+            .collect::<BPlusTreeMap<u32, String, Count>>();
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert!(json.starts_with(r#"{"0":"0","1":"1","2":"2""#));
+
+        let loaded: BPlusTreeMap<u32, String, Count> = serde_json::from_str(&json).unwrap();
+        assert!(loaded.iter().eq(map.iter()));
+        assert_eq!(loaded.root_augmentation().count(), 1000);
+    }
+
+    #[test]
+    fn test_map_unsorted_input() {
+        let loaded: BPlusTreeMap<u32, u32, Count> =
+            serde_json::from_str(r#"{"3":3,"1":1,"2":2,"1":10}"#).unwrap();
+
+        let items = loaded.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+        assert_eq!(items, vec![(1, 10), (2, 2), (3, 3)]);
+        assert_eq!(loaded.root_augmentation().count(), 3);
+    }
+
+    #[test]
+    fn test_set_bincode_round_trip() {
+        let set = BPlusTreeSet::from_iter((0..1000).rev());
+
+        let bytes = bincode::serialize(&set).unwrap();
+        let loaded: BPlusTreeSet<i64> = bincode::deserialize(&bytes).unwrap();
+        assert!(loaded.iter().eq(set.iter()));
+
+        let unsorted = bincode::serialize(&vec![5i64, 3, 4, 3]).unwrap();
+        let loaded: BPlusTreeSet<i64> = bincode::deserialize(&unsorted).unwrap();
+        assert_eq!(loaded.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+    }
+}
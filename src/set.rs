@@ -39,7 +39,7 @@ impl<K: Key> BPlusTreeSet<K> {
     ///
     /// let mut set = BPlusTreeSet::<i32>::new();
     /// assert_eq!(set.len(), 0);
-
+    ///
     /// set.insert(1);
     /// assert_eq!(set.len(), 1);
     ///
@@ -200,7 +200,7 @@ impl<K: Key> BPlusTreeSet<K> {
     ///
     /// ```
     #[inline]
-    pub fn iter(&self) -> iter::Iter<K> {
+    pub fn iter(&self) -> iter::Iter<'_, K> {
         iter::Iter {
             inner: self.tree.iter(),
         }
@@ -252,6 +252,21 @@ impl<K: Key> FromIterator<K> for BPlusTreeSet<K> {
     }
 }
 
+#[cfg(feature = "serde")]
+impl<K: Key + serde::Serialize> serde::Serialize for BPlusTreeSet<K> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K: Key + serde::Deserialize<'de>> serde::Deserialize<'de> for BPlusTreeSet<K> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tree = deserializer.deserialize_seq(crate::serde_impl::TreeSetVisitor::new())?;
+        Ok(Self { tree })
+    }
+}
+
 impl<K: Key> IntoIterator for BPlusTreeSet<K> {
     type Item = K;
     type IntoIter = iter::IntoIter<K>;
@@ -0,0 +1,363 @@
+use std::{
+    borrow::Borrow,
+    sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard},
+};
+
+use crate::{augment::Monoid, merge_iter::KMergeIter, tree::Augmentation, BPlusTreeMap, Key};
+
+/// A map range partitions the key space across several independently locked `BPlusTreeMap`s,
+/// so writers to different partitions don't contend.
+///
+/// Partition `i` holds keys in `[boundaries[i - 1], boundaries[i])`, the first and last
+/// partitions are unbounded. `rebalance` moves boundaries to even out partition sizes.
+///
+/// # Example
+/// ```rust
+/// use std::sync::Arc;
+/// use sweep_bptree::ShardedBPlusTreeMap;
+///
+/// let map = Arc::new(ShardedBPlusTreeMap::<u64, u64>::with_boundaries(vec![250, 500, 750]));
+///
+/// let writers = (0..4u64)
+///     .map(|t| {
+///         let map = map.clone();
+///         std::thread::spawn(move || {
+///             for i in (t * 250)..(t + 1) * 250 {
+///                 map.insert(i, i);
+///             }
+///         })
+///     })
+///     .collect::<Vec<_>>();
+/// for w in writers {
+///     w.join().unwrap();
+/// }
+///
+/// assert_eq!(map.len(), 1000);
+/// assert!(map.read().iter().map(|(k, _)| *k).eq(0..1000));
+/// ```
+pub struct ShardedBPlusTreeMap<K: Key, V, A: Augmentation<K> = ()> {
+    /// partition boundaries, ascending
+    boundaries: RwLock<Vec<K>>,
+    shards: Vec<Mutex<BPlusTreeMap<K, V, A>>>,
+}
+
+impl<K: Key, V, A: Augmentation<K>> ShardedBPlusTreeMap<K, V, A> {
+    /// Create a map with `boundaries.len() + 1` partitions split at `boundaries`
+    ///
+    /// # Panics
+    /// if `boundaries` is not strictly ascending
+    pub fn with_boundaries(boundaries: Vec<K>) -> Self {
+        assert!(
+            boundaries.windows(2).all(|w| w[0] < w[1]),
+            "boundaries must be strictly ascending"
+        );
+
+        let shards = (0..=boundaries.len())
+            .map(|_| Mutex::new(BPlusTreeMap::new()))
+            .collect();
+        Self {
+            boundaries: RwLock::new(boundaries),
+            shards,
+        }
+    }
+
+    /// Returns the count of partitions
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns current partition boundaries
+    pub fn boundaries(&self) -> Vec<K> {
+        self.boundaries.read().unwrap().clone()
+    }
+
+    /// Returns item count of each partition
+    pub fn shard_lens(&self) -> Vec<usize> {
+        self.read().shards.iter().map(|s| s.len()).collect()
+    }
+
+    /// Returns item count in the map
+    pub fn len(&self) -> usize {
+        self.shard_lens().into_iter().sum()
+    }
+
+    /// Returns true if the map contains no item
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Insert a key-value pair, only the partition holding `key` is locked
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let boundaries = self.boundaries.read().unwrap();
+        let mut shard = self.shards[shard_index(&boundaries, &key)].lock().unwrap();
+        shard.insert(key, value)
+    }
+
+    /// Remove a key, only the partition holding `key` is locked
+    pub fn remove<Q: ?Sized + Ord>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.with_shard(key, |shard| shard.remove(key))
+    }
+
+    /// Returns a copy of the value for `key`
+    pub fn get<Q: ?Sized + Ord>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+    {
+        self.with_shard(key, |shard| shard.get(key).cloned())
+    }
+
+    /// Lock all partitions for a consistent read of the whole map
+    pub fn read(&self) -> ShardedReadGuard<'_, K, V, A> {
+        // lock order: boundaries first, then shards in order
+        let boundaries = self.boundaries.read().unwrap();
+        let shards = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        ShardedReadGuard {
+            _boundaries: boundaries,
+            shards,
+        }
+    }
+
+    /// Returns the combined root augmentation of all partitions
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::ShardedBPlusTreeMap;
+    /// use sweep_bptree::augment::count::Count;
+    ///
+    /// let map = ShardedBPlusTreeMap::<u64, u64, Count>::with_boundaries(vec![10]);
+    /// for i in 0..20 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// assert_eq!(map.root_augmentation().count(), 20);
+    /// ```
+    pub fn root_augmentation(&self) -> A
+    where
+        A: Monoid<K>,
+    {
+        self.read().root_augmentation()
+    }
+
+    /// Move partition boundaries so that partitions hold even item counts. Items are moved
+    /// between adjacent partitions with `split_off` and `append`. All partitions are locked
+    /// during rebalance.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::ShardedBPlusTreeMap;
+    ///
+    /// let map = ShardedBPlusTreeMap::<u64, u64>::with_boundaries(vec![10, 20, 30]);
+    /// for i in 100..200 {
+    ///     map.insert(i, i);
+    /// }
+    /// assert_eq!(map.shard_lens(), vec![0, 0, 0, 100]);
+    ///
+    /// map.rebalance();
+    /// assert_eq!(map.shard_lens(), vec![25, 25, 25, 25]);
+    /// assert_eq!(map.boundaries(), vec![125, 150, 175]);
+    /// ```
+    pub fn rebalance(&self) {
+        let mut boundaries = self.boundaries.write().unwrap();
+        let mut shards = self
+            .shards
+            .iter()
+            .map(|s| s.lock().unwrap())
+            .collect::<Vec<_>>();
+
+        let n = shards.len();
+        let total = shards.iter().map(|s| s.len()).sum::<usize>();
+        // item count in shards[..i]
+        let mut prefix = 0;
+
+        for i in 0..n - 1 {
+            let target = total * (i + 1) / n - prefix;
+            let len = shards[i].len();
+
+            if len > target {
+                // move the tail to next shard
+                let key = nth_key(&shards[i], target);
+                let mut tail = shards[i].split_off(&key);
+                shards[i + 1].append(&mut tail);
+                boundaries[i] = key;
+            } else if len < target {
+                // move heads of following shards to this one
+                let mut need = target - len;
+                let mut j = i;
+                while need > 0 {
+                    j += 1;
+                    let (left, right) = shards.split_at_mut(j);
+                    let source = &mut *right[0];
+                    let take = need.min(source.len());
+                    let mut head = if take == source.len() {
+                        std::mem::take(source)
+                    } else {
+                        let rest = source.split_off(&nth_key(source, take));
+                        std::mem::replace(source, rest)
+                    };
+                    left[i].append(&mut head);
+                    need -= take;
+                }
+
+                // shards between are empty now, they share the new boundary
+                let boundary = match shards[j].iter().next() {
+                    Some((k, _)) => k.clone(),
+                    // the last shard keeps its own target, so it never gets emptied here
+                    None => boundaries[j].clone(),
+                };
+                for b in boundaries[i..j].iter_mut() {
+                    *b = boundary.clone();
+                }
+            }
+
+            prefix += target;
+        }
+    }
+
+    fn with_shard<Q: ?Sized + Ord, R>(
+        &self,
+        key: &Q,
+        f: impl FnOnce(&mut BPlusTreeMap<K, V, A>) -> R,
+    ) -> R
+    where
+        K: Borrow<Q>,
+    {
+        // holding boundaries lock, so the key can't move to another shard during `f`
+        let boundaries = self.boundaries.read().unwrap();
+        let mut shard = self.shards[shard_index(&boundaries, key)].lock().unwrap();
+        f(&mut shard)
+    }
+}
+
+/// Index of the shard holding `key`
+fn shard_index<K: Borrow<Q>, Q: ?Sized + Ord>(boundaries: &[K], key: &Q) -> usize {
+    boundaries.partition_point(|b| b.borrow() <= key)
+}
+
+/// Key of the `n`th item in `map`, `n` must be less than `map.len()`
+fn nth_key<K: Key, V, A: Augmentation<K>>(map: &BPlusTreeMap<K, V, A>, n: usize) -> K {
+    map.nth_key(n).unwrap().clone()
+}
+
+/// Guard for reading all partitions, created by `ShardedBPlusTreeMap::read`
+pub struct ShardedReadGuard<'a, K: Key, V, A: Augmentation<K>> {
+    _boundaries: RwLockReadGuard<'a, Vec<K>>,
+    shards: Vec<MutexGuard<'a, BPlusTreeMap<K, V, A>>>,
+}
+
+impl<'a, K: Key, V, A: Augmentation<K>> ShardedReadGuard<'a, K, V, A> {
+    /// Returns item count in all partitions
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.len()).sum()
+    }
+
+    /// Returns true if all partitions are empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over all items in key order, merged from all partitions
+    pub fn iter(&self) -> impl std::iter::FusedIterator<Item = (&K, &V)> {
+        KMergeIter::new(self.shards.iter().map(|s| s.iter()))
+    }
+
+    /// Returns the combined root augmentation of all partitions
+    pub fn root_augmentation(&self) -> A
+    where
+        A: Monoid<K>,
+    {
+        self.shards.iter().fold(A::identity(), |acc, s| {
+            A::combine(&acc, s.root_augmentation())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::Rng;
+
+    use super::*;
+    use crate::augment::count::Count;
+
+    #[test]
+    fn test_sharded_rebalance() {
+        let mut rng = rand::thread_rng();
+        let map = ShardedBPlusTreeMap::<u32, u32, Count>::with_boundaries(vec![100, 200, 300]);
+        let mut expected = std::collections::BTreeMap::new();
+
+        for round in 0..20 {
+            // skewed inserts and removes
+            let base = rng.gen_range(0..5000);
+            for _ in 0..500 {
+                let k = base + rng.gen_range(0..1000);
+                if rng.gen_bool(0.8) {
+                    assert_eq!(map.insert(k, round), expected.insert(k, round));
+                } else {
+                    assert_eq!(map.remove(&k), expected.remove(&k));
+                }
+            }
+
+            map.rebalance();
+
+            let lens = map.shard_lens();
+            let (min, max) = (lens.iter().min().unwrap(), lens.iter().max().unwrap());
+            assert!(max - min <= 1, "unbalanced {lens:?}");
+
+            let boundaries = map.boundaries();
+            assert!(boundaries.windows(2).all(|w| w[0] <= w[1]));
+
+            let read = map.read();
+            assert_eq!(read.root_augmentation().count(), expected.len());
+            assert!(read.iter().eq(expected.iter()));
+            for (idx, shard) in read.shards.iter().enumerate() {
+                assert!(shard.iter().all(|(k, _)| {
+                    (idx == 0 || *k >= boundaries[idx - 1])
+                        && (idx == boundaries.len() || *k < boundaries[idx])
+                }));
+            }
+            drop(read);
+
+            for (k, v) in expected.iter() {
+                assert_eq!(map.get(k), Some(*v));
+            }
+        }
+    }
+
+    #[test]
+    fn test_sharded_concurrent_insert() {
+        let map = Arc::new(ShardedBPlusTreeMap::<u64, u64, Count>::with_boundaries(
+            vec![1000, 2000, 3000],
+        ));
+
+        let writers = (0..4u64)
+            .map(|t| {
+                let map = map.clone();
+                std::thread::spawn(move || {
+                    for i in 0..4000 {
+                        if i % 4 == t {
+                            map.insert(i, t);
+                        }
+                        if i % 1000 == 0 {
+                            map.rebalance();
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for w in writers {
+            w.join().unwrap();
+        }
+
+        assert_eq!(map.root_augmentation().count(), 4000);
+        assert!(map
+            .read()
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..4000).map(|i| (i, i % 4))));
+    }
+}
@@ -3,31 +3,64 @@ use crate::tree::InnerNode;
 use super::{Augmentation, LeafNode, LeafNodeId, NodeId, NodeStore};
 
 impl<S: NodeStore> crate::BPlusTree<S> {
-    /// bulk load data into a new `BPlusTree`, the loaded tree's leaf with fill rate 1.0
+    /// bulk load data into a new `BPlusTree`, leaves are full except the last one, which
+    /// takes elements from the one before it to keep the minimum fill.
     /// It requires data sorted by `S::K`
     pub fn bulk_load(data: Vec<(S::K, S::V)>) -> Self {
-        // todo: now the last leaf maybe undersized, which should be easily fixed by
-        //       shifting some items from prev leaf.
-        let mut node_store = S::default();
-        if data.is_empty() {
-            return Self::new(node_store);
+        let data_iter = data.into_iter().dedup_keep_last(|l, r| l.0.eq(&r.0));
+        Self::from_leaves(S::default(), Self::pack_leaves(data_iter))
+    }
+
+    /// Pack sorted and deduped `data` into full leaves
+    pub(crate) fn pack_leaves(
+        mut data: impl Iterator<Item = (S::K, S::V)>,
+    ) -> impl Iterator<Item = Box<LeafNode<S::K, S::V>>> {
+        let mut full_leaves = std::iter::from_fn(move || {
+            let mut leaf = LeafNode::<S::K, S::V>::new();
+            leaf.set_data(&mut data);
+            // empty leaf means there is no data left
+            (!leaf.is_empty()).then_some(leaf)
+        })
+        .peekable();
+
+        // only the last leaf maybe undersized, fill it by shifting items from prev leaf
+        std::iter::from_fn(move || {
+            let mut leaf = full_leaves.next()?;
+            if let Some(next) = full_leaves.peek_mut() {
+                while next.len() < LeafNode::<S::K, S::V>::minimum_size() as usize {
+                    let (k, v) = leaf.pop();
+                    next.try_upsert::<S::Searcher>(k, v);
+                }
+            }
+            Some(leaf)
+        })
+    }
+
+    /// Build the tree from non empty `leaves` in key order. Leaves are chained and inner
+    /// layers are built on them.
+    pub(crate) fn from_leaves(
+        mut node_store: S,
+        leaves: impl Iterator<Item = Box<LeafNode<S::K, S::V>>>,
+    ) -> Self {
+        match Self::link_leaves(&mut node_store, leaves) {
+            Some((root_id, item_count)) => Self::new_from_parts(node_store, root_id, item_count),
+            None => Self::new(node_store),
         }
+    }
 
+    /// Add non empty `leaves` in key order to the store, chain them and build inner layers.
+    /// Returns the root id and element count, None if there is no leaf.
+    pub(crate) fn link_leaves(
+        node_store: &mut S,
+        leaves: impl Iterator<Item = Box<LeafNode<S::K, S::V>>>,
+    ) -> Option<(NodeId, usize)> {
         let mut item_count = 0usize;
-
-        let mut data_iter = data.into_iter().dedup_keep_last(|l, r| l.0.eq(&r.0));
         let mut nodes: Vec<(NodeId, (Option<S::K>, Option<S::K>), S::Augmentation)> = Vec::new();
 
         let mut prev_id: Option<LeafNodeId> = None;
 
-        loop {
-            let mut leaf = LeafNode::<S::K, S::V>::new();
-            leaf.set_data(&mut data_iter);
-
-            if leaf.is_empty() {
-                // there is no data left
-                break;
-            }
+        for mut leaf in leaves {
+            debug_assert!(!leaf.is_empty());
 
             let leaf_id = node_store.reserve_leaf();
             leaf.set_prev(prev_id);
@@ -49,9 +82,13 @@ impl<S: NodeStore> crate::BPlusTree<S> {
             node_store.assign_leaf(leaf_id, leaf);
         }
 
-        let root_id = Self::build_inner_layer(&mut node_store, nodes);
+        if nodes.is_empty() {
+            return None;
+        }
+
+        let root_id = Self::build_inner_layer(node_store, nodes);
 
-        Self::new_from_parts(node_store, root_id, item_count)
+        Some((root_id, item_count))
     }
 
     /// build an inner node layer for all `nodes`
@@ -69,15 +106,19 @@ impl<S: NodeStore> crate::BPlusTree<S> {
         let child_n = S::inner_n() as usize + 1;
 
         // each node is a child
-        let node_num = nodes.len() / child_n + (nodes.len() % child_n > 0) as usize;
+        let node_num = nodes.len().div_ceil(child_n);
 
-        let mut chunk_iter = nodes.chunks(child_n);
+        // spread children evenly, so the last node is not left with too few children
+        let (base, extra) = (nodes.len() / node_num, nodes.len() % node_num);
+        let mut rest = nodes.as_slice();
 
-        let mut nodes: Vec<(NodeId, (Option<S::K>, Option<S::K>), S::Augmentation)> =
+        let mut parents: Vec<(NodeId, (Option<S::K>, Option<S::K>), S::Augmentation)> =
             Vec::with_capacity(node_num);
 
-        for _ in 0..node_num {
-            let childs = chunk_iter.next().unwrap();
+        for i in 0..node_num {
+            let (childs, tail) = rest.split_at(base + (i < extra) as usize);
+            rest = tail;
+
             let start_key = childs[0].1 .0.clone();
             let end_key = childs[childs.len() - 1].1 .0.clone();
 
@@ -95,10 +136,10 @@ impl<S: NodeStore> crate::BPlusTree<S> {
             let augmentation = S::Augmentation::from_inner(inner.keys(), inner.augmentations());
             let node_id = node_store.add_inner(inner);
 
-            nodes.push((NodeId::Inner(node_id), (start_key, end_key), augmentation));
+            parents.push((NodeId::Inner(node_id), (start_key, end_key), augmentation));
         }
 
-        Self::build_inner_layer(node_store, nodes)
+        Self::build_inner_layer(node_store, parents)
     }
 }
 
@@ -123,7 +164,7 @@ where
         for item in self.iter.by_ref() {
             match self.current_item.as_ref() {
                 Some(current_item) if !(self.same_bucket)(current_item, &item) => {
-                    let prev_item = std::mem::replace(&mut self.current_item, Some(item));
+                    let prev_item = self.current_item.replace(item);
                     return prev_item;
                 }
                 _ => {
@@ -18,7 +18,7 @@ pub struct Cursor<K: Key> {
     offset_hint: usize,
 }
 
-impl<'k, K: Key + 'k> Cursor<K> {
+impl<K: Key> Cursor<K> {
     /// Create a new cursor
     #[inline(always)]
     pub(crate) fn new(k: K, leaf_id: LeafNodeId, offset: usize) -> Self {
@@ -38,7 +38,7 @@ impl<'k, K: Key + 'k> Cursor<K> {
     /// Create a `Cursor` pointing to the first key-value pair in the tree.
     pub fn first<'b, S: NodeStore<K = K>>(tree: &'b BPlusTree<S>) -> Option<(Self, &'b S::V)>
     where
-        'k: 'b,
+        K: 'b,
     {
         let leaf_id = tree.first_leaf()?;
         let leaf = tree.node_store.get_leaf(leaf_id);
@@ -59,7 +59,7 @@ impl<'k, K: Key + 'k> Cursor<K> {
     /// this returns the cursor for the key value pair just larger than the deleted key.
     pub fn last<'b, S: NodeStore<K = K>>(tree: &'b BPlusTree<S>) -> Option<(Self, &'b S::V)>
     where
-        'k: 'b,
+        K: 'b,
     {
         let leaf_id = tree.last_leaf()?;
         let leaf = tree.node_store.get_leaf(leaf_id);
@@ -88,7 +88,7 @@ impl<'k, K: Key + 'k> Cursor<K> {
         tree: &'b BPlusTree<S>,
     ) -> Option<(Self, &'b S::V)>
     where
-        'k: 'b,
+        K: 'b,
     {
         let (leaf_id, leaf) = self.locate_leaf(tree)?;
 
@@ -104,7 +104,7 @@ impl<'k, K: Key + 'k> Cursor<K> {
                 }
             }
             _ => {
-                let offset = match leaf.locate_slot(&self.k) {
+                let offset = match leaf.locate_slot::<S::Searcher, _>(&self.k) {
                     Ok(offset) => offset,
                     Err(offset) => offset,
                 };
@@ -143,14 +143,14 @@ impl<'k, K: Key + 'k> Cursor<K> {
         tree: &'b BPlusTree<S>,
     ) -> Option<(Self, &'b S::V)>
     where
-        'k: 'b,
+        K: 'b,
     {
         let (leaf_id, leaf) = self.locate_leaf(tree)?;
 
         let next_offset = match leaf.try_data_at(self.offset_hint) {
             Some(kv) if kv.0.eq(&self.k) => self.offset_hint + 1,
             _ => {
-                let (offset, value) = leaf.locate_slot_with_value(&self.k);
+                let (offset, value) = leaf.locate_slot_with_value::<S::Searcher, _>(&self.k);
                 match value {
                     Some(_) => offset + 1,
                     None => offset,
@@ -192,7 +192,7 @@ impl<'k, K: Key + 'k> Cursor<K> {
     /// get the value attached to cursor, if the underlying key is deleted, this returns None
     pub fn value<'a, 'b, S: NodeStore<K = K>>(&'a self, tree: &'b BPlusTree<S>) -> Option<&'b S::V>
     where
-        'k: 'b,
+        K: 'b,
     {
         let (_, leaf) = self.locate_leaf(tree)?;
 
@@ -200,7 +200,7 @@ impl<'k, K: Key + 'k> Cursor<K> {
             Some(kv) if kv.0.eq(&self.k) => Some(kv.1),
             _ => {
                 // todo: consider update self?
-                let (_, value) = leaf.locate_slot_with_value(&self.k);
+                let (_, value) = leaf.locate_slot_with_value::<S::Searcher, _>(&self.k);
                 value
             }
         }
@@ -249,7 +249,7 @@ mod tests {
 
         assert_eq!(cursor_0.k, 0);
 
-        let mut value = vec![v_0.clone()];
+        let mut value = vec![*v_0];
         let mut keys_deleted = BTreeSet::new();
         while let Some((c, v)) = cursor_0.next_with_value(&tree) {
             println!("\n-------------------");
@@ -258,7 +258,7 @@ mod tests {
             assert!(!keys_deleted.contains(&c.k));
 
             assert_eq!(c.value(&tree).unwrap(), v);
-            value.push(v.clone());
+            value.push(*v);
 
             cursor_0 = c;
 
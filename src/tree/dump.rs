@@ -0,0 +1,341 @@
+use std::fmt::{Debug, Write};
+
+use super::{BPlusTree, InnerNodeId, LeafNodeId, NodeId, NodeStore};
+
+/// Options for `BPlusTree::dump_structure` and `BPlusTree::to_dot_with`
+#[derive(Clone, Debug)]
+pub struct DumpOptions {
+    /// Max keys or items shown per node, the rest are elided with their count.
+    /// None shows all.
+    pub max_items: Option<usize>,
+    /// Max depth to descend, root is 0. Deeper nodes are elided. None for all.
+    pub max_depth: Option<usize>,
+    /// Whether to show leaf values, or keys only
+    pub show_values: bool,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            max_items: None,
+            max_depth: None,
+            show_values: true,
+        }
+    }
+}
+
+impl DumpOptions {
+    /// Format `items` as a list, truncated by `max_items`
+    fn list<T>(&self, items: impl ExactSizeIterator<Item = T>, f: impl Fn(T) -> String) -> String {
+        let len = items.len();
+        let shown = self.max_items.unwrap_or(len).min(len);
+        let mut s = items.take(shown).map(f).collect::<Vec<_>>().join(", ");
+        if shown < len {
+            if shown > 0 {
+                s.push_str(", ");
+            }
+            write!(s, "... +{}", len - shown).unwrap();
+        }
+        format!("[{s}]")
+    }
+
+    fn is_elided(&self, depth: usize) -> bool {
+        self.max_depth.is_some_and(|max| depth > max)
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S>
+where
+    S::K: Debug,
+    S::V: Debug,
+    S::Augmentation: Debug,
+{
+    /// Dump the tree as indented text, one node per line. Each child shows the augmentation
+    /// stored in its parent, and leaves show their prev/next links.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::count::Count;
+    /// use sweep_bptree::tree::DumpOptions;
+    ///
+    /// let tree = BPlusTree::<NodeStoreVec<u32, u32, Count>>::bulk_load(
+    ///     (0..100).map(|i| (i, i)).collect(),
+    /// );
+    ///
+    /// let options = DumpOptions {
+    ///     max_items: Some(2),
+    ///     show_values: false,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(
+    ///     tree.dump_structure(&options),
+    ///     "\
+    /// inner 0 aug: Count(100) keys: [64]
+    ///   leaf 0 aug: Count(64) prev: - next: 1 items: [0, 1, ... +62]
+    ///   leaf 1 aug: Count(36) prev: 0 next: - items: [64, 65, ... +34]
+    /// "
+    /// );
+    /// ```
+    pub fn dump_structure(&self, options: &DumpOptions) -> String {
+        let mut out = String::new();
+        self.dump_node(&mut out, options, self.root, &self.root_augmentation, 0);
+        out
+    }
+
+    fn dump_node(
+        &self,
+        out: &mut String,
+        options: &DumpOptions,
+        node_id: NodeId,
+        augmentation: &S::Augmentation,
+        depth: usize,
+    ) {
+        let indent = "  ".repeat(depth);
+        if options.is_elided(depth) {
+            writeln!(out, "{indent}...").unwrap();
+            return;
+        }
+
+        match node_id {
+            NodeId::Inner(inner_id) => {
+                let inner = self.node_store.get_inner(inner_id);
+                writeln!(
+                    out,
+                    "{indent}inner {} aug: {augmentation:?} keys: {}",
+                    inner_id.as_usize(),
+                    options.list(inner.keys().iter(), |k| format!("{k:?}")),
+                )
+                .unwrap();
+
+                for (idx, child_augmentation) in inner.augmentations().iter().enumerate() {
+                    self.dump_node(
+                        out,
+                        options,
+                        inner.child_id(idx),
+                        child_augmentation,
+                        depth + 1,
+                    );
+                }
+            }
+            NodeId::Leaf(leaf_id) => {
+                let leaf = self.node_store.get_leaf(leaf_id);
+                writeln!(
+                    out,
+                    "{indent}leaf {} aug: {augmentation:?} prev: {} next: {} items: {}",
+                    leaf_id.as_usize(),
+                    link(leaf.prev()),
+                    link(leaf.next()),
+                    self.leaf_items(leaf_id, options),
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    /// Render the tree as Graphviz DOT. Inner nodes show their keys, edges to children are
+    /// labeled with the children's augmentations, leaves show their items and are chained
+    /// by prev/next edges.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<u32, &str>::new());
+    /// tree.insert(1, "one");
+    ///
+    /// let dot = tree.to_dot();
+    /// assert!(dot.starts_with("digraph BPlusTree {"));
+    /// assert!(dot.contains(r#"1: \"one\""#));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DumpOptions::default())
+    }
+
+    /// Same as `to_dot`, with options to truncate large nodes and deep trees
+    pub fn to_dot_with(&self, options: &DumpOptions) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph BPlusTree {{").unwrap();
+        writeln!(out, "  node [shape=box, fontname=monospace];").unwrap();
+        writeln!(
+            out,
+            "  root [shape=plaintext, label=\"root aug: {}\"];",
+            escape(&format!("{:?}", self.root_augmentation))
+        )
+        .unwrap();
+        writeln!(out, "  root -> {};", dot_id(self.root)).unwrap();
+
+        let mut leaves = Vec::new();
+        self.dot_node(&mut out, options, self.root, 0, &mut leaves);
+
+        // leaves on the same rank, chained by prev and next
+        if !leaves.is_empty() {
+            let ids = leaves.iter().map(|l| dot_id(NodeId::Leaf(*l)));
+            writeln!(
+                out,
+                "  {{ rank=same; {} }}",
+                ids.collect::<Vec<_>>().join("; ")
+            )
+            .unwrap();
+        }
+        for leaf_id in leaves {
+            let leaf = self.node_store.get_leaf(leaf_id);
+            if let Some(next) = leaf.next() {
+                writeln!(
+                    out,
+                    "  {} -> {} [style=dashed, constraint=false, label=\"next\"];",
+                    dot_id(NodeId::Leaf(leaf_id)),
+                    dot_id(NodeId::Leaf(next)),
+                )
+                .unwrap();
+            }
+            if let Some(prev) = leaf.prev() {
+                writeln!(
+                    out,
+                    "  {} -> {} [style=dotted, constraint=false, label=\"prev\"];",
+                    dot_id(NodeId::Leaf(leaf_id)),
+                    dot_id(NodeId::Leaf(prev)),
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    fn dot_node(
+        &self,
+        out: &mut String,
+        options: &DumpOptions,
+        node_id: NodeId,
+        depth: usize,
+        leaves: &mut Vec<LeafNodeId>,
+    ) {
+        let id = dot_id(node_id);
+        if options.is_elided(depth) {
+            writeln!(out, "  {id} [shape=plaintext, label=\"...\"];").unwrap();
+            return;
+        }
+
+        match node_id {
+            NodeId::Inner(inner_id) => {
+                let inner = self.node_store.get_inner(inner_id);
+                let keys = options.list(inner.keys().iter(), |k| format!("{k:?}"));
+                writeln!(
+                    out,
+                    "  {id} [label=\"inner {}\\nkeys: {}\"];",
+                    inner_id.as_usize(),
+                    escape(&keys)
+                )
+                .unwrap();
+
+                for (idx, augmentation) in inner.augmentations().iter().enumerate() {
+                    let child_id = inner.child_id(idx);
+                    writeln!(
+                        out,
+                        "  {id} -> {} [label=\"{idx}: {}\"];",
+                        dot_id(child_id),
+                        escape(&format!("{augmentation:?}"))
+                    )
+                    .unwrap();
+                    self.dot_node(out, options, child_id, depth + 1, leaves);
+                }
+            }
+            NodeId::Leaf(leaf_id) => {
+                let leaf = self.node_store.get_leaf(leaf_id);
+                let len = leaf.len();
+                let shown = options.max_items.unwrap_or(len).min(len);
+
+                let mut label = format!("leaf {}\\l", leaf_id.as_usize());
+                for (k, v) in leaf.keys().iter().zip(leaf.values()).take(shown) {
+                    let item = if options.show_values {
+                        format!("{k:?}: {v:?}")
+                    } else {
+                        format!("{k:?}")
+                    };
+                    write!(label, "{}\\l", escape(&item)).unwrap();
+                }
+                if shown < len {
+                    write!(label, "... +{}\\l", len - shown).unwrap();
+                }
+                writeln!(out, "  {id} [label=\"{label}\"];").unwrap();
+
+                leaves.push(leaf_id);
+            }
+        }
+    }
+
+    fn leaf_items(&self, leaf_id: LeafNodeId, options: &DumpOptions) -> String {
+        let leaf = self.node_store.get_leaf(leaf_id);
+        let items = leaf.keys().iter().zip(leaf.values());
+        if options.show_values {
+            options.list(items, |(k, v)| format!("{k:?}: {v:?}"))
+        } else {
+            options.list(items, |(k, _)| format!("{k:?}"))
+        }
+    }
+}
+
+fn link(id: Option<LeafNodeId>) -> String {
+    id.map_or("-".to_string(), |id| id.as_usize().to_string())
+}
+
+fn dot_id(id: NodeId) -> String {
+    match id {
+        NodeId::Inner(InnerNodeId(id)) => format!("i{id}"),
+        NodeId::Leaf(LeafNodeId(id)) => format!("l{id}"),
+    }
+}
+
+/// Escape text inside a quoted DOT string
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{augment::count::Count, NodeStoreVec};
+
+    #[test]
+    fn test_dump_structure_and_dot() {
+        let tree = BPlusTree::<NodeStoreVec<u32, String, Count>>::bulk_load(
+            (0..10_000).map(|i| (i, format!("v\"{i}"))).collect(),
+        );
+
+        let text = tree.dump_structure(&DumpOptions::default());
+        // one line per node
+        let leaf_count = text
+            .lines()
+            .filter(|l| l.trim().starts_with("leaf"))
+            .count();
+        assert_eq!(leaf_count, 10_000usize.div_ceil(64));
+        assert!(text.starts_with("inner"));
+        assert!(text.contains(r#"9999: "v\"9999""#));
+
+        let options = DumpOptions {
+            max_items: Some(3),
+            max_depth: Some(1),
+            show_values: false,
+        };
+        let text = tree.dump_structure(&options);
+        assert!(text.lines().all(|l| l.len() < 200));
+        assert!(text.lines().any(|l| l.trim() == "..."));
+        assert!(!text.contains("leaf"));
+
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph BPlusTree {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(dot.matches("label=\"next\"").count(), leaf_count - 1);
+        assert_eq!(dot.matches("label=\"prev\"").count(), leaf_count - 1);
+        // quotes in values are escaped
+        assert!(dot.contains(r#"9999: \"v\\\"9999\"\l"#));
+
+        let dot = tree.to_dot_with(&DumpOptions {
+            max_items: Some(1),
+            ..Default::default()
+        });
+        assert!(dot.contains("... +63\\l"));
+    }
+}
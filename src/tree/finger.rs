@@ -0,0 +1,162 @@
+use std::borrow::Borrow;
+
+use super::{BPlusTree, LeafNodeId, NodeStore};
+
+/// Max leaves a finger lookup walks through siblings before descending from root
+const FINGER_WALK: usize = 4;
+
+/// A caller held hint of the last visited leaf, lookups with it start from that leaf and walk
+/// a few siblings before falling back to descending from root. Unlike the node store's leaf
+/// cache it is private to its holder, so each reader can keep its own fingers.
+///
+/// A finger is only a hint, it stays safe to use after the tree is modified, or with another
+/// tree.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{BPlusTree, NodeStoreVec};
+/// use sweep_bptree::tree::Finger;
+///
+/// let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64>::new());
+/// for i in 0..1000 {
+///     tree.insert(i * 2, i);
+/// }
+///
+/// let mut finger = Finger::new();
+/// assert_eq!(tree.get_with_finger(&mut finger, &100), Some(&50));
+/// // nearby lookups start from the finger's leaf
+/// assert_eq!(tree.get_with_finger(&mut finger, &102), Some(&51));
+/// assert_eq!(tree.get_with_finger(&mut finger, &103), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Finger {
+    leaf: Option<LeafNodeId>,
+}
+
+impl Finger {
+    /// Create a finger pointing nowhere, the first lookup descends from root
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the leaf, the next lookup descends from root
+    pub fn reset(&mut self) {
+        self.leaf = None;
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Get reference to value identified by key, starting from `finger`'s leaf, and move
+    /// `finger` to the leaf of `k`. The node store's leaf cache is not updated.
+    pub fn get_with_finger<Q>(&self, finger: &mut Finger, k: &Q) -> Option<&S::V>
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+    {
+        let leaf_id = match finger.leaf.and_then(|id| self.walk_to_leaf(id, k)) {
+            Some(Ok(leaf_id)) => leaf_id,
+            Some(Err(gap_leaf_id)) => {
+                // k falls between two leaves, so it doesn't exist
+                finger.leaf = Some(gap_leaf_id);
+                return None;
+            }
+            None => self.locate_leaf(k)?,
+        };
+
+        finger.leaf = Some(leaf_id);
+        self.find_in_leaf(leaf_id, k)
+    }
+
+    /// Walk from `leaf_id` through siblings to the leaf whose range contains `k`. Returns
+    /// `Err` with the last visited leaf if `k` falls between two leaves, and `None` if the
+    /// leaf doesn't exist or it is too far.
+    fn walk_to_leaf<Q>(
+        &self,
+        mut leaf_id: LeafNodeId,
+        k: &Q,
+    ) -> Option<Result<LeafNodeId, LeafNodeId>>
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+    {
+        let mut leaf = self.node_store.try_get_leaf(leaf_id)?;
+        for _ in 0..=FINGER_WALK {
+            if leaf.in_range(k) {
+                return Some(Ok(leaf_id));
+            }
+
+            // out of range, so k is beyond the first or last key and that sibling exists
+            let (sibling_id, sibling, in_gap) = if k < leaf.keys().first()?.borrow() {
+                let prev_id = leaf.prev()?;
+                let prev = self.node_store.try_get_leaf(prev_id)?;
+                (prev_id, prev, k > prev.keys().last()?.borrow())
+            } else {
+                let next_id = leaf.next()?;
+                let next = self.node_store.try_get_leaf(next_id)?;
+                (next_id, next, k < next.keys().first()?.borrow())
+            };
+            if in_gap {
+                return Some(Err(leaf_id));
+            }
+            leaf_id = sibling_id;
+            leaf = sibling;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeStoreArc, NodeStoreVec};
+
+    #[test]
+    fn test_get_with_finger() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<u32, u32>::new());
+        for i in 0..10_000 {
+            tree.insert(i * 2, i);
+        }
+
+        // sequential and strided lookups, hits and misses
+        for step in [1, 7, 150, 3000] {
+            let mut finger = Finger::new();
+            for k in (0..20_010).step_by(step) {
+                assert_eq!(tree.get_with_finger(&mut finger, &k), tree.get(&k));
+            }
+            for k in (0..20_010).rev().step_by(step) {
+                assert_eq!(tree.get_with_finger(&mut finger, &k), tree.get(&k));
+            }
+        }
+
+        // fingers stay valid hints after modifications free their leaves
+        let mut fingers = (0..20_000)
+            .step_by(500)
+            .map(|k| {
+                let mut finger = Finger::new();
+                tree.get_with_finger(&mut finger, &k);
+                finger
+            })
+            .collect::<Vec<_>>();
+        for i in 0..5_000 {
+            tree.remove(&(i * 4));
+        }
+        tree.compact();
+        for (finger, k) in fingers.iter_mut().zip((0..20_000).step_by(500)) {
+            for k in k..k + 100 {
+                assert_eq!(tree.get_with_finger(finger, &k), tree.get(&k));
+            }
+        }
+
+        // a finger from another tree is only a wrong hint
+        let mut other = BPlusTree::new(NodeStoreArc::<u32, u32>::new());
+        for i in 0..100_000 {
+            other.insert(i, i);
+        }
+        let mut finger = Finger::new();
+        other.get_with_finger(&mut finger, &99_999);
+        assert_eq!(tree.get_with_finger(&mut finger, &6), Some(&3));
+        finger.reset();
+        assert_eq!(tree.get_with_finger(&mut finger, &8), None);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{borrow::Borrow, ops::Bound, ops::RangeBounds};
+
+use super::{BPlusTree, Monoid, NodeId, NodeStore};
+
+impl<S: NodeStore> BPlusTree<S>
+where
+    S::Augmentation: Monoid<S::K>,
+{
+    /// Aggregate the augmentation of all keys in `range`.
+    /// Subtrees fully covered by the range use their stored augmentation, only the
+    /// nodes on the range's boundaries are folded, so it takes O(log n).
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::count::Count;
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// assert_eq!(tree.fold_range(10..20).count(), 10);
+    /// assert_eq!(tree.fold_range(..=500).count(), 501);
+    /// assert_eq!(tree.fold_range(990..).count(), 10);
+    /// ```
+    pub fn fold_range<Q, R>(&self, range: R) -> S::Augmentation
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+        R: RangeBounds<Q>,
+    {
+        match (range.start_bound(), range.end_bound()) {
+            (Bound::Unbounded, Bound::Unbounded) => self.root_augmentation.clone(),
+            (lower, upper) => self.fold_node(self.root, lower, upper),
+        }
+    }
+
+    /// fold keys inside node `node_id` within bounds. Unbounded means the node's own bound.
+    fn fold_node<Q>(&self, node_id: NodeId, lower: Bound<&Q>, upper: Bound<&Q>) -> S::Augmentation
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+    {
+        match node_id {
+            NodeId::Inner(id) => {
+                let inner = self.node_store.get_inner(id);
+                let augmentations = inner.augmentations();
+
+                let start = match lower {
+                    Bound::Included(k) | Bound::Excluded(k) => {
+                        inner.locate_child::<S::Searcher, _>(k).0
+                    }
+                    Bound::Unbounded => 0,
+                };
+                let end = match upper {
+                    Bound::Included(k) | Bound::Excluded(k) => {
+                        inner.locate_child::<S::Searcher, _>(k).0
+                    }
+                    Bound::Unbounded => inner.len(),
+                };
+
+                if start > end {
+                    return S::Augmentation::identity();
+                }
+                if start == end {
+                    return self.fold_node(inner.child_id(start), lower, upper);
+                }
+
+                let left = match lower {
+                    Bound::Unbounded => augmentations[start].clone(),
+                    _ => self.fold_node(inner.child_id(start), lower, Bound::Unbounded),
+                };
+                let middle = S::Augmentation::combine_all(&augmentations[start + 1..end]);
+                let right = match upper {
+                    Bound::Unbounded => augmentations[end].clone(),
+                    _ => self.fold_node(inner.child_id(end), Bound::Unbounded, upper),
+                };
+
+                S::Augmentation::combine(&S::Augmentation::combine(&left, &middle), &right)
+            }
+            NodeId::Leaf(id) => {
+                let keys = self.node_store.get_leaf(id).keys();
+
+                let start = match lower {
+                    Bound::Included(k) => keys.partition_point(|x| x.borrow() < k),
+                    Bound::Excluded(k) => keys.partition_point(|x| x.borrow() <= k),
+                    Bound::Unbounded => 0,
+                };
+                let end = match upper {
+                    Bound::Included(k) => keys.partition_point(|x| x.borrow() <= k),
+                    Bound::Excluded(k) => keys.partition_point(|x| x.borrow() < k),
+                    Bound::Unbounded => keys.len(),
+                };
+
+                if start >= end {
+                    S::Augmentation::identity()
+                } else {
+                    S::Augmentation::lift_all(&keys[start..end])
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::augment::{count::Count, Monoid};
+    use crate::{BPlusTree, NodeStoreVec};
+
+    /// Keeps keys in order, so the combine order is checked
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Concat(Vec<i64>);
+
+    impl Monoid<i64> for Concat {
+        fn identity() -> Self {
+            Self(vec![])
+        }
+
+        fn lift(key: &i64) -> Self {
+            Self(vec![*key])
+        }
+
+        fn combine(left: &Self, right: &Self) -> Self {
+            Self(left.0.iter().chain(right.0.iter()).copied().collect())
+        }
+    }
+
+    #[test]
+    fn test_fold_range_count() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+        for i in 0..2000 {
+            tree.insert(i * 2, i);
+        }
+
+        for (start, end) in [
+            (0, 4000),
+            (1, 3),
+            (100, 101),
+            (501, 3333),
+            (3000, 10),
+            (-5, 7),
+        ] {
+            let expected = (start.max(0)..end.min(4000)).filter(|k| k % 2 == 0).count();
+            assert_eq!(tree.fold_range(start..end).count(), expected);
+            assert_eq!(
+                tree.fold_range((
+                    std::ops::Bound::Excluded(start),
+                    std::ops::Bound::Included(end)
+                ))
+                .count(),
+                (start.max(-1) + 1..=end.min(3998))
+                    .filter(|k| k % 2 == 0)
+                    .count()
+            );
+        }
+
+        assert_eq!(tree.fold_range(..).count(), 2000);
+        assert_eq!(tree.fold_range(..100).count(), 50);
+        assert_eq!(tree.fold_range(3900..).count(), 50);
+    }
+
+    #[test]
+    fn test_fold_range_order() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<i64, (), Concat>::new());
+        for i in (0..1000).rev() {
+            tree.insert(i, ());
+        }
+
+        assert_eq!(tree.root_augmentation().0, (0..1000).collect::<Vec<_>>());
+        assert_eq!(tree.fold_range(123..877).0, (123..877).collect::<Vec<_>>());
+        assert_eq!(tree.fold_range(..=64).0, (0..=64).collect::<Vec<_>>());
+
+        for i in 0..500 {
+            tree.remove(&(i * 2));
+        }
+        assert_eq!(
+            tree.fold_range(100..200).0,
+            (100..200).filter(|k| k % 2 == 1).collect::<Vec<_>>()
+        );
+    }
+}
@@ -96,7 +96,7 @@ impl<K: Key, A: Augmentation<K>> InnerNode<K, A> {
     }
 
     /// Minimum size of a node, if the size is less than this, then the node need to be merged
-    const fn minimum_size() -> usize {
+    pub(crate) const fn minimum_size() -> usize {
         super::consts::MIN_N
     }
 
@@ -191,6 +191,13 @@ impl<K: Key, A: Augmentation<K>> InnerNode<K, A> {
         }
     }
 
+    /// Mutable reference to the augment at index
+    pub(crate) fn augmentation_mut(&mut self, idx: usize) -> &mut A {
+        debug_assert!(idx <= self.len());
+        // Safety: augments up to len are initialized
+        unsafe { self.augmentation_area_mut(idx).assume_init_mut() }
+    }
+
     /// Update the augment at index. The previous augment will be dropped.
     pub(crate) fn set_augmentation(&mut self, idx: usize, a: A) {
         // Safety: the caller must ensure that the index is valid
@@ -200,25 +207,18 @@ impl<K: Key, A: Augmentation<K>> InnerNode<K, A> {
         }
     }
 
-    /// returns the child index for k
+    /// returns the child index for k, searched with `Se`
     #[inline]
-    pub fn locate_child<Q>(&self, k: &Q) -> (usize, NodeId)
+    pub fn locate_child<Se, Q>(&self, k: &Q) -> (usize, NodeId)
     where
+        Se: KeySearch,
         K: std::borrow::Borrow<Q>,
         Q: ?Sized + Ord,
     {
-        match self.keys().binary_search_by_key(&k, |f| f.borrow()) {
-            Err(idx) => {
-                // the idx is the place where a matching element could be inserted while maintaining
-                // sorted order. go to left child
-                (idx, self.child_id(idx))
-            }
-            Ok(idx) => {
-                // exact match, go to right child.
-                // if the child split, then the new key should inserted idx + 1
-                (idx + 1, self.child_id(idx + 1))
-            }
-        }
+        // on exact match, go to right child. if the child split, then the new key should
+        // inserted idx + 1
+        let idx = Se::search_child(self.keys(), k);
+        (idx, self.child_id(idx))
     }
 
     /// Insert `key` and its `right_child` to `slot`
@@ -254,23 +254,25 @@ impl<K: Key, A: Augmentation<K>> InnerNode<K, A> {
     /// modified node, it contains smaller half
     /// new node, it contains larger half
     /// new key, it is the key need to propagate to parent
+    /// `split_origin_size` is the key count left in the modified node, must be in `1..N`
     pub(crate) fn split(
         &mut self,
+        split_origin_size: usize,
         child_idx: usize,
         k: K,
         new_child_id: NodeId,
         new_child_augmentation: A,
     ) -> (K, Box<Self>) {
         debug_assert!(self.is_full());
+        debug_assert!(split_origin_size > 0 && split_origin_size < N);
+
+        let split_new_size = N - split_origin_size;
 
         let mut new_node = Self::empty();
-        new_node.size = Self::split_new_size() as u16;
+        new_node.size = split_new_size as u16;
 
         let new_key: K;
 
-        let split_origin_size = Self::split_origin_size();
-        let split_new_size = Self::split_new_size();
-
         self.size = split_origin_size as u16;
 
         #[allow(clippy::comparison_chain)]
@@ -626,6 +628,7 @@ impl<K: Key, A: Augmentation<K>> InnerNode<K, A> {
     }
 
     #[cfg(test)]
+    #[allow(clippy::needless_range_loop)]
     pub(crate) fn set_data<I: Into<NodeId> + Copy + Clone, const N1: usize, const C1: usize>(
         &mut self,
         slot_keys: [K; N1],
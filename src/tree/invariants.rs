@@ -0,0 +1,421 @@
+use std::fmt;
+
+use super::{
+    Augmentation, BPlusTree, InnerNode, InnerNodeId, LeafNode, LeafNodeId, NodeId, NodeStore,
+};
+
+/// A broken invariant found by `BPlusTree::check_invariants`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// Keys in node are not strictly ascending at `slot`
+    KeyOrder { node: NodeId, slot: usize },
+    /// Key at `slot` is outside of the range given by the parent's separator keys
+    KeyOutOfRange { node: NodeId, slot: usize },
+    /// Node's size is out of the fill bounds, root only requires a non empty inner node
+    FillBounds {
+        node: NodeId,
+        len: usize,
+        min: usize,
+        max: usize,
+    },
+    /// Leaf is not at the same depth as the first leaf
+    UnevenDepth {
+        node: LeafNodeId,
+        depth: usize,
+        expected: usize,
+    },
+    /// Leaf's prev link doesn't point to the previous leaf in key order
+    PrevLink {
+        node: LeafNodeId,
+        expected: Option<LeafNodeId>,
+        actual: Option<LeafNodeId>,
+    },
+    /// Leaf's next link doesn't point to the next leaf in key order
+    NextLink {
+        node: LeafNodeId,
+        expected: Option<LeafNodeId>,
+        actual: Option<LeafNodeId>,
+    },
+    /// Stored augmentation of `child` doesn't equal its recomputation
+    Augmentation { node: InnerNodeId, child: usize },
+    /// Root augmentation doesn't equal its recomputation
+    RootAugmentation,
+    /// Tree's `len` doesn't equal the element count in leaves
+    Len { expected: usize, actual: usize },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyOrder { node, slot } => {
+                write!(f, "{node:?}: keys not strictly ascending at slot {slot}")
+            }
+            Self::KeyOutOfRange { node, slot } => {
+                write!(f, "{node:?}: key at slot {slot} outside of separator range")
+            }
+            Self::FillBounds {
+                node,
+                len,
+                min,
+                max,
+            } => write!(f, "{node:?}: size {len} not in [{min}, {max}]"),
+            Self::UnevenDepth {
+                node,
+                depth,
+                expected,
+            } => write!(f, "{node:?}: leaf at depth {depth}, expected {expected}"),
+            Self::PrevLink {
+                node,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{node:?}: prev link is {actual:?}, expected {expected:?}"
+            ),
+            Self::NextLink {
+                node,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{node:?}: next link is {actual:?}, expected {expected:?}"
+            ),
+            Self::Augmentation { node, child } => {
+                write!(f, "{node:?}: augmentation of child {child} is stale")
+            }
+            Self::RootAugmentation => write!(f, "root augmentation is stale"),
+            Self::Len { expected, actual } => {
+                write!(f, "len is {actual}, but leaves contain {expected} elements")
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+impl<S: NodeStore> BPlusTree<S>
+where
+    S::Augmentation: PartialEq,
+{
+    /// Check the tree's structure, returns the first broken invariant. It verifies key order,
+    /// separator keys, node fill bounds, leaf depth and links, `len` and augmentations.
+    /// The leaf minimum follows the store's `RebalancePolicy`, and is one element if the
+    /// store's `SplitPolicy` may split leaves at the right edge.
+    ///
+    /// Time complexity is O(n), mainly used in debug builds and after loading trees.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::count::Count;
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64, Count>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    /// tree.check_invariants().unwrap();
+    /// ```
+    pub fn check_invariants(&self) -> Result<(), InvariantViolation> {
+        self.check_with(Some(|a, b| a == b))
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Quick version of `check_invariants` run after each change in tests. It skips
+    /// augmentations, so works for augmentations without `PartialEq`, and key order inside
+    /// leaves, so it costs O(nodes) instead of O(n).
+    #[cfg(test)]
+    pub(crate) fn check_structure(&self) -> Result<(), InvariantViolation> {
+        self.check_with(None)
+    }
+
+    fn check_with(
+        &self,
+        augmentation_eq: Option<AugmentationEq<S>>,
+    ) -> Result<(), InvariantViolation> {
+        let mut checker = Checker {
+            tree: self,
+            augmentation_eq,
+            leaf_depth: None,
+            prev_leaf: None,
+            prev_next: None,
+            count: 0,
+        };
+        let root_augmentation = checker.check_node(self.root, 0, None, None)?;
+
+        if let Some(eq) = augmentation_eq {
+            if !eq(&root_augmentation.unwrap(), &self.root_augmentation) {
+                return Err(InvariantViolation::RootAugmentation);
+            }
+        }
+
+        if let (Some(last), Some(next)) = (checker.prev_leaf, checker.prev_next) {
+            return Err(InvariantViolation::NextLink {
+                node: last,
+                expected: None,
+                actual: Some(next),
+            });
+        }
+
+        if checker.count != self.len {
+            return Err(InvariantViolation::Len {
+                expected: checker.count,
+                actual: self.len,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+type AugmentationEq<S> =
+    fn(&<S as NodeStore>::Augmentation, &<S as NodeStore>::Augmentation) -> bool;
+
+/// State of the in order walk
+struct Checker<'a, S: NodeStore> {
+    tree: &'a BPlusTree<S>,
+    /// compares recomputed augmentations with stored ones, `None` is the quick check which
+    /// skips augmentations and key order inside leaves
+    augmentation_eq: Option<AugmentationEq<S>>,
+    /// depth of the first leaf, all leaves should be at this depth
+    leaf_depth: Option<usize>,
+    /// the last visited leaf, and its next link
+    prev_leaf: Option<LeafNodeId>,
+    prev_next: Option<LeafNodeId>,
+    /// element count of visited leaves
+    count: usize,
+}
+
+impl<'a, S: NodeStore> Checker<'a, S> {
+    /// Check the subtree, keys should be in [lower, upper). Returns the recomputed augmentation,
+    /// or `None` for the quick check
+    fn check_node(
+        &mut self,
+        node_id: NodeId,
+        depth: usize,
+        lower: Option<&'a S::K>,
+        upper: Option<&'a S::K>,
+    ) -> Result<Option<S::Augmentation>, InvariantViolation> {
+        match node_id {
+            NodeId::Inner(inner_id) => self.check_inner(inner_id, depth, lower, upper),
+            NodeId::Leaf(leaf_id) => self.check_leaf(leaf_id, depth, lower, upper),
+        }
+    }
+
+    fn check_inner(
+        &mut self,
+        inner_id: InnerNodeId,
+        depth: usize,
+        lower: Option<&'a S::K>,
+        upper: Option<&'a S::K>,
+    ) -> Result<Option<S::Augmentation>, InvariantViolation> {
+        let node = NodeId::Inner(inner_id);
+        let inner = self.tree.node_store.get_inner(inner_id);
+        let keys = inner.keys();
+
+        let min = if depth == 0 {
+            1
+        } else {
+            InnerNode::<S::K, S::Augmentation>::minimum_size()
+        };
+        let max = InnerNode::<S::K, S::Augmentation>::max_capacity() as usize;
+        if keys.len() < min || keys.len() > max {
+            return Err(InvariantViolation::FillBounds {
+                node,
+                len: keys.len(),
+                min,
+                max,
+            });
+        }
+        check_keys(node, keys, lower, upper, true)?;
+
+        for (child, stored) in inner.augmentations().iter().enumerate() {
+            let child_lower = child.checked_sub(1).map(|i| &keys[i]).or(lower);
+            let child_upper = keys.get(child).or(upper);
+            let augmentation =
+                self.check_node(inner.child_id(child), depth + 1, child_lower, child_upper)?;
+            if let (Some(eq), Some(augmentation)) = (self.augmentation_eq, augmentation) {
+                if !eq(&augmentation, stored) {
+                    return Err(InvariantViolation::Augmentation {
+                        node: inner_id,
+                        child,
+                    });
+                }
+            }
+        }
+
+        Ok(self
+            .augmentation_eq
+            .map(|_| S::Augmentation::from_inner(keys, inner.augmentations())))
+    }
+
+    fn check_leaf(
+        &mut self,
+        leaf_id: LeafNodeId,
+        depth: usize,
+        lower: Option<&S::K>,
+        upper: Option<&S::K>,
+    ) -> Result<Option<S::Augmentation>, InvariantViolation> {
+        let node = NodeId::Leaf(leaf_id);
+        let leaf = self.tree.node_store.get_leaf(leaf_id);
+        let keys = leaf.keys();
+
+        let expected = *self.leaf_depth.get_or_insert(depth);
+        if depth != expected {
+            return Err(InvariantViolation::UnevenDepth {
+                node: leaf_id,
+                depth,
+                expected,
+            });
+        }
+
+        let min = if depth == 0 {
+            0
+        } else {
+            let rebalance_minimum = self.tree.rebalance_policy().leaf_minimum::<S::K, S::V>();
+            self.tree
+                .node_store
+                .split_policy()
+                .leaf_minimum(rebalance_minimum)
+        };
+        let max = LeafNode::<S::K, S::V>::max_capacity() as usize;
+        if keys.len() < min || keys.len() > max {
+            return Err(InvariantViolation::FillBounds {
+                node,
+                len: keys.len(),
+                min,
+                max,
+            });
+        }
+        check_keys(node, keys, lower, upper, self.augmentation_eq.is_some())?;
+
+        if leaf.prev() != self.prev_leaf {
+            return Err(InvariantViolation::PrevLink {
+                node: leaf_id,
+                expected: self.prev_leaf,
+                actual: leaf.prev(),
+            });
+        }
+        if let Some(prev_id) = self.prev_leaf {
+            if self.prev_next != Some(leaf_id) {
+                return Err(InvariantViolation::NextLink {
+                    node: prev_id,
+                    expected: Some(leaf_id),
+                    actual: self.prev_next,
+                });
+            }
+        }
+
+        self.prev_leaf = Some(leaf_id);
+        self.prev_next = leaf.next();
+        self.count += keys.len();
+
+        Ok(self
+            .augmentation_eq
+            .map(|_| S::Augmentation::from_leaf(keys)))
+    }
+}
+
+/// Check `keys` are inside [lower, upper), and strictly ascending if `check_order`
+fn check_keys<K: Ord>(
+    node: NodeId,
+    keys: &[K],
+    lower: Option<&K>,
+    upper: Option<&K>,
+    check_order: bool,
+) -> Result<(), InvariantViolation> {
+    let unordered = || keys.windows(2).position(|w| w[0] >= w[1]);
+    if let Some(slot) = check_order.then(unordered).flatten() {
+        return Err(InvariantViolation::KeyOrder {
+            node,
+            slot: slot + 1,
+        });
+    }
+
+    if let (Some(lower), Some(first)) = (lower, keys.first()) {
+        if first < lower {
+            return Err(InvariantViolation::KeyOutOfRange { node, slot: 0 });
+        }
+    }
+    if let (Some(upper), Some(last)) = (upper, keys.last()) {
+        if last >= upper {
+            return Err(InvariantViolation::KeyOutOfRange {
+                node,
+                slot: keys.len() - 1,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::{augment::count::Count, NodeStoreVec};
+
+    type Tree = BPlusTree<NodeStoreVec<u32, u32, Count>>;
+
+    #[test]
+    fn test_check_invariants() {
+        for size in [0, 1, 15, 64, 65, 64 * 65, 64 * 65 + 1, 64 * 66 + 3, 20_000] {
+            let tree = Tree::bulk_load((0..size).map(|i| (i, i)).collect());
+            tree.check_invariants().unwrap();
+        }
+
+        let mut rng = rand::thread_rng();
+        let mut keys = (0..20_000).collect::<Vec<u32>>();
+        keys.shuffle(&mut rng);
+
+        let mut tree = Tree::new(NodeStoreVec::new());
+        for (i, k) in keys.iter().enumerate() {
+            tree.insert(*k, *k);
+            if i % 997 == 0 {
+                tree.check_invariants().unwrap();
+            }
+        }
+        tree.check_invariants().unwrap();
+
+        keys.shuffle(&mut rng);
+        for (i, k) in keys.iter().take(19_000).enumerate() {
+            tree.remove(k);
+            if i % 997 == 0 {
+                tree.check_invariants().unwrap();
+            }
+        }
+        tree.check_invariants().unwrap();
+
+        let mut right = tree.split_off(&10_000);
+        tree.check_invariants().unwrap();
+        right.check_invariants().unwrap();
+        tree.append(&mut right);
+        tree.check_invariants().unwrap();
+
+        // break things on purpose
+        let NodeId::Inner(root_id) = tree.root else {
+            panic!("root should be inner");
+        };
+        tree.len += 1;
+        assert_eq!(
+            tree.check_invariants(),
+            Err(InvariantViolation::Len {
+                expected: 1000,
+                actual: 1001
+            })
+        );
+        tree.len -= 1;
+
+        tree.node_store
+            .get_mut_inner(root_id)
+            .set_augmentation(1, Count::default());
+        assert_eq!(
+            tree.check_invariants(),
+            Err(InvariantViolation::Augmentation {
+                node: root_id,
+                child: 1
+            })
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use std::iter::FusedIterator;
+use std::{
+    borrow::Borrow,
+    iter::FusedIterator,
+    ops::{Bound, RangeBounds},
+};
 
 use super::*;
 
@@ -113,8 +117,150 @@ impl<'a, S: NodeStore> DoubleEndedIterator for Iter<'a, S> {
 impl<'a, S: NodeStore> FusedIterator for Iter<'a, S> {}
 impl<'a, S: NodeStore> ExactSizeIterator for Iter<'a, S> {}
 
+/// A borrowed iterator for a sub range of BPlusTree, created by `BPlusTree::range`
+#[derive(Clone)]
+pub struct Range<'a, S: NodeStore> {
+    tree: &'a BPlusTree<S>,
+    /// the next item from front, None if exhausted
+    front: Option<(&'a LeafNode<S::K, S::V>, usize)>,
+    /// the next item from back, None if exhausted
+    back: Option<(&'a LeafNode<S::K, S::V>, usize)>,
+}
+
+impl<'a, S: NodeStore> Range<'a, S> {
+    pub(crate) fn new<Q, R>(tree: &'a BPlusTree<S>, range: R) -> Self
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+        R: RangeBounds<Q>,
+    {
+        let front = match range.start_bound() {
+            Bound::Included(k) => Self::seek_front(tree, k, |x| x < k),
+            Bound::Excluded(k) => Self::seek_front(tree, k, |x| x <= k),
+            Bound::Unbounded => tree
+                .first_leaf()
+                .map(|id| (tree.node_store.get_leaf(id), 0))
+                .filter(|(leaf, _)| !leaf.is_empty()),
+        };
+        let back = match range.end_bound() {
+            Bound::Included(k) => Self::seek_back(tree, k, |x| x <= k),
+            Bound::Excluded(k) => Self::seek_back(tree, k, |x| x < k),
+            Bound::Unbounded => tree
+                .last_leaf()
+                .map(|id| tree.node_store.get_leaf(id))
+                .filter(|leaf| !leaf.is_empty())
+                .map(|leaf| (leaf, leaf.len() - 1)),
+        };
+
+        match (front, back) {
+            (Some((f_leaf, f_offset)), Some((b_leaf, b_offset)))
+                if f_leaf.data_at(f_offset).0 <= b_leaf.data_at(b_offset).0 =>
+            {
+                Self { tree, front, back }
+            }
+            _ => Self {
+                tree,
+                front: None,
+                back: None,
+            },
+        }
+    }
+
+    /// the first item not matching `before`
+    fn seek_front<Q: ?Sized + Ord>(
+        tree: &'a BPlusTree<S>,
+        k: &Q,
+        before: impl Fn(&Q) -> bool,
+    ) -> Option<(&'a LeafNode<S::K, S::V>, usize)>
+    where
+        S::K: Borrow<Q>,
+    {
+        let leaf = tree.node_store.get_leaf(tree.locate_leaf(k)?);
+        let offset = leaf.keys().partition_point(|x| before(x.borrow()));
+        if offset < leaf.len() {
+            Some((leaf, offset))
+        } else {
+            let next = tree.node_store.get_leaf(leaf.next()?);
+            Some((next, 0))
+        }
+    }
+
+    /// the last item matching `before`
+    fn seek_back<Q: ?Sized + Ord>(
+        tree: &'a BPlusTree<S>,
+        k: &Q,
+        before: impl Fn(&Q) -> bool,
+    ) -> Option<(&'a LeafNode<S::K, S::V>, usize)>
+    where
+        S::K: Borrow<Q>,
+    {
+        let leaf = tree.node_store.get_leaf(tree.locate_leaf(k)?);
+        let offset = leaf.keys().partition_point(|x| before(x.borrow()));
+        if offset > 0 {
+            Some((leaf, offset - 1))
+        } else {
+            let prev = tree.node_store.get_leaf(leaf.prev()?);
+            Some((prev, prev.len() - 1))
+        }
+    }
+
+    fn is_last(&self) -> bool {
+        match (self.front, self.back) {
+            (Some((f_leaf, f_offset)), Some((b_leaf, b_offset))) => {
+                std::ptr::eq(f_leaf, b_leaf) && f_offset == b_offset
+            }
+            _ => true,
+        }
+    }
+}
+
+impl<'a, S: NodeStore> Iterator for Range<'a, S> {
+    type Item = (&'a S::K, &'a S::V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (leaf, offset) = self.front?;
+        let kv = leaf.data_at(offset);
+
+        if self.is_last() {
+            self.front = None;
+            self.back = None;
+        } else if offset + 1 < leaf.len() {
+            self.front = Some((leaf, offset + 1));
+        } else {
+            self.front = leaf.next().map(|id| (self.tree.node_store.get_leaf(id), 0));
+        }
+
+        Some(kv)
+    }
+}
+
+impl<'a, S: NodeStore> DoubleEndedIterator for Range<'a, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (leaf, offset) = self.back?;
+        let kv = leaf.data_at(offset);
+
+        if self.is_last() {
+            self.front = None;
+            self.back = None;
+        } else if offset > 0 {
+            self.back = Some((leaf, offset - 1));
+        } else {
+            self.back = leaf.prev().map(|id| {
+                let leaf = self.tree.node_store.get_leaf(id);
+                (leaf, leaf.len() - 1)
+            });
+        }
+
+        Some(kv)
+    }
+}
+
+impl<'a, S: NodeStore> FusedIterator for Range<'a, S> {}
+
 pub struct IntoIter<S: NodeStore> {
     node_store: S,
+    /// the tree's observer, items moved out are reported as removed
+    observer: Option<BoxedObserver<S::K, S::V>>,
     len: usize,
     /// current iterator pos
     pos: (LeafNodeId, usize),
@@ -128,12 +274,13 @@ impl<S: NodeStore> IntoIter<S> {
         let first_leaf_id = tree.first_leaf().unwrap();
         let last_leaf_id = tree.last_leaf().unwrap();
 
-        let (node_store, _root_id, len) = tree.into_parts();
+        let (node_store, _root_id, len, observer) = tree.into_parts();
 
         let last_leaf_size = node_store.get_leaf(last_leaf_id).len();
 
         Self {
             node_store,
+            observer,
             len,
             pos: (first_leaf_id, 0),
             end: (last_leaf_id, last_leaf_size),
@@ -165,11 +312,14 @@ impl<S: NodeStore> Iterator for IntoIter<S> {
             // safety: right after we called this, the pos moves to next.
             let kv = unsafe { leaf.take_data(offset) };
             self.pos = (leaf_id, offset + 1);
+            notify(self.observer.as_mut(), Change::Remove(&kv.0, &kv.1));
             Some(kv)
         } else {
             // move to next leaf
             match leaf.next() {
                 Some(next_id) => {
+                    // all data in this leaf are taken
+                    unsafe { leaf.forget_data() };
                     self.pos = (next_id, 0);
                     self.next()
                 }
@@ -200,11 +350,14 @@ impl<S: NodeStore> DoubleEndedIterator for IntoIter<S> {
             // safety: right after we called this, the pos moves to next.
             let kv = unsafe { leaf.take_data(offset - 1) };
             self.end = (leaf_id, offset - 1);
+            notify(self.observer.as_mut(), Change::Remove(&kv.0, &kv.1));
             Some(kv)
         } else {
             // move to prev leaf
             match leaf.prev() {
                 Some(prev_id) => {
+                    // all data in this leaf are taken
+                    unsafe { leaf.forget_data() };
                     let prev_leaf = self.node_store.get_mut_leaf(prev_id);
                     self.end = (prev_id, prev_leaf.len());
                     self.next_back()
@@ -223,8 +376,29 @@ impl<S: NodeStore> ExactSizeIterator for IntoIter<S> {}
 
 impl<S: NodeStore> Drop for IntoIter<S> {
     fn drop(&mut self) {
+        /// Drops the rest items and marks the leaves at both ends empty, also when the observer
+        /// or a value's drop panics. Otherwise the store drops the taken items again.
+        struct DropGuard<'a, S: NodeStore>(&'a mut IntoIter<S>);
+
+        impl<S: NodeStore> Drop for DropGuard<'_, S> {
+            fn drop(&mut self) {
+                // the observer may be the one panicked, don't call it again
+                let observer = self.0.observer.take();
+                while self.0.next().is_some() {}
+
+                // data in the leaves at both ends are all taken, the rest leaves are already
+                // marked
+                unsafe {
+                    self.0.node_store.get_mut_leaf(self.0.pos.0).forget_data();
+                    self.0.node_store.get_mut_leaf(self.0.end.0).forget_data();
+                }
+                drop(observer);
+            }
+        }
+
         // drop all the remaining items
-        while self.next().is_some() {}
+        let guard = DropGuard(self);
+        while guard.0.next().is_some() {}
     }
 }
 
@@ -303,6 +477,77 @@ mod tests {
         assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 10);
     }
 
+    #[test]
+    fn test_into_iter_partial_drop() {
+        let node_store = NodeStoreVec::<i64, TestValue>::new();
+        let mut tree = BPlusTree::new(node_store);
+        let counter = Rc::new(std::sync::atomic::AtomicU64::new(0));
+        for i in 0..1000 {
+            tree.insert(i, TestValue::new(counter.clone()));
+        }
+        let mut iter = IntoIter::new(tree);
+        for _ in 0..100 {
+            iter.next().unwrap();
+            iter.next_back().unwrap();
+        }
+        assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 200);
+
+        drop(iter);
+        assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 1000);
+    }
+
+    struct PanicValue {
+        counter: Rc<std::sync::atomic::AtomicU64>,
+        panic: bool,
+    }
+
+    impl Drop for PanicValue {
+        fn drop(&mut self) {
+            self.counter
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            assert!(!self.panic);
+        }
+    }
+
+    #[test]
+    fn test_into_iter_drop_panic() {
+        struct PanicObserver;
+
+        impl TreeObserver<i64, PanicValue> for PanicObserver {
+            fn on_remove(&mut self, key: &i64, _value: &PanicValue) {
+                assert_ne!(*key, 500);
+            }
+        }
+
+        for observed in [false, true] {
+            let mut tree = BPlusTree::new(NodeStoreVec::<i64, PanicValue>::new());
+            let counter = Rc::new(std::sync::atomic::AtomicU64::new(0));
+            for i in 0..1000 {
+                let panic = !observed && i == 500;
+                tree.insert(
+                    i,
+                    PanicValue {
+                        counter: counter.clone(),
+                        panic,
+                    },
+                );
+            }
+            if observed {
+                tree.set_observer(PanicObserver);
+            }
+
+            // the drop panics in the middle of leaves consumed from both ends
+            let mut iter = IntoIter::new(tree);
+            for _ in 0..100 {
+                iter.next().unwrap();
+                iter.next_back().unwrap();
+            }
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(iter)));
+            assert!(r.is_err());
+            assert_eq!(counter.load(std::sync::atomic::Ordering::Relaxed), 1000);
+        }
+    }
+
     #[test]
     fn test_into_iter_double_ended() {
         let node_store = NodeStoreVec::<i64, TestValue>::new();
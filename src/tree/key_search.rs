@@ -0,0 +1,185 @@
+use std::borrow::Borrow;
+
+/// Search strategy for keys in a node, leaves search for the key's slot and inner nodes
+/// search for the child to descend. A node store picks one with `NodeStore::Searcher`.
+///
+/// `BinarySearch` is the default, which is hard to beat for nodes of 64 keys unless wide
+/// vector instructions are available. `LinearSearch` scans keys branchlessly, which the compiler vectorizes for integer keys, best combined
+/// with `-C target-cpu` enabling AVX2. Run `benches/bench_node.rs` to compare on the target
+/// machine.
+pub trait KeySearch {
+    /// Search `k` in ascending `keys`, returns `Ok` with the slot of the equal key, or `Err`
+    /// with the slot to insert `k`. Same contract as `slice::binary_search`.
+    fn search<K, Q>(keys: &[K], k: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord;
+
+    /// Child index to descend for `k` in an inner node with separator `keys`, which is the
+    /// count of keys less than or equal to `k`
+    #[inline]
+    fn search_child<K, Q>(keys: &[K], k: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        match Self::search(keys, k) {
+            // exact match, go to right child
+            Ok(idx) => idx + 1,
+            Err(idx) => idx,
+        }
+    }
+}
+
+/// Binary search in nodes, the default `KeySearch`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BinarySearch;
+
+impl KeySearch for BinarySearch {
+    #[inline]
+    fn search<K, Q>(keys: &[K], k: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        keys.binary_search_by(|f| f.borrow().cmp(k))
+    }
+}
+
+/// Branchless linear scan in nodes, see `linear_search`. It is correct for any key, but only
+/// pays off for small `Copy` keys like integers.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{tree::LinearSearch, BPlusTree, NodeStoreVec};
+///
+/// let mut tree = BPlusTree::new(NodeStoreVec::<u64, u32, (), LinearSearch>::new());
+/// tree.insert(1, 1);
+/// assert_eq!(tree.get(&1), Some(&1));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearSearch;
+
+impl KeySearch for LinearSearch {
+    #[inline]
+    fn search<K, Q>(keys: &[K], k: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        linear_search(keys, k)
+    }
+
+    #[inline]
+    fn search_child<K, Q>(keys: &[K], k: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        linear_search_child(keys, k)
+    }
+}
+
+/// Branchless linear version of `slice::binary_search`, it compares `k` with every key.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::tree::linear_search;
+///
+/// assert_eq!(linear_search(&[1, 3, 5], &3), Ok(1));
+/// assert_eq!(linear_search(&[1, 3, 5], &4), Err(2));
+/// ```
+#[inline]
+pub fn linear_search<K, Q>(keys: &[K], k: &Q) -> Result<usize, usize>
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    // node size fits u32, a narrow counter vectorizes better than usize
+    let idx = keys.iter().map(|f| (f.borrow() < k) as u32).sum::<u32>() as usize;
+    match keys.get(idx) {
+        Some(f) if f.borrow() == k => Ok(idx),
+        _ => Err(idx),
+    }
+}
+
+/// Branchless linear version of `KeySearch::search_child`, the count of keys less than or
+/// equal to `k`
+#[inline]
+pub fn linear_search_child<K, Q>(keys: &[K], k: &Q) -> usize
+where
+    K: Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    keys.iter().map(|f| (f.borrow() <= k) as u32).sum::<u32>() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_linear_search_matches_binary_search() {
+        let mut rng = rand::thread_rng();
+        for len in [0, 1, 2, 31, 64] {
+            let mut keys = (0..len)
+                .map(|_| rng.gen_range(0..200))
+                .collect::<Vec<u64>>();
+            keys.sort();
+            keys.dedup();
+            let signed = keys.iter().map(|k| *k as i32 - 100).collect::<Vec<i32>>();
+
+            for k in 0..210 {
+                let expected_child = keys.partition_point(|f| *f <= k);
+                assert_eq!(linear_search(&keys, &k), keys.binary_search(&k));
+                assert_eq!(linear_search_child(&keys, &k), expected_child);
+                assert_eq!(BinarySearch::search(&keys, &k), keys.binary_search(&k));
+                assert_eq!(BinarySearch::search_child(&keys, &k), expected_child);
+
+                let k = k as i32 - 105;
+                assert_eq!(linear_search(&signed, &k), signed.binary_search(&k));
+                assert_eq!(
+                    linear_search_child(&signed, &k),
+                    signed.partition_point(|f| *f <= k)
+                );
+                assert_eq!(LinearSearch::search(&signed, &k), signed.binary_search(&k));
+                assert_eq!(
+                    LinearSearch::search_child(&signed, &k),
+                    BinarySearch::search_child(&signed, &k)
+                );
+            }
+        }
+
+        // borrowed query
+        let keys = ["a", "c"].map(String::from);
+        assert_eq!(BinarySearch::search(&keys, "b"), Err(1));
+        assert_eq!(BinarySearch::search(&keys, "c"), Ok(1));
+        assert_eq!(BinarySearch::search_child(&keys, "c"), 2);
+        assert_eq!(linear_search(&keys, "c"), Ok(1));
+        assert_eq!(linear_search_child(&keys, "b"), 1);
+    }
+
+    #[test]
+    fn test_linear_search_tree() {
+        let mut rng = rand::thread_rng();
+        let mut tree =
+            crate::BPlusTree::new(crate::NodeStoreVec::<u64, u64, (), LinearSearch>::new());
+        let mut expected = std::collections::BTreeMap::new();
+        for i in 0..20000 {
+            let k = rng.gen_range(0..5000);
+            if i % 3 == 0 {
+                assert_eq!(tree.remove(&k), expected.remove(&k));
+            } else {
+                assert_eq!(tree.insert(k, i), expected.insert(k, i));
+            }
+        }
+        tree.check_invariants().unwrap();
+
+        for k in 0..5000 {
+            assert_eq!(tree.get(&k), expected.get(&k));
+        }
+        assert!(tree.range(1000..2000).eq(expected.range(1000..2000)));
+    }
+}
@@ -54,6 +54,20 @@ where
     }
 }
 
+impl<K, V> Drop for LeafNode<K, V> {
+    fn drop(&mut self) {
+        // Safety: The data in range ..self.len() is initialized
+        unsafe {
+            for k in self.slot_key.get_unchecked_mut(..self.size as usize) {
+                k.assume_init_drop();
+            }
+            for v in self.slot_value.get_unchecked_mut(..self.size as usize) {
+                v.assume_init_drop();
+            }
+        }
+    }
+}
+
 impl<K: Key, V> LeafNode<K, V> {
     pub fn new() -> Box<Self> {
         let layout = Layout::new::<mem::MaybeUninit<Self>>();
@@ -75,6 +89,7 @@ impl<K: Key, V> LeafNode<K, V> {
         self.len() == 0
     }
 
+    #[cfg(test)]
     const fn split_origin_size() -> u16 {
         (N / 2) as u16
     }
@@ -86,7 +101,7 @@ impl<K: Key, V> LeafNode<K, V> {
 
     /// the minimum size for Leaf Node, if the node size lower than this, then
     /// it is under sized
-    const fn minimum_size() -> u16 {
+    pub(crate) const fn minimum_size() -> u16 {
         let s = (N / 4) as u16;
         if s == 0 {
             1
@@ -181,6 +196,31 @@ impl<K: Key, V> LeafNode<K, V> {
         unsafe { self.value_area_mut(slot).assume_init_mut() }
     }
 
+    /// Get key ref and value mut ref at slot
+    pub(crate) fn data_at_mut(&mut self, slot: usize) -> (&K, &mut V) {
+        debug_assert!(slot < self.len());
+        unsafe {
+            (
+                self.slot_key.get_unchecked(slot).assume_init_ref(),
+                self.slot_value.get_unchecked_mut(slot).assume_init_mut(),
+            )
+        }
+    }
+
+    /// Get keys and mutable values
+    pub(crate) fn keys_values_mut(&mut self) -> (&[K], &mut [V]) {
+        let len = self.len();
+        unsafe {
+            let keys: &[MaybeUninit<K>] = self.slot_key.get_unchecked(..len);
+            let values: &mut [MaybeUninit<V>] = self.slot_value.get_unchecked_mut(..len);
+            // SAFETY: first `len` slots are initialized, and `MaybeUninit` has the same layout as `T`
+            (
+                &*(keys as *const [MaybeUninit<K>] as *const [K]),
+                &mut *(values as *mut [MaybeUninit<V>] as *mut [V]),
+            )
+        }
+    }
+
     pub fn try_data_at(&self, idx: usize) -> Option<(&K, &V)> {
         if idx >= self.size as usize {
             return None;
@@ -210,13 +250,13 @@ impl<K: Key, V> LeafNode<K, V> {
     }
 
     /// insert / update (k, v), if node is full, then returns `LeafUpsertResult::IsFull`
-    pub(crate) fn try_upsert(&mut self, k: K, v: V) -> LeafUpsertResult<K, V> {
-        match self.locate_slot(&k) {
+    pub(crate) fn try_upsert<Se: KeySearch>(&mut self, k: K, v: V) -> LeafUpsertResult<K, V> {
+        match self.locate_slot::<Se, _>(&k) {
             Ok(idx) => {
                 // update existing item
                 let prev_v =
                     std::mem::replace(unsafe { self.value_area_mut(idx) }, MaybeUninit::new(v));
-                LeafUpsertResult::Updated(unsafe { prev_v.assume_init() })
+                LeafUpsertResult::Updated(idx, unsafe { prev_v.assume_init() })
             }
 
             Err(idx) => {
@@ -225,7 +265,7 @@ impl<K: Key, V> LeafNode<K, V> {
                     unsafe { slice_utils::slice_insert(self.key_area_mut(..new_len), idx, k) };
                     unsafe { slice_utils::slice_insert(self.value_area_mut(..new_len), idx, v) };
                     self.size = new_len as u16;
-                    LeafUpsertResult::Inserted
+                    LeafUpsertResult::Inserted(idx)
                 } else {
                     LeafUpsertResult::IsFull(idx, k, v)
                 }
@@ -233,14 +273,19 @@ impl<K: Key, V> LeafNode<K, V> {
         }
     }
 
+    /// Split the full node, keeps `split_origin_size` elements before inserting `item`.
+    /// `split_origin_size` must be in `1..N`
     pub(crate) fn split_new_leaf(
         &mut self,
+        split_origin_size: usize,
         insert_idx: usize,
         item: (K, V),
         new_leaf_id: LeafNodeId,
         self_leaf_id: LeafNodeId,
     ) -> Box<Self> {
-        let split_origin_size = Self::split_origin_size() as usize;
+        // the left leaf stays full only if the item goes to the right
+        debug_assert!(split_origin_size > 0 && split_origin_size <= N);
+        debug_assert!(split_origin_size < N || insert_idx == N);
         let split_new_size = N - split_origin_size;
 
         let mut new_node = Self::new();
@@ -293,8 +338,9 @@ impl<K: Key, V> LeafNode<K, V> {
         new_node
     }
 
-    pub(crate) fn try_delete_at(&mut self, idx: usize) -> LeafDeleteResult<K, V> {
-        if self.able_to_lend() {
+    /// Delete the item at idx if the leaf stays at or above `min_size`
+    pub(crate) fn try_delete_at(&mut self, idx: usize, min_size: usize) -> LeafDeleteResult<K, V> {
+        if self.len() > min_size {
             let result = unsafe {
                 let k = slice_utils::slice_remove(self.key_area_mut(..self.size as usize), idx);
                 let v = slice_utils::slice_remove(self.value_area_mut(..self.size as usize), idx);
@@ -307,22 +353,25 @@ impl<K: Key, V> LeafNode<K, V> {
         }
     }
 
+    /// Search `k` with `Se`, returns `Ok` with its slot or `Err` with the slot to insert it
     #[inline]
-    pub fn locate_slot<Q>(&self, k: &Q) -> Result<usize, usize>
+    pub fn locate_slot<Se, Q>(&self, k: &Q) -> Result<usize, usize>
     where
+        Se: KeySearch,
         Q: ?Sized + Ord,
         K: std::borrow::Borrow<Q>,
     {
-        self.keys().binary_search_by_key(&k, |f| f.borrow())
+        Se::search(self.keys(), k)
     }
 
     #[inline(always)]
-    pub fn locate_slot_with_value<Q>(&self, k: &Q) -> (usize, Option<&V>)
+    pub fn locate_slot_with_value<Se, Q>(&self, k: &Q) -> (usize, Option<&V>)
     where
+        Se: KeySearch,
         Q: ?Sized + Ord,
         K: Borrow<Q>,
     {
-        match self.locate_slot(k) {
+        match self.locate_slot::<Se, _>(k) {
             Ok(idx) => {
                 // exact match, go to right child.
                 // if the child split, then the new key should insert idx + 1
@@ -340,32 +389,9 @@ impl<K: Key, V> LeafNode<K, V> {
         }
     }
 
-    pub(crate) fn locate_slot_mut<Q>(&mut self, k: &Q) -> (usize, Option<&mut V>)
-    where
-        Q: ?Sized + Ord,
-        K: Borrow<Q>,
-    {
-        match self.locate_slot(k) {
-            Ok(idx) => {
-                // exact match, go to right child.
-                // if the child split, then the new key should inserted idx + 1
-                (
-                    idx,
-                    Some(unsafe { self.value_area_mut(idx).assume_init_mut() }),
-                )
-            }
-
-            Err(idx) => {
-                // the idx is the place where a matching element could be inserted while maintaining
-                // sorted order. go to left child
-                (idx, None)
-            }
-        }
-    }
-
     /// pop the last item, this is used when next sibling undersize
     pub(crate) fn pop(&mut self) -> (K, V) {
-        debug_assert!(self.able_to_lend());
+        debug_assert!(self.len() > 1);
         let last_idx = self.size as usize - 1;
         let result = unsafe {
             let k = slice_utils::slice_remove(self.key_area_mut(..self.len()), last_idx);
@@ -377,7 +403,7 @@ impl<K: Key, V> LeafNode<K, V> {
     }
 
     pub(crate) fn pop_front(&mut self) -> (K, V) {
-        debug_assert!(self.able_to_lend());
+        debug_assert!(self.len() > 1);
         let result = unsafe {
             let k = slice_utils::slice_remove(self.key_area_mut(..self.size as usize), 0);
             let v = slice_utils::slice_remove(self.value_area_mut(..self.size as usize), 0);
@@ -500,6 +526,14 @@ impl<K: Key, V> LeafNode<K, V> {
         }
     }
 
+    /// Mark the leaf as empty without dropping its data.
+    ///
+    /// # Safety
+    /// All data must already be taken out by `take_data`, otherwise they are leaked.
+    pub(crate) unsafe fn forget_data(&mut self) {
+        self.size = 0;
+    }
+
     fn extend(&mut self, (keys, values): (&mut [MaybeUninit<K>], &mut [MaybeUninit<V>])) {
         unsafe {
             slice_utils::move_to_slice(
@@ -594,8 +628,10 @@ impl<K: Key, V> LeafNode<K, V> {
 }
 
 pub enum LeafUpsertResult<K, V> {
-    Inserted,
-    Updated(V),
+    /// Inserted at slot
+    Inserted(usize),
+    /// Updated the value at slot, with the previous value
+    Updated(usize, V),
     IsFull(usize, K, V),
 }
 
@@ -639,7 +675,8 @@ mod tests {
 
         {
             let mut leaf = test_leaf();
-            let new_leaf = leaf.split_new_leaf(0, (0, 0), LeafNodeId(2), LeafNodeId(1));
+            let new_leaf =
+                leaf.split_new_leaf(split_left_size, 0, (0, 0), LeafNodeId(2), LeafNodeId(1));
 
             assert_eq!(leaf.data_vec().len(), N / 2 + 1);
             assert_eq!(new_leaf.data_vec().len(), N / 2);
@@ -648,7 +685,8 @@ mod tests {
 
         {
             let mut leaf = test_leaf();
-            let new_leaf = leaf.split_new_leaf(1, (3, 0), LeafNodeId(2), LeafNodeId(1));
+            let new_leaf =
+                leaf.split_new_leaf(split_left_size, 1, (3, 0), LeafNodeId(2), LeafNodeId(1));
 
             assert_eq!(leaf.data_vec().len(), N / 2 + 1);
             assert_eq!(new_leaf.data_vec().len(), N / 2);
@@ -658,6 +696,7 @@ mod tests {
         {
             let mut leaf = test_leaf();
             let new_leaf = leaf.split_new_leaf(
+                split_left_size,
                 split_left_size,
                 (split_left_size as i64 * 2 + 1, 0),
                 LeafNodeId(2),
@@ -673,6 +712,7 @@ mod tests {
             // split at left half's last element
             let mut leaf = test_leaf();
             let new_leaf = leaf.split_new_leaf(
+                split_left_size,
                 split_left_size - 1,
                 ((split_left_size - 1) as i64 * 2 + 1, 0),
                 LeafNodeId(2),
@@ -688,6 +728,7 @@ mod tests {
             // split at last
             let mut leaf = test_leaf();
             let new_leaf = leaf.split_new_leaf(
+                split_left_size,
                 N - 1,
                 ((N as i64 - 1) * 2 + 1, 0),
                 LeafNodeId(2),
@@ -698,6 +739,22 @@ mod tests {
             assert_eq!(new_leaf.data_vec().len(), N / 2 + 1);
             assert_ascend_2(leaf.data_vec(), new_leaf.data_vec());
         }
+
+        {
+            // uneven split, append after last
+            let mut leaf = test_leaf();
+            let new_leaf = leaf.split_new_leaf(
+                N - N / 4,
+                N,
+                (N as i64 * 2 + 1, 0),
+                LeafNodeId(2),
+                LeafNodeId(1),
+            );
+
+            assert_eq!(leaf.data_vec().len(), N - N / 4);
+            assert_eq!(new_leaf.data_vec().len(), N / 4 + 1);
+            assert_ascend_2(leaf.data_vec(), new_leaf.data_vec());
+        }
     }
     #[test]
     fn test_in_range() {
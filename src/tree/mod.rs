@@ -19,7 +19,32 @@ pub use node_stores::*;
 pub mod visit;
 
 mod bulk_load;
+mod fold;
+mod snapshot;
+pub(crate) use snapshot::Checksum;
+pub use snapshot::{Codec, SnapshotError};
+mod split;
+mod transaction;
 pub use crate::augment::*;
+pub use transaction::{Replaced, Transaction};
+mod dump;
+mod stats;
+pub use dump::DumpOptions;
+pub use stats::TreeStats;
+mod invariants;
+pub use invariants::InvariantViolation;
+mod finger;
+pub use finger::Finger;
+mod key_search;
+pub use key_search::{linear_search, linear_search_child, BinarySearch, KeySearch, LinearSearch};
+mod rebalance;
+pub use rebalance::RebalancePolicy;
+mod split_policy;
+pub use split_policy::SplitPolicy;
+pub(crate) use split_policy::Splitter;
+mod observer;
+use observer::{notify, BoxedObserver, Change};
+pub use observer::{TreeObserver, ValueMut};
 
 use self::entry_ref::{EntryRef, VisitStack};
 mod entry_ref;
@@ -83,13 +108,43 @@ mod tree_remove;
 /// // now cursor_1 should retrieve the new value
 /// assert_eq!(cursor_1.value(&tree).unwrap().0, 100.);
 /// ```
-#[derive(Clone, Debug)]
 pub struct BPlusTree<S: NodeStore> {
     root: NodeId,
     root_augmentation: S::Augmentation,
     len: usize,
     node_store: ManuallyDrop<S>,
     st: Statistic,
+    observer: Option<BoxedObserver<S::K, S::V>>,
+}
+
+impl<S: NodeStore + Clone> Clone for BPlusTree<S> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root,
+            root_augmentation: self.root_augmentation.clone(),
+            len: self.len,
+            node_store: self.node_store.clone(),
+            st: self.st.clone(),
+            // the observer tracks this tree only
+            observer: None,
+        }
+    }
+}
+
+impl<S: NodeStore + std::fmt::Debug> std::fmt::Debug for BPlusTree<S>
+where
+    S::Augmentation: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BPlusTree")
+            .field("root", &self.root)
+            .field("root_augmentation", &self.root_augmentation)
+            .field("len", &self.len)
+            .field("node_store", &self.node_store)
+            .field("st", &self.st)
+            .field("observer", &self.observer.is_some())
+            .finish()
+    }
 }
 
 impl<S> BPlusTree<S>
@@ -106,6 +161,7 @@ where
             len: 0,
 
             st: Statistic::default(),
+            observer: None,
         }
     }
 
@@ -124,10 +180,11 @@ where
             len,
 
             st: Statistic::default(),
+            observer: None,
         };
 
         #[cfg(test)]
-        me.validate();
+        me.check_structure().unwrap();
 
         me
     }
@@ -137,6 +194,11 @@ where
         &self.node_store
     }
 
+    /// Returns the root node id
+    pub(crate) fn root_id(&self) -> NodeId {
+        self.root
+    }
+
     /// Returns the number of elements in the tree.
     pub fn len(&self) -> usize {
         self.len
@@ -155,10 +217,18 @@ where
     /// Insert a new key-value pair into the tree.
     pub fn insert(&mut self, k: S::K, v: S::V) -> Option<S::V> {
         let node_id = self.root;
+        let delta = S::Augmentation::insert_delta(&k);
 
-        let result = match self.descend_insert(node_id, k, v) {
+        let (r, (leaf_id, slot)) = self.descend_insert(node_id, k, v, delta.as_ref());
+        let result = match r {
             DescendInsertResult::Inserted => {
-                self.root_augmentation = Self::new_augmentation_for_id(&self.node_store, node_id);
+                let applied = delta
+                    .as_ref()
+                    .is_some_and(|delta| self.root_augmentation.apply_insert(delta));
+                if !applied {
+                    self.root_augmentation =
+                        Self::new_augmentation_for_id(&self.node_store, node_id);
+                }
                 None
             }
             DescendInsertResult::Updated(prev_v) => Some(prev_v),
@@ -187,19 +257,31 @@ where
             self.len += 1;
         }
 
+        // report after the tree is consistent again, an observer panic leaves nothing half
+        // done. Splits above the leaf don't move items, so the slot is still valid.
+        if self.observer.is_some() {
+            let (k, new) = self.node_store.get_leaf(leaf_id).data_at(slot);
+            let change = match &result {
+                Some(old) => Change::Update(k, old, new),
+                None => Change::Insert(k, new),
+            };
+            notify(self.observer.as_mut(), change);
+        }
+
         #[cfg(test)]
-        self.validate();
+        self.check_structure().unwrap();
 
         result
     }
 
     /// consume self and return the parts. This is useful when implementing `IntoIter`
-    fn into_parts(self) -> (S, NodeId, usize) {
+    fn into_parts(self) -> (S, NodeId, usize, Option<BoxedObserver<S::K, S::V>>) {
         let mut me = ManuallyDrop::new(self);
         (
             unsafe { ManuallyDrop::take(&mut me.node_store) },
             me.root,
             me.len,
+            me.observer.take(),
         )
     }
 
@@ -212,9 +294,10 @@ where
         node_id: NodeId,
         k: S::K,
         v: S::V,
-    ) -> DescendInsertResult<S::K, S::V> {
+        delta: Option<&S::Augmentation>,
+    ) -> (DescendInsertResult<S::K, S::V>, (LeafNodeId, usize)) {
         match node_id {
-            NodeId::Inner(id) => self.insert_inner(id, k, v),
+            NodeId::Inner(id) => self.insert_inner(id, k, v, delta),
             NodeId::Leaf(leaf_id) => self.insert_leaf(leaf_id, k, v),
         }
     }
@@ -224,11 +307,12 @@ where
         mut id: InnerNodeId,
         k: S::K,
         v: S::V,
-    ) -> DescendInsertResult<S::K, S::V> {
+        delta: Option<&S::Augmentation>,
+    ) -> (DescendInsertResult<S::K, S::V>, (LeafNodeId, usize)) {
         let mut stack = VisitStack::new();
-        let mut r = loop {
+        let (mut r, inserted_at) = loop {
             let node = self.node_store.get_inner(id);
-            let (child_idx, child_id) = node.locate_child(&k);
+            let (child_idx, child_id) = node.locate_child::<S::Searcher, _>(&k);
             stack.push(id, child_idx, child_id);
 
             match child_id {
@@ -260,21 +344,36 @@ where
                             inner_node.insert_at(slot, key, right_child, right_child_augmentation);
                             r = DescendInsertResult::Inserted;
                         } else {
+                            let split_origin_size =
+                                Self::split_point_for(&mut self.node_store, false, child_idx);
+                            let inner_node = self.node_store.get_mut_inner(id);
                             let (prompt_k, new_node) = inner_node.split(
+                                split_origin_size,
                                 child_idx,
                                 key,
                                 right_child,
                                 right_child_augmentation,
                             );
                             let new_node_id = self.node_store.add_inner(new_node);
+                            self.st.split_inner += 1;
                             r = DescendInsertResult::Split(prompt_k, NodeId::Inner(new_node_id));
                         }
                     }
                     DescendInsertResult::Inserted => {
-                        let child_augmentation =
-                            Self::new_augmentation_for_id(&self.node_store, child_id);
-                        let inner_node = self.node_store.get_mut_inner(id);
-                        inner_node.set_augmentation(child_idx, child_augmentation);
+                        // the child's subtree got exactly one more key, even if nodes split
+                        // below it
+                        let applied = delta.is_some_and(|delta| {
+                            self.node_store
+                                .get_mut_inner(id)
+                                .augmentation_mut(child_idx)
+                                .apply_insert(delta)
+                        });
+                        if !applied {
+                            let child_augmentation =
+                                Self::new_augmentation_for_id(&self.node_store, child_id);
+                            let inner_node = self.node_store.get_mut_inner(id);
+                            inner_node.set_augmentation(child_idx, child_augmentation);
+                        }
 
                         continue;
                     }
@@ -283,11 +382,36 @@ where
                         continue;
                     }
                 },
-                None => return r,
+                None => return (r, inserted_at),
             }
         }
     }
 
+    /// Consult the store for the split point, clamped so both nodes keep the minimum fill.
+    /// Except a leaf split at the right edge, which may keep the left leaf full and leave only
+    /// the inserted element in the right one.
+    fn split_point_for(node_store: &mut S, is_leaf: bool, insert_idx: usize) -> usize {
+        let (capacity, min) = if is_leaf {
+            (
+                S::leaf_n() as usize,
+                LeafNode::<S::K, S::V>::minimum_size() as usize,
+            )
+        } else {
+            (
+                S::inner_n() as usize,
+                InnerNode::<S::K, S::Augmentation>::minimum_size(),
+            )
+        };
+        let max = if is_leaf && insert_idx >= capacity {
+            capacity
+        } else {
+            capacity - min
+        };
+        node_store
+            .split_point(is_leaf, insert_idx, capacity)
+            .clamp(min, max)
+    }
+
     fn new_augmentation_for_id(node_store: &S, id: NodeId) -> S::Augmentation {
         match id {
             NodeId::Inner(inner) => {
@@ -301,19 +425,26 @@ where
         }
     }
 
-    fn insert_leaf(&mut self, id: LeafNodeId, k: S::K, v: S::V) -> DescendInsertResult<S::K, S::V> {
+    /// Insert into leaf, also returns where the item ends up
+    fn insert_leaf(
+        &mut self,
+        id: LeafNodeId,
+        k: S::K,
+        v: S::V,
+    ) -> (DescendInsertResult<S::K, S::V>, (LeafNodeId, usize)) {
         let leaf_node = self.node_store.get_mut_leaf(id);
-        match leaf_node.try_upsert(k, v) {
-            LeafUpsertResult::Inserted => {
+        match leaf_node.try_upsert::<S::Searcher>(k, v) {
+            LeafUpsertResult::Inserted(slot) => {
                 self.node_store.cache_leaf(id);
-                DescendInsertResult::Inserted
+                (DescendInsertResult::Inserted, (id, slot))
             }
-            LeafUpsertResult::Updated(v) => DescendInsertResult::Updated(v),
+            LeafUpsertResult::Updated(slot, old) => (DescendInsertResult::Updated(old), (id, slot)),
             LeafUpsertResult::IsFull(idx, k, v) => {
+                let split_origin_size = Self::split_point_for(&mut self.node_store, true, idx);
                 let right_id = self.node_store.reserve_leaf();
 
                 let l_leaf = self.node_store.get_mut_leaf(id);
-                let r_leaf = l_leaf.split_new_leaf(idx, (k, v), right_id, id);
+                let r_leaf = l_leaf.split_new_leaf(split_origin_size, idx, (k, v), right_id, id);
                 let slot_key: S::K = r_leaf.data_at(0).0.clone();
 
                 // fix r_leaf's next's prev
@@ -322,13 +453,17 @@ where
                 }
                 self.node_store.assign_leaf(right_id, r_leaf);
 
-                let updated_id = if idx >= S::leaf_n() as usize / 2 {
-                    right_id
+                let (updated_id, slot) = if idx >= split_origin_size {
+                    (right_id, idx - split_origin_size)
                 } else {
-                    id
+                    (id, idx)
                 };
                 self.node_store.cache_leaf(updated_id);
-                DescendInsertResult::Split(slot_key, NodeId::Leaf(right_id))
+                self.st.split_leaf += 1;
+                (
+                    DescendInsertResult::Split(slot_key, NodeId::Leaf(right_id)),
+                    (updated_id, slot),
+                )
             }
         }
     }
@@ -347,21 +482,64 @@ where
     }
 
     /// Get mutable reference to value identified by key.
+    ///
+    /// # Observers
+    /// Changes through the returned reference are NOT reported to the observer installed by
+    /// `set_observer`. Use `get_mut_observed` for that.
     pub fn get_mut<Q: ?Sized + Ord>(&mut self, k: &Q) -> Option<&mut S::V>
     where
         S::K: Borrow<Q>,
     {
-        let mut cache_leaf_id: Option<LeafNodeId> = None;
-        if let Some(leaf_id) = self.node_store.try_cache(k) {
-            // cache hit
-            cache_leaf_id = Some(leaf_id);
-        }
+        self.locate_mut(k).map(|(_, v)| v)
+    }
 
-        if let Some(leaf_id) = cache_leaf_id {
-            return self.find_in_leaf_mut(leaf_id, k);
-        }
+    /// Same as `get_mut`, but the observer is notified if the value is written through the
+    /// returned `ValueMut`, when it is dropped.
+    pub fn get_mut_observed<Q: ?Sized + Ord>(&mut self, k: &Q) -> Option<ValueMut<'_, S::K, S::V>>
+    where
+        S::K: Borrow<Q>,
+    {
+        let observer = self.observer.as_mut();
+        let (key, value) = Self::locate_mut_in(&mut self.node_store, self.root, k)?;
+        Some(ValueMut::new(key, value, observer))
+    }
+
+    fn locate_mut<Q: ?Sized + Ord>(&mut self, k: &Q) -> Option<(&S::K, &mut S::V)>
+    where
+        S::K: Borrow<Q>,
+    {
+        Self::locate_mut_in(&mut self.node_store, self.root, k)
+    }
+
+    /// Find the item of `k`, split from `&mut self` so the observer can be borrowed along
+    fn locate_mut_in<'a, Q: ?Sized + Ord>(
+        node_store: &'a mut S,
+        root: NodeId,
+        k: &Q,
+    ) -> Option<(&'a S::K, &'a mut S::V)>
+    where
+        S::K: Borrow<Q>,
+    {
+        let leaf_id = match node_store.try_cache(k) {
+            // cache hit
+            Some(leaf_id) => leaf_id,
+            None => {
+                let mut node_id = root;
+                while let NodeId::Inner(inner_id) = node_id {
+                    node_id = node_store
+                        .get_inner(inner_id)
+                        .locate_child::<S::Searcher, _>(k)
+                        .1;
+                }
+                let leaf_id = node_id.leaf_id()?;
+                node_store.cache_leaf(leaf_id);
+                leaf_id
+            }
+        };
 
-        self.find_descend_mut(self.root, k)
+        let leaf = node_store.get_mut_leaf(leaf_id);
+        let slot = leaf.locate_slot::<S::Searcher, _>(k).ok()?;
+        Some(leaf.data_at_mut(slot))
     }
 
     /// Returns first key-value pair in the map.
@@ -416,7 +594,7 @@ where
             match node_id {
                 NodeId::Inner(inner_id) => {
                     let inner_node = self.node_store.get_inner(inner_id);
-                    let (_, child_id) = inner_node.locate_child(k);
+                    let (_, child_id) = inner_node.locate_child::<S::Searcher, _>(k);
                     node_id = child_id;
                     continue;
                 }
@@ -430,44 +608,7 @@ where
         S::K: Borrow<Q>,
     {
         let leaf_node = self.node_store.get_leaf(leaf_id);
-        let (_, kv) = leaf_node.locate_slot_with_value(k);
-        kv
-    }
-
-    fn find_descend_mut<Q: ?Sized + Ord>(&mut self, node_id: NodeId, k: &Q) -> Option<&mut S::V>
-    where
-        S::K: Borrow<Q>,
-    {
-        match node_id {
-            NodeId::Inner(inner_id) => {
-                let inner_node = self.node_store.get_inner(inner_id);
-                let (_, child_id) = inner_node.locate_child(k);
-                self.find_descend_mut(child_id, k)
-            }
-            NodeId::Leaf(leaf_id) => self.find_in_leaf_mut_and_cache_it(leaf_id, k),
-        }
-    }
-
-    fn find_in_leaf_mut<Q: ?Sized + Ord>(&mut self, leaf_id: LeafNodeId, k: &Q) -> Option<&mut S::V>
-    where
-        S::K: Borrow<Q>,
-    {
-        let leaf_node = self.node_store.get_mut_leaf(leaf_id);
-        let (_, v) = leaf_node.locate_slot_mut(k);
-        v
-    }
-
-    fn find_in_leaf_mut_and_cache_it<Q: ?Sized + Ord>(
-        &mut self,
-        leaf_id: LeafNodeId,
-        k: &Q,
-    ) -> Option<&mut S::V>
-    where
-        S::K: Borrow<Q>,
-    {
-        self.node_store.cache_leaf(leaf_id);
-        let leaf_node = self.node_store.get_mut_leaf(leaf_id);
-        let (_, kv) = leaf_node.locate_slot_mut(k);
+        let (_, kv) = leaf_node.locate_slot_with_value::<S::Searcher, _>(k);
         kv
     }
 
@@ -483,7 +624,7 @@ where
     {
         self.node_store.cache_leaf(leaf_id);
         let leaf = self.node_store.get_leaf(leaf_id);
-        let (_, v) = leaf.locate_slot_with_value(k);
+        let (_, v) = leaf.locate_slot_with_value::<S::Searcher, _>(k);
         v
     }
 
@@ -493,7 +634,9 @@ where
         Q: ?Sized + Ord,
         S::K: Borrow<Q>,
     {
-        self.remove_impl(k).map(|kv| kv.1)
+        let (k, v) = self.remove_impl(k)?;
+        notify(self.observer.as_mut(), Change::Remove(&k, &v));
+        Some(v)
     }
 
     fn key_to_ref<Q>(&self, k: &Q) -> Option<EntryRef<&Self>>
@@ -507,7 +650,7 @@ where
             match node_id {
                 NodeId::Inner(inner_id) => {
                     let inner_node = self.node_store.get_inner(inner_id);
-                    let (child_offset, child_id) = inner_node.locate_child(k);
+                    let (child_offset, child_id) = inner_node.locate_child::<S::Searcher, _>(k);
                     inner_stack.push(inner_id, child_offset, child_id);
 
                     node_id = child_id;
@@ -515,7 +658,7 @@ where
                 NodeId::Leaf(leaf_id) => {
                     let leaf = self.node_store.get_leaf(leaf_id);
 
-                    match leaf.locate_slot(k) {
+                    match leaf.locate_slot::<S::Searcher, _>(k) {
                         Ok(idx) => return Some(EntryRef::new(self, inner_stack, leaf_id, idx)),
                         Err(_) => return None,
                     };
@@ -524,6 +667,34 @@ where
         }
     }
 
+    /// Key of the `n`th element. Without a count augmentation subtree sizes are unknown, so
+    /// it walks leaves from the nearer end and skips each by its length, visiting
+    /// O(n / leaf size) leaves instead of every element.
+    pub(crate) fn nth_key(&self, n: usize) -> Option<&S::K> {
+        if n >= self.len {
+            return None;
+        }
+
+        if n < self.len / 2 {
+            let mut leaf = self.node_store.get_leaf(self.first_leaf()?);
+            let mut n = n;
+            while n >= leaf.len() {
+                n -= leaf.len();
+                leaf = self.node_store.get_leaf(leaf.next()?);
+            }
+            Some(leaf.data_at(n).0)
+        } else {
+            // index from the back
+            let mut leaf = self.node_store.get_leaf(self.last_leaf()?);
+            let mut n = self.len - 1 - n;
+            while n >= leaf.len() {
+                n -= leaf.len();
+                leaf = self.node_store.get_leaf(leaf.prev()?);
+            }
+            Some(leaf.data_at(leaf.len() - 1 - n).0)
+        }
+    }
+
     /// get the first leaf_id if exists
     pub(crate) fn first_leaf(&self) -> Option<LeafNodeId> {
         match self.root {
@@ -573,7 +744,11 @@ where
     /// Locate the leaf node for `k`.
     /// Returns the leaf whose range contains `k`.
     /// User should query the leaf and check key existance.
-    pub(crate) fn locate_leaf(&self, k: &S::K) -> Option<LeafNodeId> {
+    pub(crate) fn locate_leaf<Q>(&self, k: &Q) -> Option<LeafNodeId>
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+    {
         if let Some(leaf_id) = self.node_store.try_cache(k) {
             return Some(leaf_id);
         }
@@ -582,7 +757,7 @@ where
             NodeId::Inner(inner_id) => {
                 let mut result = None;
                 self.descend_visit_inner(inner_id, |inner_node| {
-                    let (_idx, node_id) = inner_node.locate_child(k);
+                    let (_idx, node_id) = inner_node.locate_child::<S::Searcher, _>(k);
                     match node_id {
                         NodeId::Inner(inner_node) => Some(inner_node),
                         NodeId::Leaf(leaf_id) => {
@@ -616,10 +791,36 @@ where
     }
 
     /// Create an iterator on (&K, &V) pairs
-    pub fn iter(&self) -> iterator::Iter<S> {
+    pub fn iter(&self) -> iterator::Iter<'_, S> {
         iterator::Iter::new(self)
     }
 
+    /// Create an iterator on (&K, &V) pairs inside `range`
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64>::new());
+    /// for i in 0..100 {
+    ///     tree.insert(i, i * 2);
+    /// }
+    ///
+    /// let keys = tree.range(10..13).map(|(k, _)| *k).collect::<Vec<_>>();
+    /// assert_eq!(keys, vec![10, 11, 12]);
+    ///
+    /// let keys = tree.range(..=2).rev().map(|(k, _)| *k).collect::<Vec<_>>();
+    /// assert_eq!(keys, vec![2, 1, 0]);
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> iterator::Range<'_, S>
+    where
+        Q: ?Sized + Ord,
+        S::K: Borrow<Q>,
+        R: std::ops::RangeBounds<Q>,
+    {
+        iterator::Range::new(self, range)
+    }
+
     /// Create a `Cursor` from first elem if exists
     ///
     pub fn cursor_first(&self) -> Option<Cursor<S::K>> {
@@ -633,7 +834,7 @@ where
             NodeId::Inner(inner_id) => {
                 let mut result = None;
                 self.descend_visit_inner(inner_id, |inner_node| {
-                    let (_idx, node_id) = inner_node.locate_child(k);
+                    let (_idx, node_id) = inner_node.locate_child::<S::Searcher, _>(k);
                     match node_id {
                         NodeId::Inner(inner_node) => Some(inner_node),
                         NodeId::Leaf(leaf_id) => {
@@ -648,14 +849,20 @@ where
         }?;
 
         let leaf = self.node_store.get_leaf(leaf_id);
-        let (idx, v) = leaf.locate_slot_with_value(k);
+        let (idx, v) = leaf.locate_slot_with_value::<S::Searcher, _>(k);
         Some((Cursor::new(k.clone(), leaf_id, idx), v))
     }
 
     /// Clear the tree
     pub fn clear(&mut self) {
         // todo: should we keep the node_store's capacity?
-        std::mem::drop(std::mem::replace(self, Self::new(S::default())));
+        let mut old = std::mem::replace(self, Self::new(self.node_store.empty_like()));
+        self.observer = old.observer.take();
+        if self.observer.is_some() {
+            for (k, v) in old.iter() {
+                notify(self.observer.as_mut(), Change::Remove(k, v));
+            }
+        }
     }
 
     /// get by augment
@@ -704,7 +911,27 @@ where
     }
 
     /// get mut reference to value by augmentation Query
+    ///
+    /// # Observers
+    /// Changes through the returned reference are NOT reported to the observer installed by
+    /// `set_observer`. Use `get_mut_by_augmentation_observed` for that.
     pub fn get_mut_by_augmentation<Q>(&mut self, query: Q) -> Option<&mut S::V>
+    where
+        S::Augmentation: SearchAugmentation<S::K, Query = Q>,
+    {
+        let entry_ref = self.get_ref_by_augmentation(query)?;
+        let EntryRef {
+            leaf_id, offset, ..
+        } = entry_ref;
+        Some(self.node_store.get_mut_leaf(leaf_id).data_at_mut(offset).1)
+    }
+
+    /// Same as `get_mut_by_augmentation`, but the observer is notified if the value is
+    /// written through the returned `ValueMut`, when it is dropped.
+    pub fn get_mut_by_augmentation_observed<Q>(
+        &mut self,
+        query: Q,
+    ) -> Option<ValueMut<'_, S::K, S::V>>
     where
         S::Augmentation: SearchAugmentation<S::K, Query = Q>,
     {
@@ -726,7 +953,7 @@ where
             match node_id {
                 NodeId::Inner(inner_id) => {
                     let inner = self.node_store.get_inner(inner_id);
-                    let (child_idx, child_id) = inner.locate_child(k);
+                    let (child_idx, child_id) = inner.locate_child::<S::Searcher, _>(k);
                     node_id = child_id;
                     let augmentations = &inner.augmentations()[0..child_idx];
                     rank = <S::Augmentation as RankAugmentation<S::K>>::fold_inner(
@@ -737,7 +964,7 @@ where
                 }
                 NodeId::Leaf(leaf_id) => {
                     let leaf = self.node_store.get_leaf(leaf_id);
-                    let slot = leaf.locate_slot(k);
+                    let slot = leaf.locate_slot::<S::Searcher, _>(k);
                     return <S::Augmentation as RankAugmentation<_>>::fold_leaf(
                         k,
                         rank,
@@ -755,7 +982,9 @@ where
         S::Augmentation: SearchAugmentation<S::K, Query = Q>,
     {
         let entry_ref = self.get_ref_by_augmentation(query)?;
-        Self::remove_by_ref(entry_ref.into_detached().into_ref(self))
+        let (k, v) = Self::remove_by_ref(entry_ref.into_detached().into_ref(self))?;
+        notify(self.observer.as_mut(), Change::Remove(&k, &v));
+        Some((k, v))
     }
 
     /// Get the (&K, &V) pair for `EntryRef`
@@ -765,8 +994,8 @@ where
         Some(leaf.data_at(slot))
     }
 
-    /// Get the &mut V for reference
-    fn get_mut_by_ref(entry_ref: EntryRef<&mut Self>) -> &mut S::V {
+    /// Get the `ValueMut` for reference
+    fn get_mut_by_ref(entry_ref: EntryRef<&mut Self>) -> ValueMut<'_, S::K, S::V> {
         let EntryRef {
             tree,
             leaf_id,
@@ -775,41 +1004,15 @@ where
         } = entry_ref;
 
         let leaf = tree.node_store.get_mut_leaf(leaf_id);
-        let slot = offset;
-        leaf.value_at_mut(slot)
-    }
-
-    #[cfg(test)]
-    fn validate(&self) {
-        let Some(mut leaf_id) = self.first_leaf() else {
-            return;
-        };
-        let mut last_leaf_id: Option<LeafNodeId> = None;
-
-        // ensures all prev and next are correct
-        loop {
-            let leaf = self.node_store.get_leaf(leaf_id);
-
-            let p = leaf.prev();
-            let n = leaf.next();
-
-            if let Some(last_leaf_id) = last_leaf_id {
-                assert_eq!(last_leaf_id, p.unwrap());
-            }
-
-            if n.is_none() {
-                break;
-            }
-
-            last_leaf_id = Some(leaf_id);
-            leaf_id = n.unwrap();
-        }
+        let (key, value) = leaf.data_at_mut(offset);
+        ValueMut::new(key, value, tree.observer.as_mut())
     }
 }
 
 impl<S: NodeStore> Drop for BPlusTree<S> {
     fn drop(&mut self) {
-        unsafe { drop(std::ptr::read(self).into_iter()) }
+        // Safety: node_store is never used after drop, nodes drop their own data
+        unsafe { ManuallyDrop::drop(&mut self.node_store) }
     }
 }
 
@@ -835,11 +1038,12 @@ where
     }
 }
 
-impl<K, V, A> Default for BPlusTree<NodeStoreVec<K, V, A>>
+impl<K, V, A, Se> Default for BPlusTree<NodeStoreVec<K, V, A, Se>>
 where
     K: Default + Key,
     V: Default,
     A: Augmentation<K>,
+    Se: KeySearch,
 {
     fn default() -> Self {
         Self::new(NodeStoreVec::new())
@@ -870,6 +1074,14 @@ pub struct Statistic {
 
     pub merge_with_left_leaf: u64,
     pub merge_with_right_leaf: u64,
+
+    pub split_inner: u64,
+    pub split_leaf: u64,
+
+    /// leaves freed under `RebalancePolicy::FreeAtEmpty`
+    pub free_empty_leaf: u64,
+    /// calls of `BPlusTree::compact`
+    pub compact: u64,
 }
 
 enum DescendInsertResult<K, V> {
@@ -892,6 +1104,9 @@ pub trait NodeStore: Default {
     /// The Augmentation type
     type Augmentation: Augmentation<Self::K>;
 
+    /// How nodes search keys, see `KeySearch`
+    type Searcher: KeySearch;
+
     /// Get the max number of keys inner node can hold
     fn inner_n() -> u16;
     /// Get the max number of elements leaf node can hold
@@ -968,6 +1183,43 @@ pub trait NodeStore: Default {
         Q: ?Sized + Ord,
         Self::K: Borrow<Q>;
 
+    /// Size of the left node when a full node of `capacity` splits to insert at `insert_idx`,
+    /// `insert_idx == capacity` means appending after the last slot. Stores holding a
+    /// `SplitPolicy` consult it, the default splits in halves. The tree clamps the result
+    /// to the fill bounds, a leaf split at the right edge may go up to `capacity`.
+    fn split_point(&mut self, is_leaf: bool, insert_idx: usize, capacity: usize) -> usize {
+        let _ = (is_leaf, insert_idx);
+        capacity / 2
+    }
+
+    /// The split policy, it decides whether non root leaves may hold fewer elements than
+    /// the minimum fill
+    fn split_policy(&self) -> SplitPolicy {
+        SplitPolicy::Even
+    }
+
+    /// The policy deletes use to rebalance leaves
+    fn rebalance_policy(&self) -> RebalancePolicy {
+        RebalancePolicy::Eager
+    }
+
+    /// An empty store with the same configuration, like policies and cache strategy. Used
+    /// when the tree is rebuilt, e.g. by `clear` and `compact`.
+    fn empty_like(&self) -> Self {
+        Self::default()
+    }
+
+    /// Count of node slots not holding a node, slots are not reused until rebuild
+    fn free_slots(&self) -> usize {
+        0
+    }
+
+    /// Estimated heap bytes of nodes and slot tables, memory owned by keys and values is
+    /// not included
+    fn heap_bytes(&self) -> usize {
+        0
+    }
+
     #[cfg(test)]
     fn debug(&self)
     where
@@ -1110,6 +1362,58 @@ mod tests {
         assert!(kv.is_none());
     }
 
+    #[test]
+    fn test_range() {
+        use std::ops::{Bound, RangeBounds};
+
+        let (tree, _) = create_test_tree::<1000>();
+        let keys = (0..1000).collect::<Vec<i64>>();
+
+        let bounds = [
+            Bound::Unbounded,
+            Bound::Included(-1),
+            Bound::Included(0),
+            Bound::Excluded(0),
+            Bound::Included(63),
+            Bound::Excluded(64),
+            Bound::Included(500),
+            Bound::Excluded(999),
+            Bound::Included(999),
+            Bound::Excluded(1000),
+        ];
+        for lower in bounds {
+            for upper in bounds {
+                let expected = keys
+                    .iter()
+                    .filter(|k| (lower, upper).contains(*k))
+                    .collect::<Vec<_>>();
+                let forward = tree
+                    .range((lower, upper))
+                    .map(|(k, _)| k)
+                    .collect::<Vec<_>>();
+                assert_eq!(forward, expected);
+
+                let mut backward = tree
+                    .range((lower, upper))
+                    .rev()
+                    .map(|(k, _)| k)
+                    .collect::<Vec<_>>();
+                backward.reverse();
+                assert_eq!(backward, expected);
+            }
+        }
+
+        // iterate from both sides
+        let mut range = tree.range(10..20);
+        assert_eq!(range.next().unwrap().0, &10);
+        assert_eq!(range.next_back().unwrap().0, &19);
+        assert_eq!(range.count(), 8);
+
+        let empty = BPlusTree::new(NodeStoreVec::<i64, i64>::new());
+        assert!(empty.range(..).next().is_none());
+        assert!(empty.range(1..3).next_back().is_none());
+    }
+
     pub fn create_test_tree<const N: usize>() -> (BPlusTree<NodeStoreVec<i64, i64>>, Vec<i64>) {
         let node_store = NodeStoreVec::<i64, i64>::new();
         let mut tree = BPlusTree::new(node_store);
@@ -1142,7 +1446,7 @@ mod tests {
 
     impl PartialOrd for TestKey {
         fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-            self.key.partial_cmp(&other.key)
+            Some(self.cmp(other))
         }
     }
 
@@ -1210,7 +1514,7 @@ mod tests {
 
     #[test]
     fn test_drop() {
-        let count: u64 = 16000;
+        let count: u64 = if cfg!(miri) { 1000 } else { 16000 };
         // test drop
         let node_store = NodeStoreVec::<TestKey, TestValue>::new();
         let mut tree = BPlusTree::new(node_store);
@@ -1267,4 +1571,121 @@ mod tests {
         );
         assert_eq!(key_counter.load(std::sync::atomic::Ordering::Relaxed), 0);
     }
+
+    /// Live key count and value drop count shared by a drop-tracked tree
+    #[derive(Default)]
+    struct DropCounts {
+        keys: Rc<std::sync::atomic::AtomicU64>,
+        values: Rc<std::sync::atomic::AtomicU64>,
+        panic_flag: Rc<std::sync::atomic::AtomicU64>,
+    }
+
+    impl DropCounts {
+        fn key(&self, key: i32) -> TestKey {
+            TestKey::new(key, self.keys.clone(), self.panic_flag.clone())
+        }
+
+        fn tree<S: NodeStore<K = TestKey, V = TestValue>>(
+            &self,
+            store: S,
+            keys: std::ops::Range<i32>,
+        ) -> BPlusTree<S> {
+            let mut tree = BPlusTree::new(store);
+            for i in keys {
+                tree.insert(self.key(i), TestValue::new(self.values.clone()));
+            }
+            tree
+        }
+
+        fn live_keys(&self) -> u64 {
+            self.keys.load(std::sync::atomic::Ordering::Relaxed)
+        }
+
+        fn dropped_values(&self) -> u64 {
+            self.values.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    fn check_drop_counts<S: NodeStore<K = TestKey, V = TestValue, Augmentation = ()>>(
+        new_store: impl Fn() -> S,
+    ) {
+        const N: i32 = if cfg!(miri) { 300 } else { 2000 };
+
+        // into_iter consumed partially from the front, then from the back
+        for (front, back) in [(700, 0), (0, 700), (300, 400), (N as usize, 0)] {
+            let counts = DropCounts::default();
+            let mut iter = counts.tree(new_store(), 0..N).into_iter();
+            for i in 0..front {
+                assert_eq!(iter.next().unwrap().0.key, i as i32);
+            }
+            for i in 0..back {
+                assert_eq!(iter.next_back().unwrap().0.key, N - 1 - i as i32);
+            }
+            assert_eq!(counts.dropped_values(), (front + back) as u64);
+            drop(iter);
+            assert_eq!(counts.dropped_values(), N as u64);
+            assert_eq!(counts.live_keys(), 0);
+        }
+
+        // panic while consuming into_iter
+        {
+            let counts = DropCounts::default();
+            let tree = counts.tree(new_store(), 0..N);
+            let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                for (k, _v) in tree {
+                    assert_ne!(k.key, N / 3);
+                }
+            }));
+            assert!(r.is_err());
+            assert_eq!(counts.dropped_values(), N as u64);
+            assert_eq!(counts.live_keys(), 0);
+        }
+
+        // removes merge leaves, compact moves the rest into fresh nodes
+        {
+            let counts = DropCounts::default();
+            let mut tree = counts.tree(new_store(), 0..N);
+            for i in (0..N).filter(|i| i % 4 != 0) {
+                assert!(tree.remove(&counts.key(i)).is_some());
+            }
+            assert_eq!(counts.dropped_values(), (N - N / 4) as u64);
+            tree.compact();
+            tree.check_invariants().unwrap();
+            assert_eq!(counts.dropped_values(), (N - N / 4) as u64);
+            assert_eq!(tree.len(), (N / 4) as usize);
+            drop(tree);
+            assert_eq!(counts.dropped_values(), N as u64);
+            assert_eq!(counts.live_keys(), 0);
+        }
+
+        // split_off through both the small side and the rebuild path, then append back
+        {
+            let counts = DropCounts::default();
+            let mut tree = counts.tree(new_store(), 0..N);
+            for at in [N / 2, N - 10, 10] {
+                let mut right = tree.split_off(&counts.key(at));
+                assert_eq!(counts.dropped_values(), 0);
+                tree.append(&mut right);
+                assert_eq!(counts.dropped_values(), 0);
+                assert_eq!(tree.len(), N as usize);
+            }
+
+            // overlapping append drops the replaced values only
+            let mut other = counts.tree(new_store(), N - 5..N + 5);
+            tree.append(&mut other);
+            assert_eq!(counts.dropped_values(), 5);
+            let right = tree.split_off(&counts.key(N / 2));
+            drop(tree);
+            assert_eq!(counts.dropped_values(), 5 + (N / 2) as u64);
+            drop(right);
+            assert_eq!(counts.dropped_values(), 10 + N as u64);
+            assert_eq!(counts.live_keys(), 0);
+        }
+    }
+
+    #[test]
+    fn test_drop_counts() {
+        check_drop_counts(NodeStoreVec::<TestKey, TestValue>::new);
+        check_drop_counts(NodeStoreArc::<TestKey, TestValue>::new);
+    }
 }
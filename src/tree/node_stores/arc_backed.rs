@@ -0,0 +1,597 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::tree::{
+    Augmentation, BinarySearch, CacheStrategy, InnerNode, InnerNodeId, Key, KeySearch, LeafCache,
+    LeafNode, LeafNodeId, NodeStore, RebalancePolicy, SplitPolicy, Splitter,
+};
+
+const BITS: u32 = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+/// A chunk of the persistent vector, either holds child chunks or the slots
+enum Chunk<T> {
+    Branch(Vec<Arc<Chunk<T>>>),
+    Leaf(Vec<Option<Arc<T>>>),
+}
+
+impl<T> Clone for Chunk<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Branch(children) => Self::Branch(children.clone()),
+            Self::Leaf(slots) => Self::Leaf(slots.clone()),
+        }
+    }
+}
+
+/// Persistent vector of `Option<Arc<T>>`, a radix tree with `Arc` shared chunks.
+/// Clone is O(1), and updating one slot copies the chunks on its path only.
+struct PersistentVec<T> {
+    root: Arc<Chunk<T>>,
+    len: usize,
+    /// shift of the root chunk, 0 means the root is a leaf chunk
+    shift: u32,
+}
+
+impl<T> Clone for PersistentVec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.clone(),
+            len: self.len,
+            shift: self.shift,
+        }
+    }
+}
+
+impl<T> Default for PersistentVec<T> {
+    fn default() -> Self {
+        Self {
+            root: Arc::new(Chunk::Leaf(Vec::new())),
+            len: 0,
+            shift: 0,
+        }
+    }
+}
+
+impl<T> PersistentVec<T> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Count of slots holding an item
+    fn occupied(&self) -> usize {
+        (0..self.len).filter(|idx| self.get(*idx).is_some()).count()
+    }
+
+    fn get(&self, idx: usize) -> Option<&Arc<T>> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let mut chunk = self.root.as_ref();
+        let mut shift = self.shift;
+        loop {
+            match chunk {
+                Chunk::Branch(children) => {
+                    chunk = children[(idx >> shift) & MASK].as_ref();
+                    shift -= BITS;
+                }
+                Chunk::Leaf(slots) => return slots[idx & MASK].as_ref(),
+            }
+        }
+    }
+
+    /// Get the mut slot, chunks on the path are copied if shared
+    fn slot_mut(&mut self, idx: usize) -> &mut Option<Arc<T>> {
+        assert!(idx < self.len, "index out of bounds");
+
+        let mut chunk = Arc::make_mut(&mut self.root);
+        let mut shift = self.shift;
+        loop {
+            match chunk {
+                Chunk::Branch(children) => {
+                    chunk = Arc::make_mut(&mut children[(idx >> shift) & MASK]);
+                    shift -= BITS;
+                }
+                Chunk::Leaf(slots) => return &mut slots[idx & MASK],
+            }
+        }
+    }
+
+    fn push(&mut self, item: Option<Arc<T>>) {
+        // root is full, grow one level
+        if self.len == 1 << (self.shift + BITS) {
+            let old_root = std::mem::replace(&mut self.root, Arc::new(Chunk::Branch(Vec::new())));
+            Arc::make_mut(&mut self.root).push_child(old_root);
+            self.shift += BITS;
+        }
+
+        let idx = self.len;
+        let mut chunk = Arc::make_mut(&mut self.root);
+        let mut shift = self.shift;
+        loop {
+            match chunk {
+                Chunk::Branch(children) => {
+                    let child_idx = (idx >> shift) & MASK;
+                    if child_idx == children.len() {
+                        let child = if shift == BITS {
+                            Chunk::Leaf(Vec::with_capacity(WIDTH))
+                        } else {
+                            Chunk::Branch(Vec::with_capacity(WIDTH))
+                        };
+                        children.push(Arc::new(child));
+                    }
+                    chunk = Arc::make_mut(&mut children[child_idx]);
+                    shift -= BITS;
+                }
+                Chunk::Leaf(slots) => {
+                    slots.push(item);
+                    break;
+                }
+            }
+        }
+
+        self.len += 1;
+    }
+}
+
+impl<T> Chunk<T> {
+    fn push_child(&mut self, child: Arc<Chunk<T>>) {
+        match self {
+            Chunk::Branch(children) => children.push(child),
+            Chunk::Leaf(_) => unreachable!("only branch chunk holds children"),
+        }
+    }
+}
+
+/// A persistent `NodeStore`, nodes are `Arc` shared and copied on write.
+///
+/// Cloning the store is O(1), the clone shares all nodes with the origin. When a node is
+/// mutated, only the node itself and the slots leading to it are copied, so a snapshot and
+/// the live tree share all unchanged nodes.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{BPlusTree, NodeStoreArc};
+///
+/// let mut tree = BPlusTree::new(NodeStoreArc::<u64, u64>::new());
+/// for i in 0..1000 {
+///     tree.insert(i, i);
+/// }
+///
+/// let snapshot = tree.snapshot();
+/// tree.insert(0, 100);
+/// tree.remove(&1);
+///
+/// assert_eq!(snapshot.get(&0), Some(&0));
+/// assert_eq!(snapshot.get(&1), Some(&1));
+/// assert_eq!(tree.get(&0), Some(&100));
+/// assert_eq!(tree.get(&1), None);
+/// ```
+pub struct NodeStoreArc<K: Key, V, A: Augmentation<K> = (), Se: KeySearch = BinarySearch> {
+    inner_nodes: PersistentVec<InnerNode<K, A>>,
+    leaf_nodes: PersistentVec<LeafNode<K, V>>,
+
+    leaf_cache: LeafCache,
+    splitter: Splitter,
+    rebalance: RebalancePolicy,
+    searcher: PhantomData<Se>,
+}
+
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> Clone for NodeStoreArc<K, V, A, Se> {
+    fn clone(&self) -> Self {
+        Self {
+            inner_nodes: self.inner_nodes.clone(),
+            leaf_nodes: self.leaf_nodes.clone(),
+            leaf_cache: self.leaf_cache.clone(),
+            splitter: self.splitter.clone(),
+            rebalance: self.rebalance,
+            searcher: PhantomData,
+        }
+    }
+}
+
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> Default for NodeStoreArc<K, V, A, Se> {
+    fn default() -> Self {
+        Self {
+            inner_nodes: Default::default(),
+            leaf_nodes: Default::default(),
+            leaf_cache: LeafCache::default(),
+            splitter: Splitter::default(),
+            rebalance: RebalancePolicy::default(),
+            searcher: PhantomData,
+        }
+    }
+}
+
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> std::fmt::Debug for NodeStoreArc<K, V, A, Se> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeStoreArc")
+            .field("inner_nodes", &self.inner_nodes.len())
+            .field("leaf_nodes", &self.leaf_nodes.len())
+            .finish()
+    }
+}
+
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> NodeStoreArc<K, V, A, Se> {
+    /// Create a new `NodeStoreArc`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the policy to split full nodes, see `SplitPolicy`
+    pub fn with_split_policy(mut self, policy: SplitPolicy) -> Self {
+        self.set_split_policy(policy);
+        self
+    }
+
+    /// Set the policy to split full nodes, it applies to later splits
+    pub fn set_split_policy(&mut self, policy: SplitPolicy) {
+        self.splitter = Splitter::new(policy);
+    }
+
+    /// The policy to split full nodes
+    pub fn split_policy(&self) -> SplitPolicy {
+        self.splitter.policy()
+    }
+
+    /// Set the policy deletes use to rebalance leaves, see `RebalancePolicy`
+    pub fn with_rebalance_policy(mut self, policy: RebalancePolicy) -> Self {
+        self.set_rebalance_policy(policy);
+        self
+    }
+
+    /// Set the policy deletes use to rebalance leaves, it applies to later deletes.
+    /// Switching to a stricter policy doesn't fix existing leaves, call `BPlusTree::compact`
+    /// for that.
+    pub fn set_rebalance_policy(&mut self, policy: RebalancePolicy) {
+        self.rebalance = policy;
+    }
+
+    /// The policy deletes use to rebalance leaves
+    pub fn rebalance_policy(&self) -> RebalancePolicy {
+        self.rebalance
+    }
+
+    /// Set where recently used leaves are cached, see `CacheStrategy`
+    pub fn with_cache_strategy(mut self, strategy: CacheStrategy) -> Self {
+        self.set_cache_strategy(strategy);
+        self
+    }
+
+    /// Set where recently used leaves are cached, the cache is reset
+    pub fn set_cache_strategy(&mut self, strategy: CacheStrategy) {
+        self.leaf_cache = LeafCache::new(strategy);
+    }
+
+    /// Where recently used leaves are cached
+    pub fn cache_strategy(&self) -> CacheStrategy {
+        self.leaf_cache.strategy()
+    }
+}
+
+impl<K: Key, V: Clone, A: Augmentation<K>, Se: KeySearch> NodeStore for NodeStoreArc<K, V, A, Se> {
+    type K = K;
+    type V = V;
+    type Augmentation = A;
+    type Searcher = Se;
+
+    fn inner_n() -> u16 {
+        InnerNode::<K, A>::max_capacity()
+    }
+
+    fn leaf_n() -> u16 {
+        LeafNode::<K, V>::max_capacity()
+    }
+
+    #[cfg(test)]
+    fn debug(&self)
+    where
+        K: std::fmt::Debug,
+        V: std::fmt::Debug + Clone,
+        A: std::fmt::Debug,
+    {
+        println!("{self:?}")
+    }
+
+    #[cfg(test)]
+    fn new_empty_inner(&mut self) -> InnerNodeId {
+        self.add_inner(InnerNode::<K, A>::empty())
+    }
+
+    fn new_empty_leaf(&mut self) -> (LeafNodeId, &mut LeafNode<K, V>) {
+        let id = LeafNodeId::from_usize(self.leaf_nodes.len());
+        self.leaf_nodes
+            .push(Some(Arc::from(LeafNode::<K, V>::new())));
+        (id, self.get_mut_leaf(id))
+    }
+
+    fn add_inner(&mut self, node: Box<InnerNode<K, A>>) -> InnerNodeId {
+        let id = InnerNodeId::from_usize(self.inner_nodes.len());
+        self.inner_nodes.push(Some(Arc::from(node)));
+        id
+    }
+
+    #[inline]
+    fn get_inner(&self, id: InnerNodeId) -> &InnerNode<K, A> {
+        self.try_get_inner(id).unwrap()
+    }
+
+    fn try_get_inner(&self, id: InnerNodeId) -> Option<&InnerNode<K, A>> {
+        self.inner_nodes.get(id.as_usize()).map(|n| n.as_ref())
+    }
+
+    #[inline]
+    fn get_mut_inner(&mut self, id: InnerNodeId) -> &mut InnerNode<K, A> {
+        let node = self.inner_nodes.slot_mut(id.as_usize()).as_mut().unwrap();
+        Arc::make_mut(node)
+    }
+
+    unsafe fn get_mut_inner_ptr(&mut self, id: InnerNodeId) -> *mut InnerNode<K, A> {
+        // the node is unique after make_mut, and its address is stable until it is removed
+        self.get_mut_inner(id) as *mut _
+    }
+
+    fn take_inner(&mut self, id: InnerNodeId) -> Box<InnerNode<K, A>> {
+        let node = self.inner_nodes.slot_mut(id.as_usize()).take().unwrap();
+        Box::new(Arc::unwrap_or_clone(node))
+    }
+
+    fn put_back_inner(&mut self, id: InnerNodeId, node: Box<InnerNode<K, A>>) {
+        *self.inner_nodes.slot_mut(id.as_usize()) = Some(Arc::from(node));
+    }
+
+    fn reserve_leaf(&mut self) -> LeafNodeId {
+        let id = LeafNodeId::from_usize(self.leaf_nodes.len());
+        self.leaf_nodes.push(None);
+        id
+    }
+
+    #[inline]
+    fn get_leaf(&self, id: LeafNodeId) -> &LeafNode<K, V> {
+        self.try_get_leaf(id).unwrap()
+    }
+
+    fn try_get_leaf(&self, id: LeafNodeId) -> Option<&LeafNode<K, V>> {
+        self.leaf_nodes.get(id.as_usize()).map(|n| n.as_ref())
+    }
+
+    #[inline]
+    fn get_mut_leaf(&mut self, id: LeafNodeId) -> &mut LeafNode<K, V> {
+        let node = self.leaf_nodes.slot_mut(id.as_usize()).as_mut().unwrap();
+        Arc::make_mut(node)
+    }
+
+    fn take_leaf(&mut self, id: LeafNodeId) -> Box<LeafNode<K, V>> {
+        let node = self.leaf_nodes.slot_mut(id.as_usize()).take().unwrap();
+        Box::new(Arc::unwrap_or_clone(node))
+    }
+
+    fn assign_leaf(&mut self, id: LeafNodeId, leaf: Box<LeafNode<K, V>>) {
+        *self.leaf_nodes.slot_mut(id.as_usize()) = Some(Arc::from(leaf));
+    }
+
+    fn split_point(&mut self, is_leaf: bool, insert_idx: usize, capacity: usize) -> usize {
+        self.splitter.split_point(is_leaf, insert_idx, capacity)
+    }
+
+    fn split_policy(&self) -> SplitPolicy {
+        self.splitter.policy()
+    }
+
+    fn rebalance_policy(&self) -> RebalancePolicy {
+        self.rebalance
+    }
+
+    fn empty_like(&self) -> Self {
+        Self {
+            splitter: Splitter::new(self.splitter.policy()),
+            rebalance: self.rebalance,
+            leaf_cache: LeafCache::new(self.leaf_cache.strategy()),
+            ..Self::default()
+        }
+    }
+
+    fn free_slots(&self) -> usize {
+        self.inner_nodes.len() - self.inner_nodes.occupied() + self.leaf_nodes.len()
+            - self.leaf_nodes.occupied()
+    }
+
+    /// Nodes shared with other stores are counted too
+    fn heap_bytes(&self) -> usize {
+        // node plus the two counters of `Arc`
+        let arc_overhead = 2 * std::mem::size_of::<usize>();
+        let slot = std::mem::size_of::<Option<Arc<()>>>();
+        self.inner_nodes.occupied() * (std::mem::size_of::<InnerNode<K, A>>() + arc_overhead)
+            + self.leaf_nodes.occupied() * (std::mem::size_of::<LeafNode<K, V>>() + arc_overhead)
+            + (self.inner_nodes.len() + self.leaf_nodes.len()) * slot
+    }
+
+    fn cache_leaf(&self, leaf_id: LeafNodeId) {
+        self.leaf_cache.put(leaf_id);
+    }
+
+    fn try_cache<Q>(&self, k: &Q) -> Option<LeafNodeId>
+    where
+        Q: ?Sized + Ord,
+        Self::K: std::borrow::Borrow<Q>,
+    {
+        // try_get_leaf returns None for usize:MAX, so empty slots are never hit
+        self.leaf_cache
+            .get(|id| self.try_get_leaf(id).is_some_and(|l| l.in_range(k)))
+    }
+}
+
+impl<K: Key, V: Clone, A: Augmentation<K>, Se: KeySearch>
+    crate::BPlusTree<NodeStoreArc<K, V, A, Se>>
+{
+    /// Take a snapshot of the tree, takes O(1).
+    ///
+    /// The snapshot shares all nodes with the tree, and nodes are copied when either side
+    /// mutates them, so updates on the tree are not visible in the snapshot and vice versa.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreArc};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreArc::<u64, u64>::new());
+    /// tree.insert(1, 1);
+    ///
+    /// let snapshot = tree.snapshot();
+    /// tree.insert(2, 2);
+    ///
+    /// assert_eq!(snapshot.len(), 1);
+    /// assert_eq!(tree.len(), 2);
+    /// ```
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// ensure NodeStoreArc is send for send + sync v
+fn _ensure_send<V: Send + Sync>() {
+    fn _assert_send<T: Send>() {}
+    _assert_send::<NodeStoreArc<u64, V>>();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, rc::Rc};
+
+    use rand::Rng;
+
+    use super::*;
+    use crate::{augment::count::Count, BPlusTree};
+
+    #[test]
+    fn test_persistent_vec() {
+        let mut v = PersistentVec::<usize>::default();
+        for i in 0..5000 {
+            v.push(Some(Arc::new(i)));
+        }
+
+        let snapshot = v.clone();
+        for i in (0..5000).step_by(3) {
+            *v.slot_mut(i) = Some(Arc::new(i * 2));
+        }
+
+        for i in 0..5000 {
+            assert_eq!(**snapshot.get(i).unwrap(), i);
+            let expected = if i % 3 == 0 { i * 2 } else { i };
+            assert_eq!(**v.get(i).unwrap(), expected);
+        }
+        assert!(v.get(5000).is_none());
+    }
+
+    #[test]
+    fn test_snapshot_isolation() {
+        let mut rng = rand::thread_rng();
+        let mut tree = BPlusTree::new(NodeStoreArc::<u32, u32, Count>::new());
+        let mut expected = BTreeMap::new();
+        let mut snapshots = Vec::new();
+
+        for round in 0..10 {
+            for _ in 0..2000 {
+                let k = rng.gen_range(0..5000);
+                if rng.gen_bool(0.7) {
+                    tree.insert(k, round);
+                    expected.insert(k, round);
+                } else {
+                    tree.remove(&k);
+                    expected.remove(&k);
+                }
+            }
+            snapshots.push((tree.snapshot(), expected.clone()));
+        }
+
+        for (snapshot, expected) in snapshots.iter() {
+            assert_eq!(snapshot.len(), expected.len());
+            assert_eq!(snapshot.root_augmentation().count(), expected.len());
+            assert!(snapshot.iter().eq(expected.iter()));
+        }
+    }
+
+    #[test]
+    fn test_snapshot_shares_nodes() {
+        let mut tree = BPlusTree::new(NodeStoreArc::<u32, u32>::new());
+        for i in 0..10000 {
+            tree.insert(i, i);
+        }
+
+        let snapshot = tree.snapshot();
+        tree.insert(0, 100);
+
+        // only the leaf holding key 0 is copied
+        let first_leaf = tree.first_leaf().unwrap();
+        let shared = |id| {
+            std::ptr::eq(
+                tree.node_store.get_leaf(id),
+                snapshot.node_store.get_leaf(id),
+            )
+        };
+        assert!(!shared(first_leaf));
+        let mut leaf_id = tree.node_store.get_leaf(first_leaf).next();
+        while let Some(id) = leaf_id {
+            assert!(shared(id));
+            leaf_id = tree.node_store.get_leaf(id).next();
+        }
+        assert_eq!(snapshot.get(&0), Some(&0));
+    }
+
+    struct TestValue {
+        clones: Rc<std::sync::atomic::AtomicU64>,
+        drops: Rc<std::sync::atomic::AtomicU64>,
+    }
+
+    impl Clone for TestValue {
+        fn clone(&self) -> Self {
+            self.clones
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Self {
+                clones: self.clones.clone(),
+                drops: self.drops.clone(),
+            }
+        }
+    }
+
+    impl Drop for TestValue {
+        fn drop(&mut self) {
+            self.drops
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_drop() {
+        let clones = Rc::new(std::sync::atomic::AtomicU64::new(0));
+        let drops = Rc::new(std::sync::atomic::AtomicU64::new(0));
+        macro_rules! get {
+            ($id: ident) => {
+                $id.load(std::sync::atomic::Ordering::Relaxed)
+            };
+        }
+
+        let mut tree = BPlusTree::new(NodeStoreArc::<u32, TestValue>::new());
+        for i in 0..1000 {
+            let v = TestValue {
+                clones: clones.clone(),
+                drops: drops.clone(),
+            };
+            tree.insert(i, v);
+        }
+
+        let snapshot = tree.snapshot();
+        assert_eq!(get!(clones), 0);
+
+        // only the mutated leaf is copied
+        drop(tree.remove(&0));
+        assert!(get!(clones) > 0 && get!(clones) <= 64);
+
+        // dropping the tree only drops the copied values
+        drop(tree);
+        assert_eq!(get!(drops), get!(clones));
+
+        drop(snapshot);
+        assert_eq!(get!(drops), get!(clones) + 1000);
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::tree::LeafNodeId;
+
+/// Max slots of `CacheStrategy::Lru`
+const LRU_MAX_SLOTS: usize = 8;
+
+/// Max stores a thread keeps a leaf for under `CacheStrategy::ThreadLocal`
+const THREAD_SLOTS: usize = 8;
+
+/// Where a node store remembers the recently used leaf, so lookups near it skip the descent
+/// from root. Cached leaves are only hints, they are checked before use.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{BPlusTree, NodeStoreVec};
+/// use sweep_bptree::tree::CacheStrategy;
+///
+/// let store = NodeStoreVec::<u64, u64>::new().with_cache_strategy(CacheStrategy::ThreadLocal);
+/// let mut tree = BPlusTree::new(store);
+/// for i in 0..1000 {
+///     tree.insert(i, i);
+/// }
+///
+/// let tree = &tree;
+/// std::thread::scope(|s| {
+///     // each thread keeps its own hint
+///     s.spawn(|| assert!((0..500).all(|i| tree.get(&i) == Some(&i))));
+///     s.spawn(|| assert!((500..1000).all(|i| tree.get(&i) == Some(&i))));
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheStrategy {
+    /// No cache, every lookup descends from root
+    None,
+    /// One slot shared by all threads
+    #[default]
+    Global,
+    /// One slot per thread, threads scanning different regions don't evict each other
+    ThreadLocal,
+    /// A few slots shared by all threads, most recently used first. The slot count is
+    /// clamped to `1..=8`.
+    Lru(usize),
+}
+
+static NEXT_CACHE_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// (cache id, leaf id) pairs of this thread, most recently used first
+    static THREAD_CACHE: RefCell<[(usize, usize); THREAD_SLOTS]> =
+        const { RefCell::new([(usize::MAX, usize::MAX); THREAD_SLOTS]) };
+}
+
+/// Leaf cache of a node store, implements `NodeStore::cache_leaf` and `NodeStore::try_cache`
+#[derive(Debug)]
+pub(crate) struct LeafCache {
+    strategy: CacheStrategy,
+    /// the global slot, or slots of lru
+    slots: [AtomicUsize; LRU_MAX_SLOTS],
+    /// identifies this cache in thread local slots
+    id: usize,
+}
+
+impl Default for LeafCache {
+    fn default() -> Self {
+        Self::new(CacheStrategy::default())
+    }
+}
+
+impl Clone for LeafCache {
+    fn clone(&self) -> Self {
+        let cloned = Self::new(self.strategy);
+        for (to, from) in cloned.slots.iter().zip(self.slots.iter()) {
+            to.store(from.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+        cloned
+    }
+}
+
+impl LeafCache {
+    pub(crate) fn new(strategy: CacheStrategy) -> Self {
+        let strategy = match strategy {
+            CacheStrategy::Lru(n) => CacheStrategy::Lru(n.clamp(1, LRU_MAX_SLOTS)),
+            s => s,
+        };
+        Self {
+            strategy,
+            slots: std::array::from_fn(|_| AtomicUsize::new(usize::MAX)),
+            id: NEXT_CACHE_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn strategy(&self) -> CacheStrategy {
+        self.strategy
+    }
+
+    /// Remember `leaf_id` as recently used
+    pub(crate) fn put(&self, leaf_id: LeafNodeId) {
+        let leaf_id = leaf_id.as_usize();
+        match self.strategy {
+            CacheStrategy::None => {}
+            CacheStrategy::Global => self.slots[0].store(leaf_id, Ordering::Relaxed),
+            CacheStrategy::ThreadLocal => THREAD_CACHE.with(|slots| {
+                let mut slots = slots.borrow_mut();
+                let pos = slots
+                    .iter()
+                    .position(|(id, _)| *id == self.id)
+                    .unwrap_or(THREAD_SLOTS - 1);
+                slots[..=pos].rotate_right(1);
+                slots[0] = (self.id, leaf_id);
+            }),
+            CacheStrategy::Lru(n) => {
+                if self.slots[0].load(Ordering::Relaxed) == leaf_id {
+                    return;
+                }
+                self.promote(n, leaf_id);
+            }
+        }
+    }
+
+    /// Returns the cached leaf which `is_hit` accepts
+    pub(crate) fn get(&self, is_hit: impl Fn(LeafNodeId) -> bool) -> Option<LeafNodeId> {
+        match self.strategy {
+            CacheStrategy::None => None,
+            CacheStrategy::Global => {
+                Some(LeafNodeId(self.slots[0].load(Ordering::Relaxed))).filter(|id| is_hit(*id))
+            }
+            CacheStrategy::ThreadLocal => {
+                let leaf_id = THREAD_CACHE.with(|slots| {
+                    let slots = slots.borrow();
+                    slots.iter().find(|(id, _)| *id == self.id).map(|s| s.1)
+                })?;
+                Some(LeafNodeId(leaf_id)).filter(|id| is_hit(*id))
+            }
+            CacheStrategy::Lru(n) => {
+                let (idx, leaf_id) = self.slots[..n]
+                    .iter()
+                    .map(|s| LeafNodeId(s.load(Ordering::Relaxed)))
+                    .enumerate()
+                    .find(|(_, id)| is_hit(*id))?;
+                if idx > 0 {
+                    self.promote(n, leaf_id.as_usize());
+                }
+                Some(leaf_id)
+            }
+        }
+    }
+
+    /// Move `leaf_id` to the first lru slot. Concurrent updates may lose or duplicate slots,
+    /// which only costs a miss.
+    fn promote(&self, n: usize, leaf_id: usize) {
+        let pos = self.slots[..n]
+            .iter()
+            .position(|s| s.load(Ordering::Relaxed) == leaf_id)
+            .unwrap_or(n - 1);
+        for i in (1..=pos).rev() {
+            let prev = self.slots[i - 1].load(Ordering::Relaxed);
+            self.slots[i].store(prev, Ordering::Relaxed);
+        }
+        self.slots[0].store(leaf_id, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BPlusTree, NodeStoreArc, NodeStoreVec};
+
+    #[test]
+    fn test_leaf_cache() {
+        let hit = |ids: &'static [usize]| move |id: LeafNodeId| ids.contains(&id.as_usize());
+
+        let none = LeafCache::new(CacheStrategy::None);
+        none.put(LeafNodeId(1));
+        assert_eq!(none.get(hit(&[1])), None);
+
+        let global = LeafCache::new(CacheStrategy::Global);
+        global.put(LeafNodeId(1));
+        global.put(LeafNodeId(2));
+        assert_eq!(global.get(hit(&[1])), None);
+        assert_eq!(global.get(hit(&[2])), Some(LeafNodeId(2)));
+
+        let lru = LeafCache::new(CacheStrategy::Lru(2));
+        lru.put(LeafNodeId(1));
+        lru.put(LeafNodeId(2));
+        assert_eq!(lru.get(hit(&[1])), Some(LeafNodeId(1)));
+        // 1 is promoted, so 2 is evicted
+        lru.put(LeafNodeId(3));
+        assert_eq!(lru.get(hit(&[2])), None);
+        assert_eq!(lru.get(hit(&[1])), Some(LeafNodeId(1)));
+        assert_eq!(
+            LeafCache::new(CacheStrategy::Lru(100)).strategy(),
+            CacheStrategy::Lru(8)
+        );
+
+        // thread local slots are per cache and per thread
+        let a = LeafCache::new(CacheStrategy::ThreadLocal);
+        let b = a.clone();
+        a.put(LeafNodeId(1));
+        b.put(LeafNodeId(2));
+        assert_eq!(a.get(hit(&[1, 2])), Some(LeafNodeId(1)));
+        assert_eq!(b.get(hit(&[1, 2])), Some(LeafNodeId(2)));
+        std::thread::scope(|s| {
+            s.spawn(|| assert_eq!(a.get(hit(&[1, 2])), None));
+        });
+
+        for strategy in [
+            CacheStrategy::None,
+            CacheStrategy::Global,
+            CacheStrategy::ThreadLocal,
+            CacheStrategy::Lru(4),
+        ] {
+            let mut tree =
+                BPlusTree::new(NodeStoreArc::<u32, u32>::new().with_cache_strategy(strategy));
+            for i in 0..10_000 {
+                tree.insert(i, i);
+            }
+            for i in (0..10_000).step_by(3) {
+                tree.remove(&i);
+            }
+
+            let tree = &tree;
+            std::thread::scope(|s| {
+                for t in 0..4 {
+                    s.spawn(move || {
+                        for i in t * 2500..(t + 1) * 2500 {
+                            let expected = (i % 3 != 0).then_some(i);
+                            assert_eq!(tree.get(&i), expected.as_ref());
+                        }
+                    });
+                }
+            });
+
+            let mut vec_tree =
+                BPlusTree::new(NodeStoreVec::<u32, u32>::new().with_cache_strategy(strategy));
+            for i in (0..10_000).rev() {
+                vec_tree.insert(i, i);
+                assert_eq!(vec_tree.get(&i), Some(&i));
+            }
+            assert_eq!(vec_tree.node_store().cache_strategy(), strategy);
+        }
+    }
+}
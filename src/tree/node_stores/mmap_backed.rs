@@ -0,0 +1,686 @@
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    marker::PhantomData,
+    mem::{align_of, size_of},
+    path::Path,
+};
+
+use memmap2::{Mmap, MmapMut};
+
+use crate::tree::{
+    Augmentation, BPlusTree, BinarySearch, CacheStrategy, InnerNode, InnerNodeId, Key, KeySearch,
+    LeafCache, LeafNode, LeafNodeId, NodeId, NodeStore, NodeStoreVec,
+};
+
+const MAGIC: &[u8; 8] = b"SBPTMMAP";
+const VERSION: u64 = 1;
+/// Header is a fixed count of u64 fields, see `Header`
+const HEADER_SIZE: usize = 16 * 8;
+
+/// Marker for plain old data types, which can be read from mapped file as is.
+///
+/// # Safety
+/// The type must be `Copy`, contain no pointers or references, and every bit pattern written
+/// by the same build must be valid when read back.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    ()
+);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+unsafe impl<A: Pod, B: Pod> Pod for (A, B) {}
+unsafe impl Pod for crate::augment::count::Count {}
+unsafe impl Pod for crate::augment::weighted::WeightedSum {}
+
+/// File header, all fields are stored as u64 in native endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    /// size and alignment of node types, plus node capacities and item sizes. The file is only
+    /// readable by a build with the same layout
+    layout: [u64; 8],
+    len: u64,
+    root_is_leaf: u64,
+    root_id: u64,
+    inner_count: u64,
+    leaf_count: u64,
+}
+
+impl Header {
+    fn layout<K: Key, V, A: Augmentation<K>>() -> [u64; 8] {
+        [
+            size_of::<InnerNode<K, A>>() as u64,
+            align_of::<InnerNode<K, A>>() as u64,
+            size_of::<LeafNode<K, V>>() as u64,
+            align_of::<LeafNode<K, V>>() as u64,
+            InnerNode::<K, A>::max_capacity() as u64,
+            LeafNode::<K, V>::max_capacity() as u64,
+            size_of::<K>() as u64,
+            size_of::<V>() as u64,
+        ]
+    }
+
+    fn fields(&self) -> [u64; 14] {
+        let mut fields = [0; 14];
+        fields[..8].copy_from_slice(&self.layout);
+        fields[8..].copy_from_slice(&[
+            VERSION,
+            self.len,
+            self.root_is_leaf,
+            self.root_id,
+            self.inner_count,
+            self.leaf_count,
+        ]);
+        fields
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        buf[..8].copy_from_slice(MAGIC);
+        for (idx, f) in self.fields().iter().enumerate() {
+            let offset = 8 + idx * 8;
+            buf[offset..offset + 8].copy_from_slice(&f.to_ne_bytes());
+        }
+    }
+
+    fn read(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() < HEADER_SIZE || &buf[..8] != MAGIC {
+            return Err(invalid_data("not a mmap tree file"));
+        }
+
+        let mut fields = [0u64; 14];
+        for (idx, f) in fields.iter_mut().enumerate() {
+            let offset = 8 + idx * 8;
+            *f = u64::from_ne_bytes(buf[offset..offset + 8].try_into().unwrap());
+        }
+        if fields[8] != VERSION {
+            return Err(invalid_data("unsupported mmap tree file version"));
+        }
+
+        Ok(Self {
+            layout: fields[..8].try_into().unwrap(),
+            len: fields[9],
+            root_is_leaf: fields[10],
+            root_id: fields[11],
+            inner_count: fields[12],
+            leaf_count: fields[13],
+        })
+    }
+
+    /// Returns offsets of inner node area and leaf node area, and the total file size
+    fn offsets<K: Key, V, A: Augmentation<K>>(&self) -> (usize, usize, usize) {
+        let inner_offset = HEADER_SIZE.next_multiple_of(align_of::<InnerNode<K, A>>());
+        let leaf_offset = (inner_offset + self.inner_count as usize * size_of::<InnerNode<K, A>>())
+            .next_multiple_of(align_of::<LeafNode<K, V>>());
+        let total = leaf_offset + self.leaf_count as usize * size_of::<LeafNode<K, V>>();
+        (inner_offset, leaf_offset, total)
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A read-only `NodeStore` reads nodes directly from a memory mapped file, so large indexes
+/// can be queried without loading them into heap. The file is created by `MmapBuilder`.
+///
+/// Nodes are stored in their in-memory layout, so keys, values and augmentations must be
+/// `Pod`, and the file is only readable by a build with the same node layout, which is
+/// checked on open.
+///
+/// All read operations work, including lookups, cursors, iteration and visitors. Trees are
+/// opened as `MmapTree`, which only exposes them by shared reference, so operations
+/// modifying the tree, e.g. `insert`, `clear` or `into_iter`, don't compile. A
+/// `BPlusTree` built on `NodeStoreMmap::default()` by hand panics on these operations.
+pub struct NodeStoreMmap<K: Key, V, A: Augmentation<K> = (), Se: KeySearch = BinarySearch> {
+    map: Option<Mmap>,
+    inner_offset: usize,
+    leaf_offset: usize,
+    inner_count: usize,
+    leaf_count: usize,
+
+    leaf_cache: LeafCache,
+    _marker: PhantomData<(K, V, A, Se)>,
+}
+
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> Default for NodeStoreMmap<K, V, A, Se> {
+    fn default() -> Self {
+        Self {
+            map: None,
+            inner_offset: 0,
+            leaf_offset: 0,
+            inner_count: 0,
+            leaf_count: 0,
+            leaf_cache: LeafCache::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Key + Pod, V: Pod, A: Augmentation<K> + Pod, Se: KeySearch> NodeStoreMmap<K, V, A, Se> {
+    /// Open a tree file written by `MmapBuilder`. The tree is read-only, it is returned as
+    /// `MmapTree` which only gives `&BPlusTree`.
+    ///
+    /// # Safety
+    /// The file must be written by `MmapBuilder` with the same `K`, `V` and `A`, and must not
+    /// be modified while the tree is alive. Only the header is validated.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<MmapTree<K, V, A, Se>> {
+        // Safety: same contract as this fn
+        unsafe { Self::open_with_cache_strategy(path, CacheStrategy::default()) }
+    }
+
+    /// Same as `open`, with the `CacheStrategy` for recently used leaves. Readers sharing the
+    /// tree across threads usually want `CacheStrategy::ThreadLocal`.
+    ///
+    /// # Safety
+    /// Same as `open`.
+    pub unsafe fn open_with_cache_strategy(
+        path: impl AsRef<Path>,
+        strategy: CacheStrategy,
+    ) -> io::Result<MmapTree<K, V, A, Se>> {
+        let file = File::open(path)?;
+        // Safety: caller ensures the file is not modified while mapped
+        let map = unsafe { Mmap::map(&file)? };
+
+        let header = Header::read(&map)?;
+        if header.layout != Header::layout::<K, V, A>() {
+            return Err(invalid_data("mmap tree file written with different layout"));
+        }
+
+        let (inner_offset, leaf_offset, total) = header.offsets::<K, V, A>();
+        if map.len() < total {
+            return Err(invalid_data("mmap tree file truncated"));
+        }
+
+        let root = if header.root_is_leaf == 1 {
+            NodeId::Leaf(LeafNodeId::from_usize(header.root_id as usize))
+        } else {
+            NodeId::Inner(InnerNodeId::from_usize(header.root_id as usize))
+        };
+        let root_valid = match root {
+            NodeId::Leaf(id) => id.as_usize() < header.leaf_count as usize,
+            NodeId::Inner(id) => id.as_usize() < header.inner_count as usize,
+        };
+        if !root_valid {
+            return Err(invalid_data("mmap tree file has invalid root"));
+        }
+
+        let store = Self {
+            map: Some(map),
+            inner_offset,
+            leaf_offset,
+            inner_count: header.inner_count as usize,
+            leaf_count: header.leaf_count as usize,
+            leaf_cache: LeafCache::new(strategy),
+            ..Self::default()
+        };
+        Ok(MmapTree {
+            tree: BPlusTree::new_from_parts(store, root, header.len as usize),
+        })
+    }
+
+    /// Where recently used leaves are cached
+    pub fn cache_strategy(&self) -> CacheStrategy {
+        self.leaf_cache.strategy()
+    }
+
+    fn base(&self) -> *const u8 {
+        self.map.as_ref().map_or(std::ptr::null(), |m| m.as_ptr())
+    }
+}
+
+impl<K: Key + Pod, V: Pod, A: Augmentation<K> + Pod, Se: KeySearch> NodeStore
+    for NodeStoreMmap<K, V, A, Se>
+{
+    type K = K;
+    type V = V;
+    type Augmentation = A;
+    type Searcher = Se;
+
+    fn inner_n() -> u16 {
+        InnerNode::<K, A>::max_capacity()
+    }
+
+    fn leaf_n() -> u16 {
+        LeafNode::<K, V>::max_capacity()
+    }
+
+    #[cfg(test)]
+    fn debug(&self)
+    where
+        K: std::fmt::Debug,
+        V: std::fmt::Debug + Clone,
+        A: std::fmt::Debug,
+    {
+        println!(
+            "mmap store: {} inner nodes, {} leaves",
+            self.inner_count, self.leaf_count
+        );
+    }
+
+    #[cfg(test)]
+    fn new_empty_inner(&mut self) -> InnerNodeId {
+        read_only()
+    }
+
+    fn add_inner(&mut self, _node: Box<InnerNode<K, A>>) -> InnerNodeId {
+        read_only()
+    }
+
+    #[inline(always)]
+    fn get_inner(&self, id: InnerNodeId) -> &InnerNode<K, A> {
+        self.try_get_inner(id).expect("invalid inner node id")
+    }
+
+    fn try_get_inner(&self, id: InnerNodeId) -> Option<&InnerNode<K, A>> {
+        if id.as_usize() >= self.inner_count {
+            return None;
+        }
+
+        // Safety: the id is in range, the area is aligned and written by `MmapBuilder`
+        unsafe {
+            let ptr = self
+                .base()
+                .add(self.inner_offset + id.as_usize() * size_of::<InnerNode<K, A>>());
+            Some(&*(ptr as *const InnerNode<K, A>))
+        }
+    }
+
+    fn get_mut_inner(&mut self, _id: InnerNodeId) -> &mut InnerNode<K, A> {
+        read_only()
+    }
+
+    unsafe fn get_mut_inner_ptr(&mut self, _id: InnerNodeId) -> *mut InnerNode<K, A> {
+        read_only()
+    }
+
+    fn take_inner(&mut self, _id: InnerNodeId) -> Box<InnerNode<K, A>> {
+        read_only()
+    }
+
+    fn put_back_inner(&mut self, _id: InnerNodeId, _node: Box<InnerNode<K, A>>) {
+        read_only()
+    }
+
+    fn new_empty_leaf(&mut self) -> (LeafNodeId, &mut LeafNode<K, V>) {
+        read_only()
+    }
+
+    fn reserve_leaf(&mut self) -> LeafNodeId {
+        read_only()
+    }
+
+    #[inline(always)]
+    fn get_leaf(&self, id: LeafNodeId) -> &LeafNode<K, V> {
+        self.try_get_leaf(id).expect("invalid leaf node id")
+    }
+
+    fn try_get_leaf(&self, id: LeafNodeId) -> Option<&LeafNode<K, V>> {
+        if id.as_usize() >= self.leaf_count {
+            return None;
+        }
+
+        // Safety: the id is in range, the area is aligned and written by `MmapBuilder`
+        unsafe {
+            let ptr = self
+                .base()
+                .add(self.leaf_offset + id.as_usize() * size_of::<LeafNode<K, V>>());
+            Some(&*(ptr as *const LeafNode<K, V>))
+        }
+    }
+
+    fn get_mut_leaf(&mut self, _id: LeafNodeId) -> &mut LeafNode<K, V> {
+        read_only()
+    }
+
+    fn take_leaf(&mut self, _id: LeafNodeId) -> Box<LeafNode<K, V>> {
+        read_only()
+    }
+
+    fn assign_leaf(&mut self, _id: LeafNodeId, _leaf: Box<LeafNode<K, V>>) {
+        read_only()
+    }
+
+    fn cache_leaf(&self, leaf_id: LeafNodeId) {
+        self.leaf_cache.put(leaf_id);
+    }
+
+    fn try_cache<Q>(&self, k: &Q) -> Option<LeafNodeId>
+    where
+        Q: ?Sized + Ord,
+        Self::K: std::borrow::Borrow<Q>,
+    {
+        // try_get_leaf returns None for usize:MAX, so empty slots are never hit
+        self.leaf_cache
+            .get(|id| self.try_get_leaf(id).is_some_and(|l| l.in_range(k)))
+    }
+
+    fn empty_like(&self) -> Self {
+        Self {
+            leaf_cache: LeafCache::new(self.leaf_cache.strategy()),
+            ..Self::default()
+        }
+    }
+}
+
+/// A read-only tree opened by `NodeStoreMmap::open`. It derefs to `BPlusTree`, so all read
+/// operations are available, while operations modifying the tree don't compile.
+///
+/// ```compile_fail
+/// use sweep_bptree::{MmapBuilder, NodeStoreMmap};
+///
+/// let path = std::env::temp_dir().join("sweep_bptree_mmap_read_only");
+/// MmapBuilder::<u64, u64>::new().write(&path).unwrap();
+/// let mut tree = unsafe { NodeStoreMmap::<u64, u64>::open(&path) }.unwrap();
+/// tree.insert(1, 1);
+/// ```
+pub struct MmapTree<
+    K: Key + Pod,
+    V: Pod,
+    A: Augmentation<K> + Pod = (),
+    Se: KeySearch = BinarySearch,
+> {
+    tree: BPlusTree<NodeStoreMmap<K, V, A, Se>>,
+}
+
+impl<K: Key + Pod, V: Pod, A: Augmentation<K> + Pod, Se: KeySearch> std::ops::Deref
+    for MmapTree<K, V, A, Se>
+{
+    type Target = BPlusTree<NodeStoreMmap<K, V, A, Se>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tree
+    }
+}
+
+fn read_only() -> ! {
+    panic!("NodeStoreMmap is read-only")
+}
+
+/// Writes tree files for `NodeStoreMmap`.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{MmapBuilder, NodeStoreMmap};
+///
+/// let path = std::env::temp_dir().join("sweep_bptree_mmap_doc_example");
+/// MmapBuilder::<u64, u64>::new()
+///     .extend_sorted((0..1000).map(|i| (i, i * 2)))
+///     .write(&path)
+///     .unwrap();
+///
+/// // Safety: the file is just written and not modified
+/// let tree = unsafe { NodeStoreMmap::<u64, u64>::open(&path) }.unwrap();
+/// assert_eq!(tree.len(), 1000);
+/// assert_eq!(tree.get(&10), Some(&20));
+/// assert!(tree.iter().map(|(k, _)| *k).eq(0..1000));
+/// # drop(tree);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub struct MmapBuilder<K: Key, V, A: Augmentation<K> = ()> {
+    items: Vec<(K, V)>,
+    _marker: PhantomData<A>,
+}
+
+impl<K: Key + Pod, V: Pod, A: Augmentation<K> + Pod> Default for MmapBuilder<K, V, A> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Key + Pod, V: Pod, A: Augmentation<K> + Pod> MmapBuilder<K, V, A> {
+    /// Create an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder with all items in `tree`
+    pub fn from_tree<S: NodeStore<K = K, V = V>>(tree: &BPlusTree<S>) -> Self {
+        Self::new().extend_sorted(tree.iter().map(|(k, v)| (*k, *v)))
+    }
+
+    /// Append items, they must be sorted by key and greater than items already added.
+    /// For duplicated keys, the last one wins.
+    ///
+    /// # Panics
+    /// if items are not sorted
+    pub fn extend_sorted(mut self, items: impl IntoIterator<Item = (K, V)>) -> Self {
+        for item in items {
+            if let Some(last) = self.items.last() {
+                assert!(last.0 <= item.0, "items must be sorted by key");
+            }
+            self.items.push(item);
+        }
+        self
+    }
+
+    /// Build the tree and write it to `path`, the file is truncated if exists
+    pub fn write(self, path: impl AsRef<Path>) -> io::Result<()> {
+        let tree = BPlusTree::<NodeStoreVec<K, V, A>>::bulk_load(self.items);
+        let store = tree.node_store();
+
+        // nodes created by bulk_load are dense
+        let inner_count = (0..)
+            .take_while(|id| store.try_get_inner(InnerNodeId::from_usize(*id)).is_some())
+            .count();
+        let leaf_count = (0..)
+            .take_while(|id| store.try_get_leaf(LeafNodeId::from_usize(*id)).is_some())
+            .count();
+
+        let (root_is_leaf, root_id) = match tree.root_id() {
+            NodeId::Leaf(id) => (1, id.as_usize()),
+            NodeId::Inner(id) => (0, id.as_usize()),
+        };
+        let header = Header {
+            layout: Header::layout::<K, V, A>(),
+            len: tree.len() as u64,
+            root_is_leaf,
+            root_id: root_id as u64,
+            inner_count: inner_count as u64,
+            leaf_count: leaf_count as u64,
+        };
+        let (inner_offset, leaf_offset, total) = header.offsets::<K, V, A>();
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(total as u64)?;
+        // Safety: the file is just created, and not shared
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+
+        header.write(&mut map[..HEADER_SIZE]);
+
+        let base = map.as_mut_ptr();
+        // Copy nodes as raw bytes. Nodes contain uninitialized slots and padding, so they
+        // can't be viewed as `&[u8]`.
+        for id in 0..inner_count {
+            let node = store.get_inner(InnerNodeId::from_usize(id));
+            let size = size_of::<InnerNode<K, A>>();
+            // Safety: the area is in range, and doesn't overlap with the node
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    node as *const InnerNode<K, A> as *const u8,
+                    base.add(inner_offset + id * size),
+                    size,
+                );
+            }
+        }
+        for id in 0..leaf_count {
+            let leaf = store.get_leaf(LeafNodeId::from_usize(id));
+            let size = size_of::<LeafNode<K, V>>();
+            // Safety: the area is in range, and doesn't overlap with the leaf
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    leaf as *const LeafNode<K, V> as *const u8,
+                    base.add(leaf_offset + id * size),
+                    size,
+                );
+            }
+        }
+
+        map.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augment::count::Count;
+    use std::convert::Infallible;
+
+    use crate::tree::visit::{DescendVisit, DescendVisitResult, VisitContext};
+
+    fn temp_path(name: &str) -> tempfile::TempPath {
+        tempfile::Builder::new()
+            .prefix(name)
+            .tempfile()
+            .unwrap()
+            .into_temp_path()
+    }
+
+    #[test]
+    fn test_mmap_store_read() {
+        for size in [0u64, 1, 64, 65, 20000] {
+            let path = temp_path("mmap_read");
+            MmapBuilder::<u64, [u32; 2], Count>::new()
+                .extend_sorted((0..size).map(|i| (i * 2, [i as u32, 0])))
+                .write(&path)
+                .unwrap();
+
+            let tree = unsafe { NodeStoreMmap::<u64, [u32; 2], Count>::open(&path) }.unwrap();
+            tree.check_invariants().unwrap();
+            assert_eq!(tree.len(), size as usize);
+            assert_eq!(tree.root_augmentation().count(), size as usize);
+            assert!(tree
+                .iter()
+                .map(|(k, v)| (*k, v[0]))
+                .eq((0..size).map(|i| (i * 2, i as u32))));
+            assert!(tree
+                .iter()
+                .rev()
+                .map(|(k, _)| *k)
+                .eq((0..size).rev().map(|i| i * 2)));
+
+            for i in 0..size {
+                assert_eq!(tree.get(&(i * 2)).unwrap()[0], i as u32);
+                assert!(tree.get(&(i * 2 + 1)).is_none());
+            }
+
+            if size > 1 {
+                let cursor = tree.cursor_first().unwrap();
+                let (next, v) = cursor.next_with_value(&tree).unwrap();
+                assert_eq!((*next.key(), v[0]), (2, 1));
+
+                let idx = size / 2;
+                let found = tree.descend_visit(Rank(idx as usize)).unwrap().unwrap();
+                assert_eq!(found, idx * 2);
+            }
+
+            let tree = unsafe {
+                NodeStoreMmap::<u64, [u32; 2], Count>::open_with_cache_strategy(
+                    &path,
+                    CacheStrategy::ThreadLocal,
+                )
+            }
+            .unwrap();
+            assert_eq!(
+                tree.node_store().cache_strategy(),
+                CacheStrategy::ThreadLocal
+            );
+            let tree = &tree;
+            std::thread::scope(|s| {
+                for t in 0..2 {
+                    s.spawn(move || {
+                        for i in (t..size).step_by(2) {
+                            assert_eq!(tree.get(&(i * 2)).unwrap()[0], i as u32);
+                        }
+                    });
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn test_mmap_builder_from_tree() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<i32, f64>::new());
+        for i in (0..1000).rev() {
+            tree.insert(i, i as f64 / 2.);
+        }
+        tree.remove(&500);
+
+        let path = temp_path("mmap_from_tree");
+        MmapBuilder::<i32, f64>::from_tree(&tree)
+            .write(&path)
+            .unwrap();
+        let loaded = unsafe { NodeStoreMmap::<i32, f64>::open(&path) }.unwrap();
+        assert!(loaded.iter().eq(tree.iter()));
+
+        // layout mismatch
+        assert!(unsafe { NodeStoreMmap::<i32, u32>::open(&path) }.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "read-only")]
+    fn test_mmap_store_is_read_only() {
+        // only reachable by building the tree by hand, opened trees are `MmapTree`
+        BPlusTree::new(NodeStoreMmap::<u32, u32>::default());
+    }
+
+    /// Find the key at rank with `Count` augmentation
+    struct Rank(usize);
+
+    impl DescendVisit<u64, [u32; 2], Count> for Rank {
+        type Result = u64;
+        type Error = Infallible;
+
+        fn visit_inner(
+            &mut self,
+            _keys: &[u64],
+            augmentations: &[Count],
+            _ctx: VisitContext<'_, u64>,
+        ) -> Result<DescendVisitResult<Self::Result>, Infallible> {
+            for (idx, c) in augmentations.iter().enumerate() {
+                if self.0 < c.count() {
+                    return Ok(DescendVisitResult::GoDown(idx));
+                }
+                self.0 -= c.count();
+            }
+            Ok(DescendVisitResult::Cancel)
+        }
+
+        fn visit_leaf(
+            &mut self,
+            keys: &[u64],
+            _values: &[[u32; 2]],
+            _ctx: VisitContext<'_, u64>,
+        ) -> Result<Option<Self::Result>, Infallible> {
+            Ok(keys.get(self.0).copied())
+        }
+    }
+}
@@ -1,2 +1,11 @@
+mod leaf_cache;
+pub use leaf_cache::CacheStrategy;
+pub(crate) use leaf_cache::LeafCache;
 mod vec_backed;
 pub use vec_backed::NodeStoreVec;
+mod arc_backed;
+pub use arc_backed::NodeStoreArc;
+#[cfg(feature = "mmap")]
+mod mmap_backed;
+#[cfg(feature = "mmap")]
+pub use mmap_backed::{MmapBuilder, MmapTree, NodeStoreMmap, Pod};
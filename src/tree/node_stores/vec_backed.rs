@@ -1,41 +1,103 @@
-use crate::tree::{Augmentation, InnerNode, InnerNodeId, Key, LeafNode, LeafNodeId, NodeStore};
+use std::marker::PhantomData;
+
+use crate::tree::{
+    Augmentation, BinarySearch, CacheStrategy, InnerNode, InnerNodeId, Key, KeySearch, LeafCache,
+    LeafNode, LeafNodeId, NodeStore, RebalancePolicy, SplitPolicy, Splitter,
+};
 
 #[derive(Debug)]
-pub struct NodeStoreVec<K: Key, V, A: Augmentation<K> = ()> {
+pub struct NodeStoreVec<K: Key, V, A: Augmentation<K> = (), Se: KeySearch = BinarySearch> {
     inner_nodes: Vec<Option<Box<InnerNode<K, A>>>>,
     leaf_nodes: Vec<Option<Box<LeafNode<K, V>>>>,
 
-    cached_leaf: std::sync::atomic::AtomicUsize,
+    leaf_cache: LeafCache,
+    splitter: Splitter,
+    rebalance: RebalancePolicy,
+    searcher: PhantomData<Se>,
 }
 
-impl<K: Key, V: Clone, A: Augmentation<K>> Clone for NodeStoreVec<K, V, A> {
+impl<K: Key, V: Clone, A: Augmentation<K>, Se: KeySearch> Clone for NodeStoreVec<K, V, A, Se> {
     fn clone(&self) -> Self {
         Self {
             inner_nodes: self.inner_nodes.clone(),
             leaf_nodes: self.leaf_nodes.clone(),
-            cached_leaf: std::sync::atomic::AtomicUsize::new(
-                self.cached_leaf.load(std::sync::atomic::Ordering::Relaxed),
-            ),
+            leaf_cache: self.leaf_cache.clone(),
+            splitter: self.splitter.clone(),
+            rebalance: self.rebalance,
+            searcher: PhantomData,
         }
     }
 }
 
-impl<K: Key, V, A: Augmentation<K>> Default for NodeStoreVec<K, V, A> {
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> Default for NodeStoreVec<K, V, A, Se> {
     fn default() -> Self {
         Self {
             inner_nodes: Default::default(),
             leaf_nodes: Default::default(),
-            cached_leaf: std::sync::atomic::AtomicUsize::new(usize::MAX),
+            leaf_cache: LeafCache::default(),
+            splitter: Splitter::default(),
+            rebalance: RebalancePolicy::default(),
+            searcher: PhantomData,
         }
     }
 }
 
-impl<K: Key, V, A: Augmentation<K>> NodeStoreVec<K, V, A> {
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> NodeStoreVec<K, V, A, Se> {
     /// Create a new `NodeStoreVec`
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Set the policy to split full nodes, see `SplitPolicy`
+    pub fn with_split_policy(mut self, policy: SplitPolicy) -> Self {
+        self.set_split_policy(policy);
+        self
+    }
+
+    /// Set the policy to split full nodes, it applies to later splits
+    pub fn set_split_policy(&mut self, policy: SplitPolicy) {
+        self.splitter = Splitter::new(policy);
+    }
+
+    /// The policy to split full nodes
+    pub fn split_policy(&self) -> SplitPolicy {
+        self.splitter.policy()
+    }
+
+    /// Set the policy deletes use to rebalance leaves, see `RebalancePolicy`
+    pub fn with_rebalance_policy(mut self, policy: RebalancePolicy) -> Self {
+        self.set_rebalance_policy(policy);
+        self
+    }
+
+    /// Set the policy deletes use to rebalance leaves, it applies to later deletes.
+    /// Switching to a stricter policy doesn't fix existing leaves, call `BPlusTree::compact`
+    /// for that.
+    pub fn set_rebalance_policy(&mut self, policy: RebalancePolicy) {
+        self.rebalance = policy;
+    }
+
+    /// The policy deletes use to rebalance leaves
+    pub fn rebalance_policy(&self) -> RebalancePolicy {
+        self.rebalance
+    }
+
+    /// Set where recently used leaves are cached, see `CacheStrategy`
+    pub fn with_cache_strategy(mut self, strategy: CacheStrategy) -> Self {
+        self.set_cache_strategy(strategy);
+        self
+    }
+
+    /// Set where recently used leaves are cached, the cache is reset
+    pub fn set_cache_strategy(&mut self, strategy: CacheStrategy) {
+        self.leaf_cache = LeafCache::new(strategy);
+    }
+
+    /// Where recently used leaves are cached
+    pub fn cache_strategy(&self) -> CacheStrategy {
+        self.leaf_cache.strategy()
+    }
+
     /// Create a new `NodeStoreVec` with capacity
     pub fn with_capacity(cap: usize) -> Self {
         Self {
@@ -81,10 +143,11 @@ impl<K: Key, V, A: Augmentation<K>> NodeStoreVec<K, V, A> {
     }
 }
 
-impl<K: Key, V, A: Augmentation<K>> NodeStore for NodeStoreVec<K, V, A> {
+impl<K: Key, V, A: Augmentation<K>, Se: KeySearch> NodeStore for NodeStoreVec<K, V, A, Se> {
     type K = K;
     type V = V;
     type Augmentation = A;
+    type Searcher = Se;
 
     fn inner_n() -> u16 {
         InnerNode::<K, A>::max_capacity()
@@ -221,9 +284,44 @@ impl<K: Key, V, A: Augmentation<K>> NodeStore for NodeStoreVec<K, V, A> {
         }
     }
 
+    fn split_point(&mut self, is_leaf: bool, insert_idx: usize, capacity: usize) -> usize {
+        self.splitter.split_point(is_leaf, insert_idx, capacity)
+    }
+
+    fn split_policy(&self) -> SplitPolicy {
+        self.splitter.policy()
+    }
+
+    fn rebalance_policy(&self) -> RebalancePolicy {
+        self.rebalance
+    }
+
+    fn empty_like(&self) -> Self {
+        Self {
+            splitter: Splitter::new(self.splitter.policy()),
+            rebalance: self.rebalance,
+            leaf_cache: LeafCache::new(self.leaf_cache.strategy()),
+            ..Self::default()
+        }
+    }
+
+    fn free_slots(&self) -> usize {
+        let inner_free = self.inner_nodes.iter().filter(|n| n.is_none()).count();
+        let leaf_free = self.leaf_nodes.iter().filter(|n| n.is_none()).count();
+        inner_free + leaf_free
+    }
+
+    fn heap_bytes(&self) -> usize {
+        let inner_count = self.inner_nodes.iter().flatten().count();
+        let leaf_count = self.leaf_nodes.iter().flatten().count();
+        inner_count * std::mem::size_of::<InnerNode<K, A>>()
+            + leaf_count * std::mem::size_of::<LeafNode<K, V>>()
+            + self.inner_nodes.capacity() * std::mem::size_of::<Option<Box<InnerNode<K, A>>>>()
+            + self.leaf_nodes.capacity() * std::mem::size_of::<Option<Box<LeafNode<K, V>>>>()
+    }
+
     fn cache_leaf(&self, leaf_id: LeafNodeId) {
-        self.cached_leaf
-            .store(leaf_id.as_usize(), std::sync::atomic::Ordering::Relaxed);
+        self.leaf_cache.put(leaf_id);
     }
 
     fn try_cache<Q>(&self, k: &Q) -> Option<LeafNodeId>
@@ -231,18 +329,9 @@ impl<K: Key, V, A: Augmentation<K>> NodeStore for NodeStoreVec<K, V, A> {
         Q: ?Sized + Ord,
         Self::K: std::borrow::Borrow<Q>,
     {
-        let leaf_id = self.cached_leaf.load(std::sync::atomic::Ordering::Relaxed);
-        let leaf_id = LeafNodeId(leaf_id);
-        // try_get_leaf returns None for usize:MAX, so we don't need to handle it explicitly
-        if self
-            .try_get_leaf(leaf_id)
-            .map(|l| l.in_range(k))
-            .unwrap_or_default()
-        {
-            Some(leaf_id)
-        } else {
-            None
-        }
+        // try_get_leaf returns None for usize:MAX, so empty slots are never hit
+        self.leaf_cache
+            .get(|id| self.try_get_leaf(id).is_some_and(|l| l.in_range(k)))
     }
 }
 
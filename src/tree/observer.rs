@@ -0,0 +1,347 @@
+use std::ops::{Deref, DerefMut};
+
+use super::{BPlusTree, NodeStore};
+
+/// Observer of changes to a `BPlusTree`, used to keep derived state in sync with the tree.
+///
+/// Installed with `BPlusTree::set_observer`. Every mutation path reports here, including
+/// `insert`, `remove`, `remove_by_augmentation`, `get_mut_observed`, `clear`, `split_off`,
+/// `append`, committed transactions and `IntoIter`. Plain `get_mut` is not reported. Items
+/// already in the tree when the observer is set are not reported.
+pub trait TreeObserver<K, V> {
+    /// A new key is inserted
+    fn on_insert(&mut self, key: &K, value: &V) {
+        let _ = (key, value);
+    }
+
+    /// An existing key's value is replaced
+    fn on_update(&mut self, key: &K, old: &V, new: &V) {
+        let _ = (key, old, new);
+    }
+
+    /// Value is modified in place through `get_mut_observed`, the old value is not available.
+    /// Called when the `ValueMut` is dropped, if it was written through.
+    fn on_modify(&mut self, key: &K, value: &V) {
+        let _ = (key, value);
+    }
+
+    /// A key is removed, or moved out by `IntoIter`
+    fn on_remove(&mut self, key: &K, value: &V) {
+        let _ = (key, value);
+    }
+}
+
+pub(crate) type BoxedObserver<K, V> = Box<dyn TreeObserver<K, V> + Send + Sync>;
+
+/// A change to report
+pub(crate) enum Change<'a, K, V> {
+    Insert(&'a K, &'a V),
+    Update(&'a K, &'a V, &'a V),
+    Modify(&'a K, &'a V),
+    Remove(&'a K, &'a V),
+}
+
+/// Report `change` to `observer`, all mutation paths report through here
+#[inline]
+pub(crate) fn notify<K, V>(observer: Option<&mut BoxedObserver<K, V>>, change: Change<'_, K, V>) {
+    let Some(observer) = observer else {
+        return;
+    };
+
+    match change {
+        Change::Insert(k, v) => observer.on_insert(k, v),
+        Change::Update(k, old, new) => observer.on_update(k, old, new),
+        Change::Modify(k, v) => observer.on_modify(k, v),
+        Change::Remove(k, v) => observer.on_remove(k, v),
+    }
+}
+
+/// Mutable reference to a value in tree, returned by `get_mut_observed`. Reports the change
+/// to tree's observer when dropped, if the value was mutably dereferenced.
+pub struct ValueMut<'a, K, V> {
+    key: &'a K,
+    value: &'a mut V,
+    observer: Option<&'a mut BoxedObserver<K, V>>,
+    modified: bool,
+}
+
+impl<'a, K, V> ValueMut<'a, K, V> {
+    pub(crate) fn new(
+        key: &'a K,
+        value: &'a mut V,
+        observer: Option<&'a mut BoxedObserver<K, V>>,
+    ) -> Self {
+        Self {
+            key,
+            value,
+            observer,
+            modified: false,
+        }
+    }
+
+    /// Returns the key of the value
+    pub fn key(&self) -> &K {
+        self.key
+    }
+}
+
+impl<K, V> Deref for ValueMut<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value
+    }
+}
+
+impl<K, V> DerefMut for ValueMut<'_, K, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.modified = true;
+        self.value
+    }
+}
+
+impl<K, V> Drop for ValueMut<'_, K, V> {
+    fn drop(&mut self) {
+        if self.modified {
+            notify(self.observer.take(), Change::Modify(self.key, self.value));
+        }
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Install an observer, it replaces the previous one. Clones of the tree don't inherit
+    /// the observer.
+    ///
+    /// # Observers
+    /// Writes through `get_mut` and `get_mut_by_augmentation` are not reported, since they
+    /// hand out a plain `&mut V`. Use `get_mut_observed` and
+    /// `get_mut_by_augmentation_observed` while an observer is installed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::sync::{Arc, Mutex};
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::tree::TreeObserver;
+    ///
+    /// #[derive(Default)]
+    /// struct Log(Arc<Mutex<Vec<String>>>);
+    ///
+    /// impl TreeObserver<i32, i32> for Log {
+    ///     fn on_insert(&mut self, k: &i32, v: &i32) {
+    ///         self.0.lock().unwrap().push(format!("insert {k} {v}"));
+    ///     }
+    ///     fn on_update(&mut self, k: &i32, old: &i32, new: &i32) {
+    ///         self.0.lock().unwrap().push(format!("update {k} {old} {new}"));
+    ///     }
+    ///     fn on_modify(&mut self, k: &i32, v: &i32) {
+    ///         self.0.lock().unwrap().push(format!("modify {k} {v}"));
+    ///     }
+    ///     fn on_remove(&mut self, k: &i32, v: &i32) {
+    ///         self.0.lock().unwrap().push(format!("remove {k} {v}"));
+    ///     }
+    /// }
+    ///
+    /// let log = Arc::new(Mutex::new(vec![]));
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i32, i32>::new());
+    /// tree.set_observer(Log(log.clone()));
+    ///
+    /// tree.insert(1, 1);
+    /// tree.insert(1, 2);
+    /// *tree.get_mut_observed(&1).unwrap() += 1;
+    /// // reading through `ValueMut` is not reported
+    /// assert_eq!(*tree.get_mut_observed(&1).unwrap(), 3);
+    /// tree.remove(&1);
+    ///
+    /// assert_eq!(
+    ///     *log.lock().unwrap(),
+    ///     vec!["insert 1 1", "update 1 1 2", "modify 1 3", "remove 1 3"]
+    /// );
+    /// ```
+    pub fn set_observer(
+        &mut self,
+        observer: impl TreeObserver<S::K, S::V> + Send + Sync + 'static,
+    ) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Remove the observer and returns it
+    pub fn take_observer(&mut self) -> Option<Box<dyn TreeObserver<S::K, S::V> + Send + Sync>> {
+        self.observer.take()
+    }
+
+    /// Same as `bulk_load`, and reports all loaded items to `observer` as inserted
+    pub fn bulk_load_with_observer(
+        data: Vec<(S::K, S::V)>,
+        observer: impl TreeObserver<S::K, S::V> + Send + Sync + 'static,
+    ) -> Self {
+        let mut tree = Self::bulk_load(data);
+        let mut observer: BoxedObserver<S::K, S::V> = Box::new(observer);
+        for (k, v) in tree.iter() {
+            notify(Some(&mut observer), Change::Insert(k, v));
+        }
+        tree.observer = Some(observer);
+        tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::{augment::count::Count, NodeStoreVec};
+
+    /// Mirrors the tree into a BTreeMap
+    #[derive(Clone, Default)]
+    struct Mirror(Arc<Mutex<BTreeMap<u32, u32>>>);
+
+    impl TreeObserver<u32, u32> for Mirror {
+        fn on_insert(&mut self, key: &u32, value: &u32) {
+            assert!(self.0.lock().unwrap().insert(*key, *value).is_none());
+        }
+
+        fn on_update(&mut self, key: &u32, old: &u32, new: &u32) {
+            assert_eq!(self.0.lock().unwrap().insert(*key, *new), Some(*old));
+        }
+
+        fn on_modify(&mut self, key: &u32, value: &u32) {
+            assert!(self.0.lock().unwrap().insert(*key, *value).is_some());
+        }
+
+        fn on_remove(&mut self, key: &u32, value: &u32) {
+            assert_eq!(self.0.lock().unwrap().remove(key), Some(*value));
+        }
+    }
+
+    type Tree = BPlusTree<NodeStoreVec<u32, u32, Count>>;
+
+    fn assert_mirrored(tree: &Tree, mirror: &Mirror) {
+        assert!(tree.iter().map(|(k, v)| (*k, *v)).eq(mirror
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (*k, *v))));
+    }
+
+    #[test]
+    fn test_observer_mutation_paths() {
+        let mirror = Mirror::default();
+        let mut tree =
+            Tree::bulk_load_with_observer((0..500).map(|i| (i, i)).collect(), mirror.clone());
+        assert_mirrored(&tree, &mirror);
+
+        for i in 250..750 {
+            tree.insert(i, i + 1);
+        }
+        for i in (0..750).step_by(7) {
+            tree.remove(&i);
+        }
+        *tree.get_mut_observed(&1).unwrap() = 100;
+        *tree.get_mut_by_augmentation_observed(0).unwrap() += 1;
+        tree.remove_by_augmentation(10);
+        tree.transaction(|tx| {
+            tx.insert(1000, 0);
+            tx.remove(&2);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert_mirrored(&tree, &mirror);
+
+        let mut right = tree.split_off(&400);
+        assert_mirrored(&tree, &mirror);
+
+        // small sides are moved element by element
+        let mut tail = tree.split_off(&395);
+        assert_mirrored(&tree, &mirror);
+        let mut rest = tree.split_off(&5);
+        assert_mirrored(&tree, &mirror);
+        rest.append(&mut tail);
+        tree.append(&mut rest);
+        assert_mirrored(&tree, &mirror);
+        let mut small_tail = tree.split_off(&390);
+        tree.append(&mut small_tail);
+        assert_mirrored(&tree, &mirror);
+
+        let other = Mirror::default();
+        *other.0.lock().unwrap() = right.iter().map(|(k, v)| (*k, *v)).collect();
+        right.set_observer(other.clone());
+        right.insert(5, 5);
+        tree.append(&mut right);
+        assert_mirrored(&tree, &mirror);
+        assert!(other.0.lock().unwrap().is_empty());
+
+        let mut iter = tree.into_iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(mirror.0.lock().unwrap().len(), iter.len());
+        drop(iter);
+        assert!(mirror.0.lock().unwrap().is_empty());
+
+        let mut tree = Tree::new(NodeStoreVec::new());
+        tree.set_observer(mirror.clone());
+        tree.insert(1, 1);
+        tree.clear();
+        assert!(mirror.0.lock().unwrap().is_empty());
+        assert!(tree.take_observer().is_some());
+    }
+
+    #[test]
+    fn test_observer_reports_only_writes_and_survives_panic() {
+        /// Counts modifies, and panics on inserting or removing `PANIC_KEY`
+        #[derive(Clone, Default)]
+        struct Picky(Arc<Mutex<usize>>);
+
+        const PANIC_KEY: u32 = 77;
+
+        impl TreeObserver<u32, u32> for Picky {
+            fn on_insert(&mut self, key: &u32, _value: &u32) {
+                assert_ne!(*key, PANIC_KEY);
+            }
+
+            fn on_modify(&mut self, _key: &u32, _value: &u32) {
+                *self.0.lock().unwrap() += 1;
+            }
+
+            fn on_remove(&mut self, key: &u32, _value: &u32) {
+                assert_ne!(*key, PANIC_KEY);
+            }
+        }
+
+        let picky = Picky::default();
+        let mut tree = Tree::new(NodeStoreVec::new());
+        tree.set_observer(picky.clone());
+        for i in 0..1000 {
+            if i != PANIC_KEY {
+                tree.insert(i, i);
+            }
+        }
+
+        // plain get_mut and reads through ValueMut are not reported
+        *tree.get_mut(&1).unwrap() += 1;
+        assert_eq!(*tree.get_mut_observed(&1).unwrap(), 2);
+        assert_eq!(*picky.0.lock().unwrap(), 0);
+        *tree.get_mut_observed(&1).unwrap() += 1;
+        assert_eq!(*picky.0.lock().unwrap(), 1);
+
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            tree.insert(PANIC_KEY, 0);
+        }));
+        assert!(r.is_err());
+        // the insert is complete, and the observer is kept
+        tree.check_invariants().unwrap();
+        assert_eq!(tree.len(), 1000);
+        *tree.get_mut_observed(&1).unwrap() += 1;
+        assert_eq!(*picky.0.lock().unwrap(), 2);
+
+        let r = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            tree.split_off(&70);
+        }));
+        assert!(r.is_err());
+        tree.check_invariants().unwrap();
+        assert!(tree.take_observer().is_some());
+    }
+}
@@ -0,0 +1,246 @@
+use super::{BPlusTree, Key, LeafNode, NodeId, NodeStore};
+
+/// How deletes rebalance under filled leaves, set on node stores with
+/// `with_rebalance_policy`.
+///
+/// Inner nodes are always rebalanced eagerly. With a non eager policy leaves may stay under
+/// filled, call `BPlusTree::compact` to pack them again.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{BPlusTree, NodeStoreVec};
+/// use sweep_bptree::tree::RebalancePolicy;
+///
+/// let store = NodeStoreVec::<u64, u64>::new().with_rebalance_policy(RebalancePolicy::FreeAtEmpty);
+/// let mut tree = BPlusTree::new(store);
+/// for i in 0..10_000 {
+///     tree.insert(i, i);
+/// }
+/// // consume like a queue, every other element
+/// for i in (0..10_000).step_by(2) {
+///     tree.remove(&i);
+/// }
+/// let stats = tree.stats();
+/// assert_eq!(stats.operations.merge_with_left_leaf, 0);
+///
+/// tree.compact();
+/// assert!(tree.stats().leaf_nodes < stats.leaf_nodes);
+/// tree.check_invariants().unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RebalancePolicy {
+    /// Rebalance a leaf once it drops under a quarter of capacity, rotating from a sibling
+    /// which can lend, otherwise merging with it
+    #[default]
+    Eager,
+    /// Same as `Eager`, but leaves may drop to `min_leaf` elements before rebalancing.
+    /// `min_leaf` is clamped to `1..=` the eager minimum.
+    Lazy { min_leaf: u16 },
+    /// Never rotate or merge leaves, a leaf is freed when its last element is deleted
+    FreeAtEmpty,
+}
+
+impl RebalancePolicy {
+    /// Minimum size of a non root leaf under this policy
+    pub(crate) fn leaf_minimum<K: Key, V>(self) -> usize {
+        let eager = LeafNode::<K, V>::minimum_size() as usize;
+        match self {
+            Self::Eager => eager,
+            Self::Lazy { min_leaf } => (min_leaf as usize).clamp(1, eager),
+            Self::FreeAtEmpty => 1,
+        }
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// The policy deletes use to rebalance leaves, held by the node store
+    pub fn rebalance_policy(&self) -> RebalancePolicy {
+        self.node_store.rebalance_policy()
+    }
+
+    /// Pack all elements into full leaves and rebuild inner nodes, which restores fill bounds
+    /// after deletes under a lazy `RebalancePolicy`. Nodes are moved into a new store with
+    /// the same configuration, so slots freed by deletes are released. Keys and values are
+    /// not changed, so observers are not notified. Time complexity is O(n).
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// tree.compact();
+    /// assert_eq!(tree.stats().leaf_fill_factor(), 1000. / 1024.);
+    /// assert_eq!(tree.get(&999), Some(&999));
+    /// ```
+    pub fn compact(&mut self) {
+        self.st.compact += 1;
+        if let NodeId::Leaf(_) = self.root {
+            return;
+        }
+
+        // free inner nodes and move elements out of leaves, in key order
+        let mut data = Vec::with_capacity(self.len);
+        let mut stack = vec![self.root];
+        while let Some(node_id) = stack.pop() {
+            match node_id {
+                NodeId::Inner(inner_id) => {
+                    let inner = self.node_store.take_inner(inner_id);
+                    stack.extend((0..=inner.len()).rev().map(|idx| inner.child_id(idx)));
+                }
+                NodeId::Leaf(leaf_id) => {
+                    let mut leaf = self.node_store.take_leaf(leaf_id);
+                    for slot in 0..leaf.len() {
+                        // Safety: each slot is taken once, then the leaf forgets them
+                        data.push(unsafe { leaf.take_data(slot) });
+                    }
+                    unsafe { leaf.forget_data() };
+                }
+            }
+        }
+
+        // self is an empty tree until the rebuild finishes, so a panicking key clone or
+        // augmentation drops the elements once and leaves a valid tree
+        let tree_len = std::mem::take(&mut self.len);
+        let (empty_id, _) = self.node_store.new_empty_leaf();
+        self.root = NodeId::Leaf(empty_id);
+        self.root_augmentation = Self::new_augmentation_for_id(&self.node_store, self.root);
+
+        let mut node_store = self.node_store.empty_like();
+        let leaves = Self::pack_leaves(data.into_iter());
+        match Self::link_leaves(&mut node_store, leaves) {
+            Some((root, len)) => {
+                debug_assert_eq!(len, tree_len);
+                self.root = root;
+            }
+            None => {
+                let (leaf_id, _) = node_store.new_empty_leaf();
+                self.root = NodeId::Leaf(leaf_id);
+            }
+        }
+        // the old store holds no node now
+        *self.node_store = node_store;
+        self.len = tree_len;
+        self.root_augmentation = Self::new_augmentation_for_id(&self.node_store, self.root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::{
+        augment::count::Count,
+        tree::{CacheStrategy, SplitPolicy},
+        NodeStoreArc, NodeStoreVec,
+    };
+
+    #[test]
+    fn test_rebalance_policy() {
+        let mut keys = (0..20_000).collect::<Vec<u32>>();
+        keys.shuffle(&mut rand::thread_rng());
+
+        for policy in [
+            RebalancePolicy::Eager,
+            RebalancePolicy::Lazy { min_leaf: 1 },
+            RebalancePolicy::Lazy { min_leaf: 4 },
+            RebalancePolicy::FreeAtEmpty,
+        ] {
+            let store = NodeStoreVec::<u32, u32, Count>::new().with_rebalance_policy(policy);
+            let mut tree = BPlusTree::new(store);
+            assert_eq!(tree.rebalance_policy(), policy);
+            for k in keys.iter() {
+                tree.insert(*k, *k);
+            }
+
+            for (i, k) in keys.iter().take(19_000).enumerate() {
+                assert_eq!(tree.remove(k), Some(*k));
+                if i % 997 == 0 {
+                    tree.check_invariants().unwrap();
+                }
+            }
+            tree.check_invariants().unwrap();
+            assert_eq!(tree.len(), 1000);
+
+            let st = tree.stats().operations;
+            let rotates = st.rotate_left_leaf + st.rotate_right_leaf;
+            let merges = st.merge_with_left_leaf + st.merge_with_right_leaf;
+            match policy {
+                RebalancePolicy::Eager => assert!(rotates > 0 && merges > 0),
+                RebalancePolicy::Lazy { .. } => assert!(merges > 0),
+                RebalancePolicy::FreeAtEmpty => {
+                    assert_eq!(rotates + merges, 0);
+                    assert!(st.free_empty_leaf > 0);
+                }
+            }
+
+            let before = tree.stats();
+            assert!(tree.node_store().free_slots() > 0);
+            tree.compact();
+            tree.check_invariants().unwrap();
+            let after = tree.stats();
+            assert!(after.leaf_nodes <= before.leaf_nodes);
+            assert_eq!(tree.node_store().free_slots(), 0);
+            assert_eq!(tree.rebalance_policy(), policy);
+            assert_eq!(after.leaf_nodes, 1000usize.div_ceil(64));
+            assert_eq!(after.operations.compact, 1);
+
+            let mut rest = keys[19_000..].to_vec();
+            rest.sort();
+            assert!(tree.iter().map(|(k, _)| *k).eq(rest.iter().copied()));
+
+            // the compacted tree keeps working
+            for k in rest.iter() {
+                tree.remove(k);
+            }
+            tree.check_invariants().unwrap();
+            assert!(tree.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_rebuilds_keep_store_config() {
+        let policy = RebalancePolicy::Lazy { min_leaf: 2 };
+        let split = SplitPolicy::RIGHTMOST;
+        let cache = CacheStrategy::Lru(4);
+        let store = NodeStoreArc::<u32, u32, Count>::new()
+            .with_rebalance_policy(policy)
+            .with_split_policy(split)
+            .with_cache_strategy(cache);
+        let assert_config = |tree: &BPlusTree<NodeStoreArc<u32, u32, Count>>| {
+            assert_eq!(tree.rebalance_policy(), policy);
+            assert_eq!(tree.node_store().split_policy(), split);
+            assert_eq!(tree.node_store().cache_strategy(), cache);
+        };
+
+        let mut tree = BPlusTree::new(store);
+        for i in 0..5000 {
+            tree.insert(i, i);
+        }
+        // leaves drop to the lazy minimum
+        for i in (0..5000).filter(|i| i % 64 > 2) {
+            tree.remove(&i);
+        }
+        tree.check_invariants().unwrap();
+
+        let mut right = tree.split_off(&2500);
+        assert_config(&tree);
+        assert_config(&right);
+        for i in 2500..2600 {
+            right.insert(i, i);
+        }
+        tree.append(&mut right);
+        assert_config(&tree);
+        assert_config(&right);
+        tree.check_invariants().unwrap();
+
+        tree.compact();
+        assert_config(&tree);
+        tree.clear();
+        assert_config(&tree);
+    }
+}
@@ -0,0 +1,492 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use super::{Augmentation, BPlusTree, Key, KeySearch, LeafNode, NodeId, NodeStore, NodeStoreVec};
+
+const MAGIC: &[u8; 4] = b"SBPT";
+const VERSION: u32 = 1;
+
+/// Binary encoding for keys and values in snapshot.
+///
+/// Implemented for primitives, `String`, `Vec<T>` and pairs. Implement it for your own types
+/// to write them into a snapshot.
+pub trait Codec: Sized {
+    /// Encode `self` into `w`
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()>;
+
+    /// Decode a value from `r`
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! impl_codec_for_num {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                #[inline]
+                fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+                    w.write_all(&self.to_le_bytes())
+                }
+
+                #[inline]
+                fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+                    let mut buf = [0u8; std::mem::size_of::<$t>()];
+                    r.read_exact(&mut buf)?;
+                    Ok(<$t>::from_le_bytes(buf))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_for_num!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Codec for usize {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u64).encode(w)
+    }
+
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+        usize::try_from(u64::decode(r)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Codec for isize {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        (*self as i64).encode(w)
+    }
+
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+        isize::try_from(i64::decode(r)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Codec for bool {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        (*self as u8).encode(w)
+    }
+
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+        match u8::decode(r)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid bool")),
+        }
+    }
+}
+
+impl Codec for () {
+    fn encode<W: Write + ?Sized>(&self, _w: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn decode<R: Read + ?Sized>(_r: &mut R) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+impl Codec for String {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.len().encode(w)?;
+        w.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+        let len = usize::decode(r)?;
+        let mut buf = Vec::new();
+        r.take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.len().encode(w)?;
+        self.iter().try_for_each(|item| item.encode(w))
+    }
+
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+        let len = usize::decode(r)?;
+        // don't trust len for allocation, the data may be corrupted
+        let mut items = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            items.push(T::decode(r)?);
+        }
+        Ok(items)
+    }
+}
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+    fn encode<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        self.0.encode(w)?;
+        self.1.encode(w)
+    }
+
+    fn decode<R: Read + ?Sized>(r: &mut R) -> io::Result<Self> {
+        Ok((A::decode(r)?, B::decode(r)?))
+    }
+}
+
+/// Error when reading snapshot
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Underlying io error, including decode errors from `Codec`
+    Io(io::Error),
+    /// The data is not a snapshot
+    BadMagic,
+    /// The snapshot is written by an unsupported format version
+    UnsupportedVersion(u32),
+    /// The snapshot is written with different node capacities
+    CapacityMismatch {
+        inner_n: u16,
+        leaf_n: u16,
+        expected_inner_n: u16,
+        expected_leaf_n: u16,
+    },
+    /// Checksum doesn't match the content
+    ChecksumMismatch { expected: u64, actual: u64 },
+    /// The content is inconsistent, e.g. keys out of order
+    Corrupted(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::BadMagic => write!(f, "not a snapshot"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {v}"),
+            Self::CapacityMismatch {
+                inner_n,
+                leaf_n,
+                expected_inner_n,
+                expected_leaf_n,
+            } => write!(
+                f,
+                "node capacity mismatch, snapshot inner {inner_n} leaf {leaf_n}, \
+                 expected inner {expected_inner_n} leaf {expected_leaf_n}"
+            ),
+            Self::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "checksum mismatch, expected {expected:#x} got {actual:#x}"
+                )
+            }
+            Self::Corrupted(reason) => write!(f, "corrupted snapshot: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// FNV-1a, used to checksum all bytes before the checksum itself
+pub(crate) struct Checksum(u64);
+
+impl Checksum {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    checksum: Checksum,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.checksum.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct ChecksumReader<R> {
+    inner: R,
+    checksum: Checksum,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.checksum.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<K, V, A, Se> BPlusTree<NodeStoreVec<K, V, A, Se>>
+where
+    K: Key + Codec,
+    V: Codec,
+    A: Augmentation<K>,
+    Se: KeySearch,
+{
+    /// Write the tree into `w` in snapshot format.
+    ///
+    /// The snapshot contains a header with length, height and node capacities, then the
+    /// leaves' data in key order, and a checksum of all the above. Augmentations are not
+    /// written, they are rebuilt by `read_snapshot`.
+    ///
+    /// `w` is written in small pieces, wrap it in `BufWriter` if it is not buffered.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<u64, String>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i.to_string());
+    /// }
+    ///
+    /// let mut buf = Vec::new();
+    /// tree.write_snapshot(&mut buf).unwrap();
+    ///
+    /// let loaded = BPlusTree::<NodeStoreVec<u64, String>>::read_snapshot(buf.as_slice()).unwrap();
+    /// assert_eq!(loaded.len(), 1000);
+    /// assert_eq!(loaded.get(&10).unwrap(), "10");
+    /// ```
+    pub fn write_snapshot(&self, w: impl Write) -> io::Result<()> {
+        let mut w = ChecksumWriter {
+            inner: w,
+            checksum: Checksum::new(),
+        };
+
+        // header
+        w.write_all(MAGIC)?;
+        VERSION.encode(&mut w)?;
+        (self.len() as u64).encode(&mut w)?;
+        (self.height() as u32).encode(&mut w)?;
+        NodeStoreVec::<K, V, A>::inner_n().encode(&mut w)?;
+        NodeStoreVec::<K, V, A>::leaf_n().encode(&mut w)?;
+
+        // leaves in chain order
+        let mut leaf_id = self.first_leaf();
+        while let Some(id) = leaf_id {
+            let leaf = self.node_store.get_leaf(id);
+            if !leaf.is_empty() {
+                (leaf.len() as u16).encode(&mut w)?;
+                for (k, v) in leaf.keys().iter().zip(leaf.values()) {
+                    k.encode(&mut w)?;
+                    v.encode(&mut w)?;
+                }
+            }
+            leaf_id = leaf.next();
+        }
+        // end of leaves
+        0u16.encode(&mut w)?;
+
+        let checksum = w.checksum.value();
+        checksum.encode(&mut w.inner)?;
+        w.flush()
+    }
+
+    /// Read a tree written by `write_snapshot`. Leaves are loaded as written, and inner nodes
+    /// are rebuilt the same way as `bulk_load`.
+    ///
+    /// `r` is read in small pieces, wrap it in `BufReader` if it is not buffered.
+    pub fn read_snapshot(r: impl Read) -> Result<Self, SnapshotError> {
+        let mut r = ChecksumReader {
+            inner: r,
+            checksum: Checksum::new(),
+        };
+
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+
+        let version = u32::decode(&mut r)?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let len = u64::decode(&mut r)?;
+        let height = u32::decode(&mut r)?;
+        let inner_n = u16::decode(&mut r)?;
+        let leaf_n = u16::decode(&mut r)?;
+
+        let (expected_inner_n, expected_leaf_n) = (
+            NodeStoreVec::<K, V, A>::inner_n(),
+            NodeStoreVec::<K, V, A>::leaf_n(),
+        );
+        if (inner_n, leaf_n) != (expected_inner_n, expected_leaf_n) {
+            return Err(SnapshotError::CapacityMismatch {
+                inner_n,
+                leaf_n,
+                expected_inner_n,
+                expected_leaf_n,
+            });
+        }
+
+        let mut leaves: Vec<Box<LeafNode<K, V>>> = Vec::new();
+        let mut count = 0u64;
+        loop {
+            let size = u16::decode(&mut r)?;
+            if size == 0 {
+                break;
+            }
+            if size > leaf_n {
+                return Err(SnapshotError::Corrupted("leaf size exceeds capacity"));
+            }
+
+            let mut items = Vec::with_capacity(size as usize);
+            for _ in 0..size {
+                items.push((K::decode(&mut r)?, V::decode(&mut r)?));
+            }
+
+            // keys must be strictly ascending, across leaves
+            let prev_key = leaves.last().map(|l| &l.keys()[l.len() - 1]);
+            let ordered = prev_key.map_or(true, |k| *k < items[0].0)
+                && items.windows(2).all(|w| w[0].0 < w[1].0);
+            if !ordered {
+                return Err(SnapshotError::Corrupted("keys out of order"));
+            }
+
+            count += size as u64;
+            let mut leaf = LeafNode::<K, V>::new();
+            leaf.set_data(items.into_iter());
+            leaves.push(leaf);
+        }
+
+        let actual = r.checksum.value();
+        let expected = u64::decode(&mut r.inner)?;
+        if expected != actual {
+            return Err(SnapshotError::ChecksumMismatch { expected, actual });
+        }
+        if count != len {
+            return Err(SnapshotError::Corrupted("length mismatch"));
+        }
+
+        // inner layers are rebuilt packed, so they are never taller than the written tree
+        let tree = Self::from_leaves(NodeStoreVec::new(), leaves.into_iter());
+        if tree.height() > height as usize || (len == 0 && height != 0) {
+            return Err(SnapshotError::Corrupted("height mismatch"));
+        }
+        Ok(tree)
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Returns the count of inner node layers, 0 means the root is a leaf
+    pub(crate) fn height(&self) -> usize {
+        let mut height = 0;
+        let mut node_id = self.root;
+        while let NodeId::Inner(id) = node_id {
+            node_id = self.node_store.get_inner(id).child_id(0);
+            height += 1;
+        }
+        height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::augment::count::Count;
+
+    type Tree = BPlusTree<NodeStoreVec<u64, (String, i32), Count>>;
+
+    fn create_tree(size: u64) -> Tree {
+        let mut tree = Tree::new(NodeStoreVec::new());
+        for i in (0..size).rev() {
+            tree.insert(i * 3, (format!("v{i}"), i as i32));
+        }
+        tree
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        for size in [0, 1, 64, 65, 10000] {
+            let tree = create_tree(size);
+            let mut buf = Vec::new();
+            tree.write_snapshot(&mut buf).unwrap();
+
+            let loaded = Tree::read_snapshot(buf.as_slice()).unwrap();
+            loaded.check_invariants().unwrap();
+            assert_eq!(loaded.len(), tree.len());
+            assert_eq!(loaded.root_augmentation().count(), tree.len());
+            assert!(loaded.iter().eq(tree.iter()));
+            for i in 0..size {
+                assert_eq!(loaded.get(&(i * 3)).unwrap().1, i as i32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_snapshot_rejects_corruption() {
+        let tree = create_tree(1000);
+        let mut buf = Vec::new();
+        tree.write_snapshot(&mut buf).unwrap();
+
+        // flip one byte in leaf data
+        let mut corrupted = buf.clone();
+        corrupted[buf.len() / 2] ^= 0x10;
+        assert!(Tree::read_snapshot(corrupted.as_slice()).is_err());
+
+        // truncated
+        assert!(matches!(
+            Tree::read_snapshot(&buf[..buf.len() - 3]),
+            Err(SnapshotError::Io(_))
+        ));
+
+        // tampered checksum
+        let mut corrupted = buf.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(matches!(
+            Tree::read_snapshot(corrupted.as_slice()),
+            Err(SnapshotError::ChecksumMismatch { .. })
+        ));
+
+        // wrong height with a valid checksum
+        let mut corrupted = buf.clone();
+        corrupted[16..20].copy_from_slice(&0u32.to_le_bytes());
+        let body = corrupted.len() - 8;
+        let mut checksum = Checksum::new();
+        checksum.update(&corrupted[..body]);
+        corrupted[body..].copy_from_slice(&checksum.value().to_le_bytes());
+        assert!(matches!(
+            Tree::read_snapshot(corrupted.as_slice()),
+            Err(SnapshotError::Corrupted("height mismatch"))
+        ));
+
+        let mut corrupted = buf.clone();
+        corrupted[0] = b'X';
+        assert!(matches!(
+            Tree::read_snapshot(corrupted.as_slice()),
+            Err(SnapshotError::BadMagic)
+        ));
+    }
+}
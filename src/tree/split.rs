@@ -0,0 +1,264 @@
+use std::{borrow::Borrow, cmp::Ordering, ops::Bound};
+
+use super::{entry_ref::VisitStack, notify, BPlusTree, Change, EntryRef, NodeId, NodeStore};
+
+/// A side holding at most `len / SMALL_SIDE_DIVISOR` elements is moved element by element,
+/// otherwise both trees are rebuilt
+const SMALL_SIDE_DIVISOR: usize = 16;
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Split the tree into two at `key`. Returns a new tree with all elements greater than or
+    /// equal to `key`, and leaves the rest in `self`.
+    ///
+    /// If one side is small, its elements are moved one by one, which costs O(k log n) for k
+    /// moved elements. Otherwise both trees are rebuilt with `bulk_load` in O(n). The new
+    /// tree's store is configured like `self`'s. The observer stays with `self`, and moved
+    /// elements are reported as removed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64>::new());
+    /// for i in 0..100 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let right = tree.split_off(&60);
+    /// assert_eq!(tree.len(), 60);
+    /// assert_eq!(right.len(), 40);
+    /// assert_eq!(right.first().unwrap().0, &60);
+    /// ```
+    pub fn split_off<Q: ?Sized + Ord>(&mut self, key: &Q) -> Self
+    where
+        S::K: Borrow<Q>,
+    {
+        let small = self.len / SMALL_SIDE_DIVISOR;
+        let right_len = self
+            .range((Bound::Included(key), Bound::Unbounded))
+            .take(small + 1)
+            .count();
+        let left_len = self
+            .range((Bound::Unbounded, Bound::Excluded(key)))
+            .take(small + 1)
+            .count();
+
+        if right_len <= small {
+            let mut right = Self::new(self.node_store.empty_like());
+            while self.last().is_some_and(|(k, _)| k.borrow() >= key) {
+                let (k, v) = self.take_edge(true).unwrap();
+                notify(self.observer.as_mut(), Change::Remove(&k, &v));
+                right.insert(k, v);
+            }
+            return right;
+        }
+
+        if left_len <= small {
+            let mut left = Self::new(self.node_store.empty_like());
+            while self.first().is_some_and(|(k, _)| k.borrow() < key) {
+                let (k, v) = self.take_edge(false).unwrap();
+                left.insert(k, v);
+            }
+            // self becomes the left part, the observer and stats stay with it
+            std::mem::swap(self, &mut left);
+            std::mem::swap(&mut self.observer, &mut left.observer);
+            std::mem::swap(&mut self.st, &mut left.st);
+            if self.observer.is_some() {
+                for (k, v) in left.iter() {
+                    notify(self.observer.as_mut(), Change::Remove(k, v));
+                }
+            }
+            return left;
+        }
+
+        let (left_store, right_store) =
+            (self.node_store.empty_like(), self.node_store.empty_like());
+        let mut tree = std::mem::replace(self, Self::new(self.node_store.empty_like()));
+        // keep the observer and stats in self, so a panicking observer doesn't lose them
+        self.observer = tree.observer.take();
+        self.st = std::mem::take(&mut tree.st);
+        let (left, right): (Vec<_>, Vec<_>) = tree.into_iter().partition(|(k, _)| k.borrow() < key);
+        for (k, v) in right.iter() {
+            notify(self.observer.as_mut(), Change::Remove(k, v));
+        }
+
+        let mut left = Self::from_leaves(left_store, Self::pack_leaves(left.into_iter()));
+        left.observer = self.observer.take();
+        left.st = std::mem::take(&mut self.st);
+        *self = left;
+        Self::from_leaves(right_store, Self::pack_leaves(right.into_iter()))
+    }
+
+    /// Move all elements from `other` into `self`, leaving `other` empty.
+    /// If a key exists in both, the value from `other` wins.
+    ///
+    /// If `other` is small, its elements are inserted one by one, otherwise both trees are
+    /// merged and rebuilt with `bulk_load` in O(n). Moved elements are reported to `other`'s
+    /// observer as removed, and to `self`'s observer as inserted or updated.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut left = BPlusTree::new(NodeStoreVec::<i64, i64>::new());
+    /// let mut right = BPlusTree::new(NodeStoreVec::<i64, i64>::new());
+    /// for i in 0..10 {
+    ///     left.insert(i, i);
+    ///     right.insert(i + 5, 0);
+    /// }
+    ///
+    /// left.append(&mut right);
+    /// assert!(right.is_empty());
+    /// assert_eq!(left.len(), 15);
+    /// assert_eq!(left.get(&5), Some(&0));
+    /// ```
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+        if self.is_empty() && self.observer.is_none() && other.observer.is_none() {
+            std::mem::swap(self, other);
+            std::mem::swap(&mut self.st, &mut other.st);
+            return;
+        }
+
+        if other.len() <= self.len() / SMALL_SIDE_DIVISOR {
+            while let Some((k, v)) = other.take_edge(false) {
+                notify(other.observer.as_mut(), Change::Remove(&k, &v));
+                self.insert(k, v);
+            }
+            return;
+        }
+
+        let node_store = self.node_store.empty_like();
+        let mut left = std::mem::replace(self, Self::new(self.node_store.empty_like()));
+        // observers and stats stay in the trees, so a panicking observer doesn't lose them
+        self.observer = left.observer.take();
+        self.st = std::mem::take(&mut left.st);
+        let mut right = std::mem::replace(other, Self::new(other.node_store.empty_like()));
+        other.observer = right.observer.take();
+
+        let mut merged = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.into_iter().peekable();
+        let mut right = right
+            .into_iter()
+            .inspect(|(k, v)| notify(other.observer.as_mut(), Change::Remove(k, v)))
+            .peekable();
+        loop {
+            let from_right = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => match l.0.cmp(&r.0) {
+                    Ordering::Less => false,
+                    Ordering::Greater => true,
+                    Ordering::Equal => {
+                        let (_, old) = left.next().unwrap();
+                        let (k, new) = right.next().unwrap();
+                        notify(self.observer.as_mut(), Change::Update(&k, &old, &new));
+                        merged.push((k, new));
+                        continue;
+                    }
+                },
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (None, None) => break,
+            };
+
+            if from_right {
+                let (k, v) = right.next().unwrap();
+                notify(self.observer.as_mut(), Change::Insert(&k, &v));
+                merged.push((k, v));
+            } else {
+                merged.extend(left.next());
+            }
+        }
+
+        let mut merged = Self::from_leaves(node_store, Self::pack_leaves(merged.into_iter()));
+        merged.observer = self.observer.take();
+        merged.st = std::mem::take(&mut self.st);
+        *self = merged;
+    }
+
+    /// Remove the first or last element, without reporting to the observer
+    fn take_edge(&mut self, last: bool) -> Option<(S::K, S::V)> {
+        let mut stack = VisitStack::new();
+        let mut node_id = self.root;
+        while let NodeId::Inner(inner_id) = node_id {
+            let inner = self.node_store.get_inner(inner_id);
+            let child_idx = if last { inner.len() } else { 0 };
+            let child_id = inner.child_id(child_idx);
+            stack.push(inner_id, child_idx, child_id);
+            node_id = child_id;
+        }
+
+        let leaf_id = node_id.leaf_id()?;
+        let leaf_len = self.node_store.get_leaf(leaf_id).len();
+        let slot = match (leaf_len, last) {
+            (0, _) => return None,
+            (_, true) => leaf_len - 1,
+            (_, false) => 0,
+        };
+        Self::remove_by_ref(EntryRef::new(&mut *self, stack, leaf_id, slot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::{augment::count::Count, BPlusTree, NodeStoreVec};
+
+    #[test]
+    fn test_split_off_and_append() {
+        let mut rng = rand::thread_rng();
+        for round in 0..40 {
+            let size = rng.gen_range(0..2000);
+            let mut tree = BPlusTree::new(NodeStoreVec::<u32, u32, Count>::new());
+            for i in 0..size {
+                tree.insert(i * 2, i);
+            }
+            for n in (0..size as usize).step_by(97) {
+                assert_eq!(tree.nth_key(n), tree.iter().nth(n).map(|(k, _)| k));
+            }
+
+            // half of the rounds split near the edges, which moves the small side
+            let at = match round % 4 {
+                0 => rng.gen_range(0..size / 20 + 2),
+                1 => size * 2 - rng.gen_range(0..size / 20 + 2).min(size * 2),
+                _ => rng.gen_range(0..size * 2 + 2),
+            };
+            // counters live as long as the tree, whichever way it is split or appended
+            let splits = tree.stats().operations.split_leaf;
+            let mut right = tree.split_off(&at);
+            assert!(tree.stats().operations.split_leaf >= splits);
+            assert!(tree.iter().all(|(k, _)| *k < at));
+            assert!(right.iter().all(|(k, _)| *k >= at));
+            assert_eq!(tree.len() + right.len(), size as usize);
+            tree.check_invariants().unwrap();
+            right.check_invariants().unwrap();
+
+            tree.append(&mut right);
+            assert!(right.is_empty());
+            assert!(tree.stats().operations.split_leaf >= splits);
+            tree.check_invariants().unwrap();
+            assert!(tree
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .eq((0..size).map(|i| (i * 2, i))));
+
+            // a small overlapping tree is inserted, its values win
+            let mut small = BPlusTree::new(NodeStoreVec::<u32, u32, Count>::new());
+            for i in (0..size).step_by(50) {
+                small.insert(i * 2, 0);
+                small.insert(i * 2 + 1, 1);
+            }
+            let small_len = small.len();
+            tree.append(&mut small);
+            assert!(small.is_empty());
+            assert!(tree.stats().operations.split_leaf >= splits);
+            tree.check_invariants().unwrap();
+            assert_eq!(tree.len(), size as usize + small_len / 2);
+            assert!((0..size)
+                .step_by(50)
+                .all(|i| tree.get(&(i * 2)) == Some(&0)));
+        }
+    }
+}
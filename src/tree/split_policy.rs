@@ -0,0 +1,171 @@
+/// How a full node picks its split point, set on node stores with `with_split_policy`.
+///
+/// A leaf split at the right edge may leave the new right leaf under the minimum fill, down
+/// to a single element, so sequential inserts fill leaves up to `left_percent`. Such leaves
+/// are filled by later inserts, or rebalanced by removes. Inner node splits are clamped so
+/// both nodes keep the minimum fill, a quarter of the capacity.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::{BPlusTree, NodeStoreVec};
+/// use sweep_bptree::tree::SplitPolicy;
+///
+/// let store = NodeStoreVec::<u64, u64>::new().with_split_policy(SplitPolicy::RIGHTMOST);
+/// let mut tree = BPlusTree::new(store);
+/// for i in 0..10_000 {
+///     tree.insert(i, i);
+/// }
+///
+/// assert!(tree.stats().leaf_fill_factor() > 0.98);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitPolicy {
+    /// Split in halves
+    #[default]
+    Even,
+    /// When the insert goes past the node's last slot, keep `left_percent` of the elements,
+    /// including the inserted one, in the left node, e.g. 90 for a 90/10 split. Other inserts
+    /// split in halves.
+    RightEdge { left_percent: u8 },
+    /// Same as `RightEdge`, but only after `run` consecutive leaf splits happened at the
+    /// right edge, which detects sequential inserts. Any other leaf split resets the run.
+    Adaptive { left_percent: u8, run: u32 },
+}
+
+impl SplitPolicy {
+    /// Split at the right edge keeping the left node full
+    pub const RIGHTMOST: Self = Self::RightEdge { left_percent: 100 };
+
+    /// Minimum size of non root leaves, edge splits may leave a single element in the new
+    /// leaf
+    pub(crate) fn leaf_minimum(self, rebalance_minimum: usize) -> usize {
+        match self {
+            Self::Even => rebalance_minimum,
+            Self::RightEdge { .. } | Self::Adaptive { .. } => 1,
+        }
+    }
+}
+
+/// `SplitPolicy` with its runtime state, held by node stores
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Splitter {
+    policy: SplitPolicy,
+    /// consecutive leaf splits at the right edge
+    edge_run: u32,
+}
+
+impl Splitter {
+    pub(crate) fn new(policy: SplitPolicy) -> Self {
+        Self {
+            policy,
+            edge_run: 0,
+        }
+    }
+
+    pub(crate) fn policy(&self) -> SplitPolicy {
+        self.policy
+    }
+
+    /// Left node size for a full node of `capacity` splitting to insert at `insert_idx`
+    pub(crate) fn split_point(
+        &mut self,
+        is_leaf: bool,
+        insert_idx: usize,
+        capacity: usize,
+    ) -> usize {
+        let at_edge = insert_idx >= capacity;
+        if is_leaf {
+            self.edge_run = if at_edge {
+                self.edge_run.saturating_add(1)
+            } else {
+                0
+            };
+        }
+
+        let left_percent = match self.policy {
+            SplitPolicy::Even => None,
+            SplitPolicy::RightEdge { left_percent } => Some(left_percent),
+            SplitPolicy::Adaptive { left_percent, run } => {
+                (self.edge_run >= run).then_some(left_percent)
+            }
+        };
+
+        match left_percent {
+            // the split holds capacity + 1 elements, the inserted one goes to the right
+            Some(percent) if at_edge => {
+                ((capacity + 1) * percent.min(100) as usize / 100).min(capacity)
+            }
+            _ => capacity / 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+
+    use super::*;
+    use crate::{augment::count::Count, BPlusTree, NodeStoreArc, NodeStoreVec};
+
+    #[test]
+    fn test_split_policy() {
+        let even_fill = {
+            let mut tree = BPlusTree::new(NodeStoreVec::<u32, u32, Count>::new());
+            for i in 0..10_000 {
+                tree.insert(i, i);
+            }
+            tree.stats().leaf_fill_factor()
+        };
+
+        // sequential inserts fill leaves by the left percent
+        for (policy, fill) in [
+            (SplitPolicy::RIGHTMOST, 1.0),
+            (SplitPolicy::RightEdge { left_percent: 90 }, 0.9),
+            (
+                SplitPolicy::Adaptive {
+                    left_percent: 100,
+                    run: 2,
+                },
+                1.0,
+            ),
+        ] {
+            let store = NodeStoreVec::<u32, u32, Count>::new().with_split_policy(policy);
+            assert_eq!(store.split_policy(), policy);
+            let mut tree = BPlusTree::new(store);
+            for i in 0..10_000 {
+                tree.insert(i, i);
+            }
+            tree.check_invariants().unwrap();
+            let stats = tree.stats();
+            assert!(
+                (stats.leaf_fill_factor() - fill).abs() < 0.02,
+                "{policy:?}: {}",
+                stats.leaf_fill_factor()
+            );
+            assert!(stats.leaf_fill_factor() > even_fill + 0.3);
+            assert!(stats.inner_nodes < 10);
+        }
+
+        // random inserts don't trip adaptive detection, and biased splits keep invariants
+        let mut keys = (0..20_000).collect::<Vec<u32>>();
+        keys.shuffle(&mut rand::thread_rng());
+        for policy in [
+            SplitPolicy::RIGHTMOST,
+            SplitPolicy::Adaptive {
+                left_percent: 100,
+                run: 3,
+            },
+        ] {
+            let store = NodeStoreArc::<u32, u32, Count>::new().with_split_policy(policy);
+            let mut tree = BPlusTree::new(store);
+            for k in keys.iter() {
+                tree.insert(*k, *k);
+            }
+            tree.check_invariants().unwrap();
+            for k in keys.iter().take(15_000) {
+                tree.remove(k);
+            }
+            tree.check_invariants().unwrap();
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use super::{BPlusTree, NodeId, NodeStore, Statistic};
+
+/// Structural statistics of a tree, computed by `BPlusTree::stats`
+#[derive(Debug, Clone, Default)]
+pub struct TreeStats {
+    /// Element count
+    pub len: usize,
+    /// Levels of the tree, a tree with only a root leaf has height 1
+    pub height: usize,
+    /// Count of inner nodes
+    pub inner_nodes: usize,
+    /// Count of leaf nodes
+    pub leaf_nodes: usize,
+    /// Max element count of a leaf
+    pub leaf_capacity: usize,
+    /// Leaf fill factor histogram, bucket `i` counts leaves filled in `[i * 10%, (i + 1) * 10%)`,
+    /// full leaves are counted in the last bucket
+    pub leaf_fill: [usize; 10],
+    /// Node slots not holding a node in the store
+    pub free_slots: usize,
+    /// Estimated heap bytes of the store, see `NodeStore::heap_bytes`
+    pub heap_bytes: usize,
+    /// Counters of rebalance operations since the tree was created
+    pub operations: Statistic,
+}
+
+impl TreeStats {
+    /// Average fill factor of leaves, 0 for empty tree
+    pub fn leaf_fill_factor(&self) -> f64 {
+        if self.leaf_nodes == 0 {
+            return 0.;
+        }
+        self.len as f64 / (self.leaf_capacity * self.leaf_nodes) as f64
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Compute structural statistics by walking all nodes, time complexity is O(nodes).
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// let stats = tree.stats();
+    /// assert_eq!(stats.height, 2);
+    /// assert_eq!(stats.inner_nodes, 1);
+    /// assert_eq!(stats.leaf_fill.iter().sum::<usize>(), stats.leaf_nodes);
+    /// assert_eq!(stats.operations.split_leaf as usize, stats.leaf_nodes - 1);
+    /// assert!(stats.heap_bytes > 0);
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            len: self.len,
+            free_slots: self.node_store.free_slots(),
+            heap_bytes: self.node_store.heap_bytes(),
+            leaf_capacity: S::leaf_n() as usize,
+            operations: self.st.clone(),
+            ..TreeStats::default()
+        };
+
+        let mut level = vec![self.root];
+        while !level.is_empty() {
+            stats.height += 1;
+            let mut next_level = Vec::new();
+            for node_id in level {
+                match node_id {
+                    NodeId::Inner(inner_id) => {
+                        stats.inner_nodes += 1;
+                        let inner = self.node_store.get_inner(inner_id);
+                        next_level.extend((0..=inner.len()).map(|idx| inner.child_id(idx)));
+                    }
+                    NodeId::Leaf(leaf_id) => {
+                        stats.leaf_nodes += 1;
+                        let len = self.node_store.get_leaf(leaf_id).len();
+                        let bucket = (len * 10 / stats.leaf_capacity).min(9);
+                        stats.leaf_fill[bucket] += 1;
+                    }
+                }
+            }
+            level = next_level;
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{augment::count::Count, tree::LeafNode, NodeStoreArc, NodeStoreVec};
+
+    #[test]
+    fn test_stats() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<u32, u32, Count>::new());
+        let stats = tree.stats();
+        assert_eq!(
+            (stats.height, stats.leaf_nodes, stats.inner_nodes),
+            (1, 1, 0)
+        );
+        assert_eq!(stats.leaf_fill[0], 1);
+        assert_eq!(stats.leaf_fill_factor(), 0.);
+
+        for i in 0..10_000 {
+            tree.insert(i, i);
+        }
+        let stats = tree.stats();
+        assert_eq!(stats.height, 3);
+        assert_eq!(stats.operations.split_leaf as usize, stats.leaf_nodes - 1);
+        // each root split adds a new root besides the split node
+        assert_eq!(
+            stats.operations.split_inner as usize + stats.height - 1,
+            stats.inner_nodes
+        );
+        assert_eq!(stats.free_slots, 0);
+        // sequential insert leaves half full leaves
+        assert_eq!(stats.leaf_fill[5], stats.leaf_nodes - 1);
+        let fill = stats.leaf_fill_factor();
+        assert!(fill > 0.5 && fill < 0.55);
+
+        for i in 0..9_000 {
+            tree.remove(&i);
+        }
+        let after_remove = tree.stats();
+        assert!(after_remove.leaf_nodes < stats.leaf_nodes);
+        assert_eq!(
+            after_remove.free_slots,
+            stats.leaf_nodes + stats.inner_nodes
+                - after_remove.leaf_nodes
+                - after_remove.inner_nodes
+        );
+        assert!(after_remove.heap_bytes < stats.heap_bytes);
+
+        let bulk =
+            BPlusTree::<NodeStoreArc<u32, u32>>::bulk_load((0..10_000).map(|i| (i, i)).collect());
+        let stats = bulk.stats();
+        assert_eq!(stats.leaf_fill[9], stats.leaf_nodes - 1);
+        assert_eq!(stats.free_slots, 0);
+        assert!(stats.heap_bytes >= stats.leaf_nodes * std::mem::size_of::<LeafNode<u32, u32>>());
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    borrow::Borrow,
+    collections::BTreeMap,
+    ops::{Bound, Deref},
+};
+
+use super::{BPlusTree, NodeStore};
+
+/// Changes made inside `BPlusTree::transaction`. Writes are buffered and only applied to the
+/// tree when the transaction commits, reads see the buffered writes.
+pub struct Transaction<'a, S: NodeStore> {
+    tree: &'a BPlusTree<S>,
+    /// pending changes, None means removed
+    pending: BTreeMap<S::K, Option<S::V>>,
+    len: usize,
+}
+
+/// The value replaced by `Transaction::insert` or `Transaction::remove`. A value written
+/// earlier in the same transaction is moved out, a value in the tree is borrowed, since it
+/// stays in the tree until the transaction commits.
+#[derive(Debug)]
+pub enum Replaced<'a, V> {
+    /// Written earlier in the transaction
+    Pending(V),
+    /// Still in the tree
+    Committed(&'a V),
+}
+
+impl<V> Deref for Replaced<'_, V> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        match self {
+            Replaced::Pending(v) => v,
+            Replaced::Committed(v) => v,
+        }
+    }
+}
+
+impl<V: Clone> Replaced<'_, V> {
+    /// Returns the value, the one in the tree is cloned
+    pub fn into_owned(self) -> V {
+        match self {
+            Replaced::Pending(v) => v,
+            Replaced::Committed(v) => v.clone(),
+        }
+    }
+}
+
+impl<'a, S: NodeStore> Transaction<'a, S> {
+    fn new(tree: &'a BPlusTree<S>) -> Self {
+        Self {
+            tree,
+            pending: BTreeMap::new(),
+            len: tree.len(),
+        }
+    }
+
+    /// Returns item count with pending changes applied
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no item left with pending changes applied
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a reference to the value for `key`, with pending changes applied
+    pub fn get<Q: ?Sized + Ord>(&self, key: &Q) -> Option<&S::V>
+    where
+        S::K: Borrow<Q>,
+    {
+        match self.pending.get(key) {
+            Some(pending) => pending.as_ref(),
+            None => self.tree.get(key),
+        }
+    }
+
+    /// Returns true if `key` exists with pending changes applied
+    pub fn contains_key<Q: ?Sized + Ord>(&self, key: &Q) -> bool
+    where
+        S::K: Borrow<Q>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Insert a key-value pair, returns the previous value, see `Replaced`
+    pub fn insert(&mut self, key: S::K, value: S::V) -> Option<Replaced<'a, S::V>> {
+        let tree = self.tree;
+        let old = match self.pending.get_mut(&key) {
+            Some(pending) => pending.replace(value).map(Replaced::Pending),
+            None => {
+                let old = tree.get(&key).map(Replaced::Committed);
+                self.pending.insert(key, Some(value));
+                old
+            }
+        };
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Remove a key, returns the previous value, see `Replaced`
+    pub fn remove<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<Replaced<'a, S::V>>
+    where
+        S::K: Borrow<Q>,
+    {
+        let tree = self.tree;
+        let old = match self.pending.get_mut(key) {
+            Some(pending) => pending.take().map(Replaced::Pending),
+            None => {
+                let (k, v) = tree
+                    .range((Bound::Included(key), Bound::Included(key)))
+                    .next()?;
+                self.pending.insert(k.clone(), None);
+                Some(Replaced::Committed(v))
+            }
+        };
+        if old.is_some() {
+            self.len -= 1;
+        }
+        old
+    }
+}
+
+impl<S: NodeStore> BPlusTree<S> {
+    /// Run `f` as a transaction. Changes made through `tx` are applied if `f` returns `Ok`,
+    /// and discarded if `f` returns `Err` or panics, leaving the tree untouched.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64>::new());
+    /// tree.insert(1, 100);
+    ///
+    /// // move 30 from key 1 to key 2
+    /// let r = tree.transaction(|tx| {
+    ///     let from = *tx.remove(&1).unwrap();
+    ///     tx.insert(1, from - 30);
+    ///     tx.insert(2, 30);
+    ///     assert_eq!(tx.get(&1), Some(&70));
+    ///     Ok::<_, ()>(())
+    /// });
+    /// assert!(r.is_ok());
+    /// assert_eq!(tree.get(&2), Some(&30));
+    ///
+    /// let r = tree.transaction(|tx| {
+    ///     tx.insert(3, 0);
+    ///     tx.remove(&1);
+    ///     Err::<(), _>("rejected")
+    /// });
+    /// assert_eq!(r, Err("rejected"));
+    /// assert_eq!(tree.len(), 2);
+    /// assert_eq!(tree.get(&1), Some(&70));
+    /// ```
+    pub fn transaction<R, E>(
+        &mut self,
+        f: impl FnOnce(&mut Transaction<'_, S>) -> Result<R, E>,
+    ) -> Result<R, E> {
+        let mut tx = Transaction::new(self);
+        let r = f(&mut tx)?;
+
+        let pending = tx.pending;
+        for (k, v) in pending {
+            match v {
+                Some(v) => {
+                    self.insert(k, v);
+                }
+                None => {
+                    self.remove(&k);
+                }
+            }
+        }
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::Replaced;
+    use crate::{augment::count::Count, BPlusTree, NodeStoreVec};
+
+    #[test]
+    fn test_transaction_rollback() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<u32, u32, Count>::new());
+        for i in 0..1000 {
+            tree.insert(i, i);
+        }
+        let before = tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>();
+
+        let r: Result<(), &str> = tree.transaction(|tx| {
+            for i in 0..500 {
+                tx.remove(&i);
+            }
+            tx.insert(2000, 0);
+            assert_eq!(tx.len(), 501);
+            assert!(tx.get(&10).is_none());
+            Err("abort")
+        });
+        assert!(r.is_err());
+
+        let r = catch_unwind(AssertUnwindSafe(|| {
+            tree.transaction(|tx| {
+                tx.insert(3000, 0);
+                panic!("boom");
+                #[allow(unreachable_code)]
+                Ok::<_, ()>(())
+            })
+        }));
+        assert!(r.is_err());
+
+        assert!(tree
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq(before.iter().copied()));
+        assert_eq!(tree.root_augmentation().count(), 1000);
+
+        tree.transaction(|tx| {
+            for i in 0..500 {
+                assert_eq!(tx.remove(&i).as_deref(), Some(&i));
+            }
+            assert!(tx.insert(1, 1).is_none());
+            assert_eq!(tx.insert(999, 0).as_deref(), Some(&999));
+            // values written in the transaction are moved out
+            assert!(matches!(tx.insert(999, 1), Some(Replaced::Pending(0))));
+            assert!(matches!(tx.remove(&1), Some(Replaced::Pending(1))));
+            assert!(tx.remove(&1).is_none());
+            assert_eq!(tx.insert(1, 1).map(Replaced::into_owned), None);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert_eq!(tree.len(), 501);
+        assert_eq!(tree.root_augmentation().count(), 501);
+        assert_eq!(tree.get(&999), Some(&1));
+        assert_eq!(tree.get(&1), Some(&1));
+
+        // values need not be Clone, keys are removed by borrowed form
+        #[derive(Debug, PartialEq)]
+        struct NoClone(u32);
+        let mut tree = BPlusTree::new(NodeStoreVec::<String, NoClone>::new());
+        tree.insert("a".to_string(), NoClone(1));
+        tree.transaction(|tx| {
+            assert_eq!(tx.remove("a").as_deref(), Some(&NoClone(1)));
+            assert!(tx.insert("b".to_string(), NoClone(2)).is_none());
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert_eq!(tree.get("a"), None);
+        assert_eq!(tree.get("b"), Some(&NoClone(2)));
+    }
+}
@@ -1,5 +1,6 @@
 use super::{
-    entry_ref::EntryRef, BPlusTree, InnerMergeResult, InnerNode, LeafDeleteResult, NodeStore,
+    entry_ref::EntryRef, BPlusTree, InnerMergeResult, InnerNode, LeafDeleteResult, LeafNode,
+    NodeStore, RebalancePolicy,
 };
 use crate::augment::Augmentation;
 use std::borrow::Borrow;
@@ -24,9 +25,10 @@ impl<S: NodeStore> BPlusTree<S> {
         } = entry_ref;
 
         let mut r = {
+            let min_size = tree.rebalance_policy().leaf_minimum::<S::K, S::V>();
             let leaf = tree.node_store.get_mut_leaf(leaf_id);
 
-            match leaf.try_delete_at(offset) {
+            match leaf.try_delete_at(offset, min_size) {
                 LeafDeleteResult::Done(kv) => DeleteDescendResult::Done(kv),
                 LeafDeleteResult::UnderSize(idx) => DeleteDescendResult::LeafUnderSize(idx),
             }
@@ -63,12 +65,13 @@ impl<S: NodeStore> BPlusTree<S> {
                     let r = match r {
                         DeleteDescendResult::Done(kv) => Some(kv),
                         DeleteDescendResult::InnerUnderSize(deleted_item) => {
-                            let root = tree
-                                .node_store
-                                .get_mut_inner(unsafe { tree.root.inner_id_unchecked() });
+                            let root_id = unsafe { tree.root.inner_id_unchecked() };
+                            let root = tree.node_store.get_mut_inner(root_id);
 
                             if root.is_empty() {
                                 tree.root = root.child_id(0);
+                                // the empty root is unreachable now, free it
+                                drop(tree.node_store.take_inner(root_id));
                             }
 
                             Some(deleted_item)
@@ -151,10 +154,16 @@ impl<S: NodeStore> BPlusTree<S> {
             None
         };
 
+        let policy = self.rebalance_policy();
+        let min_size = policy.leaf_minimum::<S::K, S::V>();
+        let able_to_lend = |leaf: &LeafNode<S::K, S::V>| {
+            policy != RebalancePolicy::FreeAtEmpty && leaf.len() > min_size
+        };
+
         let action: FixAction = match (prev_sibling, next_sibling) {
             (Some(p), Some(n)) => {
-                if p.able_to_lend() {
-                    if n.able_to_lend() {
+                if able_to_lend(p) {
+                    if able_to_lend(n) {
                         if p.len() > n.len() {
                             FixAction::RotateRight
                         } else {
@@ -163,21 +172,21 @@ impl<S: NodeStore> BPlusTree<S> {
                     } else {
                         FixAction::RotateRight
                     }
-                } else if n.able_to_lend() {
+                } else if able_to_lend(n) {
                     FixAction::RotateLeft
                 } else {
                     FixAction::MergeLeft
                 }
             }
             (Some(p), None) => {
-                if p.able_to_lend() {
+                if able_to_lend(p) {
                     FixAction::RotateRight
                 } else {
                     FixAction::MergeLeft
                 }
             }
             (None, Some(n)) => {
-                if n.able_to_lend() {
+                if able_to_lend(n) {
                     FixAction::RotateLeft
                 } else {
                     FixAction::MergeRight
@@ -186,6 +195,11 @@ impl<S: NodeStore> BPlusTree<S> {
             _ => unreachable!(),
         };
 
+        // the leaf is emptied, merging frees it
+        if policy == RebalancePolicy::FreeAtEmpty {
+            self.st.free_empty_leaf += 1;
+        }
+
         match action {
             FixAction::RotateRight => {
                 let deleted = Self::rotate_right_for_leaf(
@@ -208,7 +222,9 @@ impl<S: NodeStore> BPlusTree<S> {
                 DeleteDescendResult::Done(deleted)
             }
             FixAction::MergeLeft => {
-                self.st.merge_with_left_leaf += 1;
+                if policy != RebalancePolicy::FreeAtEmpty {
+                    self.st.merge_with_left_leaf += 1;
+                }
                 // merge with prev node
 
                 Self::merge_leaf_node_left(
@@ -219,7 +235,9 @@ impl<S: NodeStore> BPlusTree<S> {
                 )
             }
             FixAction::MergeRight => {
-                self.st.merge_with_right_leaf += 1;
+                if policy != RebalancePolicy::FreeAtEmpty {
+                    self.st.merge_with_right_leaf += 1;
+                }
                 // merge with next node
 
                 Self::merge_leaf_node_with_right(
@@ -339,7 +357,7 @@ impl<S: NodeStore> BPlusTree<S> {
         let right_id = unsafe { node.child_id(slot + 1).leaf_id_unchecked() };
 
         let left = node_store.get_mut_leaf(left_id);
-        debug_assert!(left.able_to_lend());
+        debug_assert!(left.len() > 1);
 
         let kv = left.pop();
         node.set_augmentation(slot, S::Augmentation::from_leaf(left.keys()));
@@ -367,7 +385,7 @@ impl<S: NodeStore> BPlusTree<S> {
         let right_id = unsafe { parent.child_id(slot + 1).leaf_id_unchecked() };
 
         let right = node_store.get_mut_leaf(right_id);
-        debug_assert!(right.able_to_lend());
+        debug_assert!(right.len() > 1);
 
         let kv = right.pop_front();
         parent.set_augmentation(slot + 1, S::Augmentation::from_leaf(right.keys()));
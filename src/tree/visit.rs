@@ -1,15 +1,113 @@
-use crate::NodeStore;
+use std::{convert::Infallible, fmt, marker::PhantomData, ops::Range};
+
+use super::{
+    entry_ref::{EntryRef, VisitStack},
+    notify, BPlusTree, Change, Cursor, InnerNode, InnerNodeId, LeafNodeId, NodeId,
+};
+use crate::{tree::Augmentation, Key, NodeStore};
 
 /// This kind visit is used to visit the tree from root to leaf, each layer visit only one node.
 /// Mainly used as search like visit. Each inner node will returns at most one child to visit.
 /// Time complexity for this visit is log(n)
+///
+/// Visitors may fail with their own `Error`, use `std::convert::Infallible` if it never fails.
 pub trait DescendVisit<K, V, A> {
     type Result;
+    type Error;
+
+    fn visit_inner(
+        &mut self,
+        keys: &[K],
+        augmentations: &[A],
+        ctx: VisitContext<'_, K>,
+    ) -> Result<DescendVisitResult<Self::Result>, Self::Error>;
+
+    fn visit_leaf(
+        &mut self,
+        keys: &[K],
+        values: &[V],
+        ctx: VisitContext<'_, K>,
+    ) -> Result<Option<Self::Result>, Self::Error>;
+}
+
+/// Position of the node being visited
+#[derive(Debug)]
+pub struct VisitContext<'a, K> {
+    /// Depth of the node, root is 0
+    pub depth: usize,
+    /// Keys in the node are greater than or equal to `lower`, None for unbounded
+    pub lower: Option<&'a K>,
+    /// Keys in the node are less than `upper`, None for unbounded
+    pub upper: Option<&'a K>,
+}
+
+impl<K> Clone for VisitContext<'_, K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
 
-    fn visit_inner(&mut self, keys: &[K], augmentations: &[A]) -> DescendVisitResult<Self::Result>;
-    fn visit_leaf(&mut self, keys: &[K], values: &[V]) -> Option<Self::Result>;
+impl<K> Copy for VisitContext<'_, K> {}
+
+impl<'a, K> VisitContext<'a, K> {
+    fn root() -> Self {
+        Self {
+            depth: 0,
+            lower: None,
+            upper: None,
+        }
+    }
+
+    /// Context of child `idx` of the inner node with `keys`
+    fn child(&self, keys: &'a [K], idx: usize) -> Self {
+        Self {
+            depth: self.depth + 1,
+            lower: idx.checked_sub(1).map(|i| &keys[i]).or(self.lower),
+            upper: keys.get(idx).or(self.upper),
+        }
+    }
+}
+
+/// Error of descend visit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VisitError<E> {
+    /// Visitor returned `GoDown` with a child index out of range
+    InvalidChild {
+        /// Depth of the inner node
+        depth: usize,
+        /// The returned index
+        index: usize,
+        /// Child count of the inner node
+        len: usize,
+    },
+    /// Visitor returned `LeafEdit::Modified` or `LeafEdit::Remove` with a slot out of range
+    InvalidSlot {
+        /// The returned slot
+        index: usize,
+        /// Item count of the leaf
+        len: usize,
+    },
+    /// Error returned by the visitor
+    Visitor(E),
 }
 
+impl<E: fmt::Display> fmt::Display for VisitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VisitError::InvalidChild { depth, index, len } => write!(
+                f,
+                "invalid child index {index} at depth {depth}, node has {len} children"
+            ),
+            VisitError::InvalidSlot { index, len } => {
+                write!(f, "invalid slot {index}, leaf has {len} items")
+            }
+            VisitError::Visitor(e) => write!(f, "visitor error: {e}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for VisitError<E> {}
+
 pub enum DescendVisitResult<R> {
     /// Go down to index
     GoDown(usize),
@@ -20,40 +118,1105 @@ pub enum DescendVisitResult<R> {
     Complete(R),
 }
 
+/// Same as `DescendVisit`, but the leaf's values are mutable, and the visitor may edit the
+/// located leaf through `LeafEdit`.
+pub trait DescendVisitMut<K, V, A> {
+    type Result;
+    type Error;
+
+    fn visit_inner(
+        &mut self,
+        keys: &[K],
+        augmentations: &[A],
+        ctx: VisitContext<'_, K>,
+    ) -> Result<DescendVisitResult<Self::Result>, Self::Error>;
+
+    /// Visit the leaf, returns the result and the edit to apply.
+    /// Values changed in place are reported to observer only through `LeafEdit::Modified`.
+    #[allow(clippy::type_complexity)]
+    fn visit_leaf(
+        &mut self,
+        keys: &[K],
+        values: &mut [V],
+        ctx: VisitContext<'_, K>,
+    ) -> Result<Option<(Self::Result, LeafEdit<K, V>)>, Self::Error>;
+}
+
+/// Edit of the located leaf, returned by `DescendVisitMut::visit_leaf`
+pub enum LeafEdit<K, V> {
+    /// No edit
+    None,
+    /// Value at slot is modified in place
+    Modified(usize),
+    /// Remove the item at slot
+    Remove(usize),
+    /// Insert the item, or update the value if key exists. The key is placed by its order,
+    /// it is inserted into the located leaf if it belongs there and the leaf is not full.
+    Insert(K, V),
+}
+
+/// This kind visit walks the tree from root to leaves, and may enter several children of
+/// each inner node. Subtrees not entered are skipped as a whole, so an augmentation guided
+/// scan only reads the leaves it needs.
+pub trait PruneVisit<K, V, A> {
+    /// Returns the children to enter, by index of `augmentations`
+    fn visit_inner(&mut self, keys: &[K], augmentations: &[A]) -> ChildSet;
+
+    /// Returns the slot range to yield in the leaf, defaults to all slots
+    fn visit_leaf(&mut self, keys: &[K], values: &[V]) -> Range<usize> {
+        let _ = values;
+        0..keys.len()
+    }
+}
+
+impl<K, V, A, T: PruneVisit<K, V, A> + ?Sized> PruneVisit<K, V, A> for &mut T {
+    fn visit_inner(&mut self, keys: &[K], augmentations: &[A]) -> ChildSet {
+        (**self).visit_inner(keys, augmentations)
+    }
+
+    fn visit_leaf(&mut self, keys: &[K], values: &[V]) -> Range<usize> {
+        (**self).visit_leaf(keys, values)
+    }
+}
+
+/// Set of child indices, returned by `PruneVisit::visit_inner`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChildSet(u128);
+
+impl ChildSet {
+    /// Max child index plus one, larger than any inner node's child count
+    const CAPACITY: usize = 128;
+
+    /// Empty set, skips all children
+    pub fn none() -> Self {
+        Self(0)
+    }
+
+    /// Full set, enters all children
+    pub fn all() -> Self {
+        Self(u128::MAX)
+    }
+
+    /// Add child `idx` to the set
+    ///
+    /// # Panics
+    /// if `idx` is not less than 128
+    pub fn insert(&mut self, idx: usize) {
+        assert!(idx < Self::CAPACITY, "child index out of range");
+        self.0 |= 1 << idx;
+    }
+
+    /// Returns true if child `idx` is in the set
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < Self::CAPACITY && self.0 & (1 << idx) != 0
+    }
+
+    /// Returns true if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Keep only indices less than `len`
+    fn truncate(self, len: usize) -> Self {
+        if len >= Self::CAPACITY {
+            self
+        } else {
+            Self(self.0 & ((1 << len) - 1))
+        }
+    }
+
+    /// Remove and return the smallest index
+    fn pop_first(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let idx = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(idx)
+    }
+}
+
+impl FromIterator<usize> for ChildSet {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut set = Self::none();
+        for idx in iter {
+            set.insert(idx);
+        }
+        set
+    }
+}
+
+/// Iterator over leaf slices selected by a `PruneVisit`, created by `BPlusTree::prune_visit`.
+/// Yields `(keys, values)` slices in key order, the visitor is called lazily as the
+/// iterator advances.
+pub struct PruneIter<'a, S: NodeStore, V> {
+    tree: &'a BPlusTree<S>,
+    visitor: V,
+    /// inner nodes on the path, with children left to enter
+    stack: Vec<(InnerNodeId, ChildSet)>,
+    /// root leaf to visit, for tree without inner node
+    root_leaf: Option<LeafNodeId>,
+}
+
+impl<'a, S, V> PruneIter<'a, S, V>
+where
+    S: NodeStore,
+    V: PruneVisit<S::K, S::V, S::Augmentation>,
+{
+    fn new(tree: &'a BPlusTree<S>, visitor: V) -> Self {
+        let mut me = Self {
+            tree,
+            visitor,
+            stack: Vec::new(),
+            root_leaf: None,
+        };
+        match tree.root {
+            NodeId::Inner(inner_id) => me.enter_inner(inner_id),
+            NodeId::Leaf(leaf_id) => me.root_leaf = Some(leaf_id),
+        }
+        me
+    }
+
+    fn enter_inner(&mut self, inner_id: InnerNodeId) {
+        let inner = self.tree.node_store.get_inner(inner_id);
+        let children = self
+            .visitor
+            .visit_inner(inner.keys(), inner.augmentations())
+            .truncate(inner.len() + 1);
+        if !children.is_empty() {
+            self.stack.push((inner_id, children));
+        }
+    }
+
+    fn visit_leaf(&mut self, leaf_id: LeafNodeId) -> Option<(&'a [S::K], &'a [S::V])> {
+        let leaf = self.tree.node_store.get_leaf(leaf_id);
+        let (keys, values) = (leaf.keys(), leaf.values());
+        let range = self.visitor.visit_leaf(keys, values);
+        let range = range.start.min(keys.len())..range.end.min(keys.len());
+        if range.is_empty() {
+            None
+        } else {
+            Some((&keys[range.clone()], &values[range]))
+        }
+    }
+}
+
+impl<'a, S, V> Iterator for PruneIter<'a, S, V>
+where
+    S: NodeStore,
+    V: PruneVisit<S::K, S::V, S::Augmentation>,
+{
+    type Item = (&'a [S::K], &'a [S::V]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(leaf_id) = self.root_leaf.take() {
+            return self.visit_leaf(leaf_id);
+        }
+
+        while let Some((inner_id, children)) = self.stack.last_mut() {
+            let inner_id = *inner_id;
+            let Some(child_idx) = children.pop_first() else {
+                self.stack.pop();
+                continue;
+            };
+            if children.is_empty() {
+                self.stack.pop();
+            }
+
+            match self.tree.node_store.get_inner(inner_id).child_id(child_idx) {
+                NodeId::Inner(child_id) => self.enter_inner(child_id),
+                NodeId::Leaf(leaf_id) => {
+                    if let Some(slices) = self.visit_leaf(leaf_id) {
+                        return Some(slices);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, S, V> std::iter::FusedIterator for PruneIter<'a, S, V>
+where
+    S: NodeStore,
+    V: PruneVisit<S::K, S::V, S::Augmentation>,
+{
+}
+
+/// Step returned by the fold function of `search_by_fold`
+pub enum FoldStep<Acc> {
+    /// Skip the subtree or element, with the accumulated value including it
+    Continue(Acc),
+    /// The target is inside the subtree or is the element
+    Descend,
+}
+
+/// Visitor for `search_by_fold`. Folds augmentations left to right until the fold function
+/// decides to descend, elements in leaf are folded with their own augmentation.
+pub struct FoldSearch<K, A, Acc, F> {
+    /// always Some until the visit completes
+    acc: Option<Acc>,
+    f: F,
+    _marker: PhantomData<(K, A)>,
+}
+
+impl<K, A, Acc, F> FoldSearch<K, A, Acc, F>
+where
+    F: FnMut(&Acc, &A) -> FoldStep<Acc>,
+{
+    pub fn new(init: Acc, f: F) -> Self {
+        Self {
+            acc: Some(init),
+            f,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K: Key, V, A: Augmentation<K>, Acc, F> DescendVisit<K, V, A> for FoldSearch<K, A, Acc, F>
+where
+    F: FnMut(&Acc, &A) -> FoldStep<Acc>,
+{
+    /// The slot in leaf and the accumulated value before it
+    type Result = (usize, Acc);
+    type Error = Infallible;
+
+    fn visit_inner(
+        &mut self,
+        _keys: &[K],
+        augmentations: &[A],
+        _ctx: VisitContext<'_, K>,
+    ) -> Result<DescendVisitResult<Self::Result>, Infallible> {
+        let Some(acc) = self.acc.as_mut() else {
+            return Ok(DescendVisitResult::Cancel);
+        };
+        for (idx, a) in augmentations.iter().enumerate() {
+            match (self.f)(acc, a) {
+                FoldStep::Continue(new_acc) => *acc = new_acc,
+                FoldStep::Descend => return Ok(DescendVisitResult::GoDown(idx)),
+            }
+        }
+        Ok(DescendVisitResult::Cancel)
+    }
+
+    fn visit_leaf(
+        &mut self,
+        keys: &[K],
+        _values: &[V],
+        _ctx: VisitContext<'_, K>,
+    ) -> Result<Option<Self::Result>, Infallible> {
+        Ok(self.fold_leaf(keys))
+    }
+}
+
+impl<K: Key, A: Augmentation<K>, Acc, F> FoldSearch<K, A, Acc, F>
+where
+    F: FnMut(&Acc, &A) -> FoldStep<Acc>,
+{
+    fn fold_leaf(&mut self, keys: &[K]) -> Option<(usize, Acc)> {
+        for (idx, k) in keys.iter().enumerate() {
+            let a = A::from_leaf(std::slice::from_ref(k));
+            match (self.f)(self.acc.as_ref()?, &a) {
+                FoldStep::Continue(acc) => self.acc = Some(acc),
+                FoldStep::Descend => return Some((idx, self.acc.take()?)),
+            }
+        }
+        None
+    }
+}
+
 impl<S: NodeStore> super::BPlusTree<S> {
-    /// visit the tree descendly through visitor `v`
-    pub fn descend_visit<V>(&self, mut v: V) -> Option<V::Result>
+    /// visit the tree descendly through visitor `v`. Returns None if the visit is cancelled,
+    /// or error if the visitor fails or goes down to a child not exists.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::convert::Infallible;
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::tree::visit::{DescendVisit, DescendVisitResult, VisitContext, VisitError};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// /// Goes down to the given child of root
+    /// struct Child(usize);
+    ///
+    /// impl DescendVisit<i64, i64, ()> for Child {
+    ///     type Result = (usize, Option<i64>);
+    ///     type Error = Infallible;
+    ///
+    ///     fn visit_inner(
+    ///         &mut self,
+    ///         _keys: &[i64],
+    ///         _augmentations: &[()],
+    ///         ctx: VisitContext<'_, i64>,
+    ///     ) -> Result<DescendVisitResult<Self::Result>, Infallible> {
+    ///         Ok(match ctx.depth {
+    ///             0 => DescendVisitResult::GoDown(self.0),
+    ///             depth => DescendVisitResult::Complete((depth, ctx.lower.copied())),
+    ///         })
+    ///     }
+    ///
+    ///     fn visit_leaf(
+    ///         &mut self,
+    ///         _keys: &[i64],
+    ///         _values: &[i64],
+    ///         ctx: VisitContext<'_, i64>,
+    ///     ) -> Result<Option<Self::Result>, Infallible> {
+    ///         Ok(Some((ctx.depth, ctx.lower.copied())))
+    ///     }
+    /// }
+    ///
+    /// let (depth, lower) = tree.descend_visit(Child(1)).unwrap().unwrap();
+    /// assert_eq!(depth, 1);
+    /// assert!(lower.is_some());
+    ///
+    /// assert!(matches!(
+    ///     tree.descend_visit(Child(1000)),
+    ///     Err(VisitError::InvalidChild { depth: 0, index: 1000, .. })
+    /// ));
+    /// ```
+    pub fn descend_visit<V>(&self, v: V) -> Result<Option<V::Result>, VisitError<V::Error>>
     where
         V: DescendVisit<S::K, S::V, S::Augmentation>,
     {
+        Ok(self.descend_visit_node(v)?.map(|(_, r)| r))
+    }
+
+    /// visit the tree descendly through visitor `v`, with mutable access to the located leaf.
+    /// Edits returned by the visitor are applied, and augmentations along the path are updated.
+    ///
+    /// Returns `InvalidSlot` without applying the edit if the slot of `LeafEdit::Modified` or
+    /// `LeafEdit::Remove` is out of the leaf.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::count::Count;
+    /// use std::convert::Infallible;
+    /// use sweep_bptree::tree::visit::{DescendVisitMut, DescendVisitResult, LeafEdit, VisitContext};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// /// Remove the nth element and returns its value
+    /// struct TakeNth(usize);
+    ///
+    /// impl DescendVisitMut<i64, i64, Count> for TakeNth {
+    ///     type Result = i64;
+    ///     type Error = Infallible;
+    ///
+    ///     fn visit_inner(
+    ///         &mut self,
+    ///         _keys: &[i64],
+    ///         counts: &[Count],
+    ///         _ctx: VisitContext<'_, i64>,
+    ///     ) -> Result<DescendVisitResult<i64>, Infallible> {
+    ///         for (idx, c) in counts.iter().enumerate() {
+    ///             if self.0 < c.count() {
+    ///                 return Ok(DescendVisitResult::GoDown(idx));
+    ///             }
+    ///             self.0 -= c.count();
+    ///         }
+    ///         Ok(DescendVisitResult::Cancel)
+    ///     }
+    ///
+    ///     fn visit_leaf(
+    ///         &mut self,
+    ///         _keys: &[i64],
+    ///         values: &mut [i64],
+    ///         _ctx: VisitContext<'_, i64>,
+    ///     ) -> Result<Option<(i64, LeafEdit<i64, i64>)>, Infallible> {
+    ///         Ok(values.get(self.0).map(|v| (*v, LeafEdit::Remove(self.0))))
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(tree.descend_visit_mut(TakeNth(500)), Ok(Some(500)));
+    /// assert_eq!(tree.descend_visit_mut(TakeNth(500)), Ok(Some(501)));
+    /// assert_eq!(tree.root_augmentation().count(), 998);
+    /// assert!(tree.get(&500).is_none());
+    /// ```
+    pub fn descend_visit_mut<V>(
+        &mut self,
+        mut v: V,
+    ) -> Result<Option<V::Result>, VisitError<V::Error>>
+    where
+        V: DescendVisitMut<S::K, S::V, S::Augmentation>,
+    {
+        let mut stack = VisitStack::new();
         let mut node_id = self.root;
+        let mut ctx = VisitContext::root();
+        let leaf_id = loop {
+            match node_id {
+                NodeId::Inner(inner_id) => {
+                    let inner = self.node_store.get_inner(inner_id);
+                    let visit = v
+                        .visit_inner(inner.keys(), inner.augmentations(), ctx)
+                        .map_err(VisitError::Visitor)?;
+                    match visit {
+                        DescendVisitResult::GoDown(child_idx) => {
+                            let child_id = Self::child_to_visit(inner, child_idx, ctx.depth)?;
+                            stack.push(inner_id, child_idx, child_id);
+                            ctx = ctx.child(inner.keys(), child_idx);
+                            node_id = child_id;
+                        }
+                        DescendVisitResult::Cancel => return Ok(None),
+                        DescendVisitResult::Complete(r) => return Ok(Some(r)),
+                    }
+                }
+                NodeId::Leaf(leaf_id) => break leaf_id,
+            }
+        };
+
+        // bounds are in other nodes, clone them so the leaf can be borrowed mutably
+        let (depth, lower, upper) = (ctx.depth, ctx.lower.cloned(), ctx.upper.cloned());
+        let ctx = VisitContext {
+            depth,
+            lower: lower.as_ref(),
+            upper: upper.as_ref(),
+        };
+        let leaf = self.node_store.get_mut_leaf(leaf_id);
+        let (keys, values) = leaf.keys_values_mut();
+        let Some((r, edit)) = v
+            .visit_leaf(keys, values, ctx)
+            .map_err(VisitError::Visitor)?
+        else {
+            return Ok(None);
+        };
+
+        if let LeafEdit::Modified(slot) | LeafEdit::Remove(slot) = edit {
+            let len = self.node_store.get_leaf(leaf_id).len();
+            if slot >= len {
+                return Err(VisitError::InvalidSlot { index: slot, len });
+            }
+        }
+
+        match edit {
+            LeafEdit::None => {}
+            LeafEdit::Modified(slot) => {
+                let (k, v) = self.node_store.get_leaf(leaf_id).data_at(slot);
+                notify(self.observer.as_mut(), Change::Modify(k, v));
+            }
+            LeafEdit::Remove(slot) => {
+                let (k, v) = Self::remove_by_ref(EntryRef::new(&mut *self, stack, leaf_id, slot))
+                    .expect("slot to remove must exist");
+                notify(self.observer.as_mut(), Change::Remove(&k, &v));
+            }
+            LeafEdit::Insert(k, v) => self.insert_at_leaf(stack, leaf_id, k, v),
+        }
+
+        Ok(Some(r))
+    }
+
+    /// Returns the child at `idx`, or `InvalidChild` if not exists
+    fn child_to_visit<E>(
+        inner: &InnerNode<S::K, S::Augmentation>,
+        idx: usize,
+        depth: usize,
+    ) -> Result<NodeId, VisitError<E>> {
+        if idx > inner.len() {
+            return Err(VisitError::InvalidChild {
+                depth,
+                index: idx,
+                len: inner.len() + 1,
+            });
+        }
+        Ok(inner.child_id(idx))
+    }
+
+    /// Insert into the leaf located by `stack` if it is the right place and has room,
+    /// otherwise insert from root
+    fn insert_at_leaf(&mut self, mut stack: VisitStack, leaf_id: LeafNodeId, k: S::K, v: S::V) {
+        let leaf = self.node_store.get_mut_leaf(leaf_id);
+        if leaf.is_full() || !leaf.in_range(&k) {
+            self.insert(k, v);
+            return;
+        }
+
+        let slot = match leaf.locate_slot::<S::Searcher, _>(&k) {
+            Ok(slot) => {
+                let old = std::mem::replace(leaf.value_at_mut(slot), v);
+                let (k, v) = leaf.data_at(slot);
+                notify(self.observer.as_mut(), Change::Update(k, &old, v));
+                return;
+            }
+            Err(slot) => slot,
+        };
+        leaf.try_upsert::<S::Searcher>(k, v);
+        self.len += 1;
+
+        // fix augmentations from leaf up to root
+        while let Some((parent_id, child_idx, child_id)) = stack.pop() {
+            let augmentation = Self::new_augmentation_for_id(&self.node_store, child_id);
+            self.node_store
+                .get_mut_inner(parent_id)
+                .set_augmentation(child_idx, augmentation);
+        }
+        self.root_augmentation = Self::new_augmentation_for_id(&self.node_store, self.root);
+
+        let (k, v) = self.node_store.get_leaf(leaf_id).data_at(slot);
+        notify(self.observer.as_mut(), Change::Insert(k, v));
+    }
+
+    /// Visit the tree with `v`, entering only the children it selects. Returns an iterator
+    /// over the selected leaf slices in key order.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::interval::{Endpoint, IntervalMax};
+    /// use sweep_bptree::tree::visit::{ChildSet, PruneVisit};
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<(u32, u32), (), IntervalMax<Endpoint<u32>>>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert((i, i + 1 + i % 7), ());
+    /// }
+    ///
+    /// /// Intervals ending after `p`, skips subtrees whose max end is not after `p`
+    /// struct EndAfter(u32);
+    ///
+    /// impl PruneVisit<(u32, u32), (), IntervalMax<Endpoint<u32>>> for EndAfter {
+    ///     fn visit_inner(&mut self, _keys: &[(u32, u32)], augs: &[IntervalMax<Endpoint<u32>>]) -> ChildSet {
+    ///         augs.iter()
+    ///             .enumerate()
+    ///             .filter(|(_, a)| a.max_end().map_or(false, |e| e.0 > self.0))
+    ///             .map(|(idx, _)| idx)
+    ///             .collect()
+    ///     }
+    /// }
+    ///
+    /// let count = tree
+    ///     .prune_visit(EndAfter(990))
+    ///     .flat_map(|(keys, _)| keys)
+    ///     .filter(|(_, end)| *end > 990)
+    ///     .count();
+    /// assert_eq!(count, 13);
+    /// ```
+    pub fn prune_visit<V>(&self, v: V) -> PruneIter<'_, S, V>
+    where
+        V: PruneVisit<S::K, S::V, S::Augmentation>,
+    {
+        PruneIter::new(self, v)
+    }
+
+    /// Search the first element where the fold over augmentations decides to descend.
+    /// Starting with `init`, `f` is called with the accumulated value and augmentations of
+    /// subtrees in key order, returns `FoldStep::Continue` to skip the subtree or
+    /// `FoldStep::Descend` to go into it. In leaf, `f` is called with each element's augmentation.
+    ///
+    /// Returns the cursor of the element and the accumulated value before it, or None if `f`
+    /// never descends.
+    ///
+    /// # Example
+    /// ```rust
+    /// use sweep_bptree::{BPlusTree, NodeStoreVec};
+    /// use sweep_bptree::augment::count::Count;
+    /// use sweep_bptree::tree::visit::FoldStep;
+    ///
+    /// let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+    /// for i in 0..1000 {
+    ///     tree.insert(i, i);
+    /// }
+    ///
+    /// // find the 500th element
+    /// let (cursor, skipped) = tree
+    ///     .search_by_fold(0, |acc, c| {
+    ///         if acc + c.count() > 500 {
+    ///             FoldStep::Descend
+    ///         } else {
+    ///             FoldStep::Continue(acc + c.count())
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(*cursor.key(), 500);
+    /// assert_eq!(skipped, 500);
+    /// ```
+    pub fn search_by_fold<Acc, F>(&self, init: Acc, f: F) -> Option<(Cursor<S::K>, Acc)>
+    where
+        F: FnMut(&Acc, &S::Augmentation) -> FoldStep<Acc>,
+    {
+        let (leaf_id, slot, acc) = self.search_slot_by_fold(init, f)?;
+        let k = self.node_store.get_leaf(leaf_id).data_at(slot).0.clone();
+        Some((Cursor::new(k, leaf_id, slot), acc))
+    }
+
+    /// Same as `search_by_fold`, but returns the entry
+    pub(crate) fn search_entry_by_fold<Acc, F>(&self, init: Acc, f: F) -> Option<(&S::K, &S::V)>
+    where
+        F: FnMut(&Acc, &S::Augmentation) -> FoldStep<Acc>,
+    {
+        let (leaf_id, slot, _) = self.search_slot_by_fold(init, f)?;
+        Some(self.node_store.get_leaf(leaf_id).data_at(slot))
+    }
+
+    fn search_slot_by_fold<Acc, F>(&self, init: Acc, f: F) -> Option<(LeafNodeId, usize, Acc)>
+    where
+        F: FnMut(&Acc, &S::Augmentation) -> FoldStep<Acc>,
+    {
+        let visit = self
+            .descend_visit_node(FoldSearch::new(init, f))
+            .expect("FoldSearch only goes down to existing child");
+        match visit? {
+            (NodeId::Leaf(leaf_id), (slot, acc)) => Some((leaf_id, slot, acc)),
+            (NodeId::Inner(_), _) => unreachable!("FoldSearch only completes in leaf"),
+        }
+    }
+
+    /// visit the tree descendly, returns the result and the node where visit completes
+    #[allow(clippy::type_complexity)]
+    fn descend_visit_node<V>(
+        &self,
+        mut v: V,
+    ) -> Result<Option<(NodeId, V::Result)>, VisitError<V::Error>>
+    where
+        V: DescendVisit<S::K, S::V, S::Augmentation>,
+    {
+        let mut node_id = self.root;
+        let mut ctx = VisitContext::root();
         loop {
             match node_id {
-                super::NodeId::Inner(inner_id) => {
+                NodeId::Inner(inner_id) => {
                     let inner = self.node_store.get_inner(inner_id);
 
-                    match v.visit_inner(inner.keys(), inner.augmentations()) {
+                    let visit = v
+                        .visit_inner(inner.keys(), inner.augmentations(), ctx)
+                        .map_err(VisitError::Visitor)?;
+                    match visit {
                         DescendVisitResult::GoDown(child_idx) => {
-                            if child_idx > inner.len() {
-                                panic!("invalid index");
-                            }
-                            let child_id = inner.child_id(child_idx);
-
-                            node_id = child_id;
+                            node_id = Self::child_to_visit(inner, child_idx, ctx.depth)?;
+                            ctx = ctx.child(inner.keys(), child_idx);
                         }
                         DescendVisitResult::Cancel => {
-                            return None;
+                            return Ok(None);
                         }
                         DescendVisitResult::Complete(r) => {
-                            return Some(r);
+                            return Ok(Some((node_id, r)));
                         }
                     }
                 }
-                super::NodeId::Leaf(leaf_id) => {
+                NodeId::Leaf(leaf_id) => {
                     let leaf = self.node_store.get_leaf(leaf_id);
-                    return v.visit_leaf(leaf.keys(), leaf.values());
+                    let r = v
+                        .visit_leaf(leaf.keys(), leaf.values(), ctx)
+                        .map_err(VisitError::Visitor)?;
+                    return Ok(r.map(|r| (node_id, r)));
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::Rng;
+
+    use super::*;
+    use crate::{
+        augment::{count::Count, Monoid},
+        BPlusTree, NodeStoreVec,
+    };
+
+    #[derive(Clone, Copy, Debug, Default)]
+    struct KeySum(u64);
+
+    impl Monoid<u64> for KeySum {
+        fn identity() -> Self {
+            Self(0)
+        }
+
+        fn lift(key: &u64) -> Self {
+            Self(*key)
+        }
+
+        fn combine(left: &Self, right: &Self) -> Self {
+            Self(left.0 + right.0)
+        }
+    }
+
+    #[test]
+    fn test_search_by_fold_prefix_sum() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<u64, (), KeySum>::new());
+        for i in 1..=1000 {
+            tree.insert(i, ());
+        }
+
+        for target in [0, 1, 2, 3, 100, 5050, 5051, 250_000, 500_499] {
+            // first key where prefix sum of keys exceeds target
+            let (cursor, prefix) = tree
+                .search_by_fold(0, |acc, s| {
+                    if acc + s.0 > target {
+                        FoldStep::Descend
+                    } else {
+                        FoldStep::Continue(acc + s.0)
+                    }
+                })
+                .unwrap();
+
+            let k = *cursor.key();
+            assert_eq!(prefix, k * (k - 1) / 2);
+            assert!(prefix <= target && prefix + k > target);
+            assert!(cursor.exists(&tree));
+        }
+
+        // total sum is 500_500, so no key reaches it
+        assert!(tree
+            .search_by_fold(0, |acc, s| {
+                if acc + s.0 > 500_500 {
+                    FoldStep::Descend
+                } else {
+                    FoldStep::Continue(acc + s.0)
+                }
+            })
+            .is_none());
+    }
+
+    /// Flags subtrees containing a multiple of 100
+    #[derive(Clone, Copy, Debug, Default)]
+    struct HasHundred(bool);
+
+    impl Monoid<u64> for HasHundred {
+        fn identity() -> Self {
+            Self(false)
+        }
+
+        fn lift(key: &u64) -> Self {
+            Self(key % 100 == 0)
+        }
+
+        fn combine(left: &Self, right: &Self) -> Self {
+            Self(left.0 || right.0)
+        }
+    }
+
+    #[derive(Default)]
+    struct Hundreds {
+        leaves: usize,
+    }
+
+    impl PruneVisit<u64, u64, HasHundred> for Hundreds {
+        fn visit_inner(&mut self, _keys: &[u64], augmentations: &[HasHundred]) -> ChildSet {
+            augmentations
+                .iter()
+                .enumerate()
+                .filter(|(_, a)| a.0)
+                .map(|(idx, _)| idx)
+                .collect()
+        }
+
+        fn visit_leaf(&mut self, keys: &[u64], _values: &[u64]) -> Range<usize> {
+            self.leaves += 1;
+            let start = keys.iter().position(|k| k % 100 == 0).unwrap_or(keys.len());
+            let end = keys
+                .iter()
+                .rposition(|k| k % 100 == 0)
+                .map_or(start, |i| i + 1);
+            start..end
+        }
+    }
+
+    struct Everything;
+
+    impl PruneVisit<u64, u64, HasHundred> for Everything {
+        fn visit_inner(&mut self, _: &[u64], _: &[HasHundred]) -> ChildSet {
+            ChildSet::all()
+        }
+    }
+
+    #[test]
+    fn test_prune_visit_skips_subtrees() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<u64, u64, HasHundred>::new());
+        assert_eq!(tree.prune_visit(Hundreds::default()).count(), 0);
+
+        for i in 1..=10_000 {
+            tree.insert(i, i * 2);
+        }
+
+        let mut visitor = Hundreds::default();
+        let found = tree
+            .prune_visit(&mut visitor)
+            .flat_map(|(keys, values)| keys.iter().zip(values))
+            .filter(|(k, _)| *k % 100 == 0)
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+
+        let expected = tree
+            .iter()
+            .filter(|(k, _)| *k % 100 == 0)
+            .map(|(k, v)| (*k, *v))
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected);
+        assert_eq!(found.len(), 100);
+        assert!(visitor.leaves <= 100);
+        assert!(visitor.leaves < tree.prune_visit(Everything).count());
+
+        // everything is pruned when root says so
+        struct Nothing;
+        impl PruneVisit<u64, u64, HasHundred> for Nothing {
+            fn visit_inner(&mut self, _: &[u64], _: &[HasHundred]) -> ChildSet {
+                ChildSet::none()
+            }
+        }
+        assert_eq!(tree.prune_visit(Nothing).count(), 0);
+
+        // a full set visits all items
+        assert!(tree
+            .prune_visit(Everything)
+            .flat_map(|(keys, _)| keys)
+            .eq(tree.iter().map(|(k, _)| k)));
+    }
+
+    /// Locate the nth element by count, and apply `edit` to it
+    struct EditNth {
+        n: usize,
+        edit: fn(&i64, &mut i64) -> LeafEdit<i64, i64>,
+    }
+
+    impl DescendVisitMut<i64, i64, Count> for EditNth {
+        type Result = i64;
+        type Error = Infallible;
+
+        fn visit_inner(
+            &mut self,
+            _keys: &[i64],
+            counts: &[Count],
+            _ctx: VisitContext<'_, i64>,
+        ) -> Result<DescendVisitResult<i64>, Infallible> {
+            for (idx, c) in counts.iter().enumerate() {
+                if self.n < c.count() {
+                    return Ok(DescendVisitResult::GoDown(idx));
+                }
+                self.n -= c.count();
+            }
+            Ok(DescendVisitResult::Cancel)
+        }
+
+        fn visit_leaf(
+            &mut self,
+            keys: &[i64],
+            values: &mut [i64],
+            ctx: VisitContext<'_, i64>,
+        ) -> Result<Option<(i64, LeafEdit<i64, i64>)>, Infallible> {
+            assert!(keys.first() >= ctx.lower);
+            assert!(ctx.upper.map_or(true, |upper| keys.last() < Some(upper)));
+            let Some(key) = keys.get(self.n) else {
+                return Ok(None);
+            };
+            let edit = match (self.edit)(key, &mut values[self.n]) {
+                LeafEdit::Modified(_) => LeafEdit::Modified(self.n),
+                LeafEdit::Remove(_) => LeafEdit::Remove(self.n),
+                edit => edit,
+            };
+            Ok(Some((*key, edit)))
+        }
+    }
+
+    #[test]
+    fn test_descend_visit_mut_edits() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+        let mut expected = BTreeMap::new();
+        for i in 0..2000 {
+            tree.insert(i * 2, i);
+            expected.insert(i * 2, i);
+        }
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..3000 {
+            let n = rng.gen_range(0..expected.len());
+            let key = *expected.keys().nth(n).unwrap();
+            let edit: fn(&i64, &mut i64) -> LeafEdit<i64, i64> = match rng.gen_range(0..4) {
+                0 => |_, v| {
+                    *v += 1;
+                    LeafEdit::Modified(0)
+                },
+                1 => |_, _| LeafEdit::Remove(0),
+                2 => |k, _| LeafEdit::Insert(k + 1, -1),
+                _ => |k, _| LeafEdit::Insert(k + 10_000, -2),
+            };
+            let found = tree
+                .descend_visit_mut(EditNth { n, edit })
+                .unwrap()
+                .unwrap();
+            assert_eq!(found, key);
+
+            let mut value = *expected.get(&key).unwrap();
+            match edit(&key, &mut value) {
+                LeafEdit::None => {}
+                LeafEdit::Modified(_) => {
+                    expected.insert(key, value);
+                }
+                LeafEdit::Remove(_) => {
+                    expected.remove(&key);
+                }
+                LeafEdit::Insert(k, v) => {
+                    expected.insert(k, v);
+                }
+            }
+
+            assert_eq!(tree.len(), expected.len());
+            assert_eq!(tree.root_augmentation().count(), expected.len());
+        }
+
+        assert!(tree.iter().eq(expected.iter()));
+        for (idx, (k, _)) in expected.iter().enumerate() {
+            assert_eq!(tree.rank_by_augmentation::<usize>(k), Ok(idx));
+        }
+        assert!(tree
+            .descend_visit_mut(EditNth {
+                n: expected.len(),
+                edit: |_, _| LeafEdit::None,
+            })
+            .unwrap()
+            .is_none());
+    }
+
+    /// Goes down to the first leaf, and returns `edit`
+    struct EditFirstLeaf(Option<LeafEdit<i64, i64>>);
+
+    impl DescendVisitMut<i64, i64, Count> for EditFirstLeaf {
+        type Result = ();
+        type Error = Infallible;
+
+        fn visit_inner(
+            &mut self,
+            _keys: &[i64],
+            _counts: &[Count],
+            _ctx: VisitContext<'_, i64>,
+        ) -> Result<DescendVisitResult<()>, Infallible> {
+            Ok(DescendVisitResult::GoDown(0))
+        }
+
+        fn visit_leaf(
+            &mut self,
+            _keys: &[i64],
+            _values: &mut [i64],
+            _ctx: VisitContext<'_, i64>,
+        ) -> Result<Option<((), LeafEdit<i64, i64>)>, Infallible> {
+            Ok(self.0.take().map(|edit| ((), edit)))
+        }
+    }
+
+    #[test]
+    fn test_descend_visit_mut_edit_out_of_range() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+        for i in 0..1000 {
+            tree.insert(i, i);
+        }
+        let len = tree.node_store.get_leaf(tree.first_leaf().unwrap()).len();
+
+        for edit in [LeafEdit::Modified(len), LeafEdit::Remove(len + 100)] {
+            let index = match edit {
+                LeafEdit::Modified(slot) | LeafEdit::Remove(slot) => slot,
+                _ => unreachable!(),
+            };
+            assert_eq!(
+                tree.descend_visit_mut(EditFirstLeaf(Some(edit))),
+                Err(VisitError::InvalidSlot { index, len })
+            );
+        }
+        assert_eq!(tree.len(), 1000);
+        tree.check_invariants().unwrap();
+    }
+
+    /// Goes down by key until `fail_at` depth, fails there with the node's bounds
+    struct FailAt {
+        key: i64,
+        fail_at: usize,
+    }
+
+    impl DescendVisit<i64, i64, Count> for FailAt {
+        type Result = ();
+        type Error = (Option<i64>, Option<i64>);
+
+        fn visit_inner(
+            &mut self,
+            keys: &[i64],
+            _augmentations: &[Count],
+            ctx: VisitContext<'_, i64>,
+        ) -> Result<DescendVisitResult<()>, Self::Error> {
+            if ctx.depth == self.fail_at {
+                return Err((ctx.lower.copied(), ctx.upper.copied()));
+            }
+            Ok(DescendVisitResult::GoDown(
+                keys.partition_point(|k| *k <= self.key),
+            ))
+        }
+
+        fn visit_leaf(
+            &mut self,
+            _keys: &[i64],
+            _values: &[i64],
+            ctx: VisitContext<'_, i64>,
+        ) -> Result<Option<()>, Self::Error> {
+            Err((ctx.lower.copied(), ctx.upper.copied()))
+        }
+    }
+
+    #[test]
+    fn test_descend_visit_errors() {
+        let mut tree = BPlusTree::new(NodeStoreVec::<i64, i64, Count>::new());
+        for i in 0..10_000 {
+            tree.insert(i, i);
+        }
+
+        for key in [0, 1, 63, 5000, 9999] {
+            for fail_at in 0..4 {
+                let Err(VisitError::Visitor((lower, upper))) =
+                    tree.descend_visit(FailAt { key, fail_at })
+                else {
+                    panic!("visitor error expected");
+                };
+                assert!(lower.map_or(true, |l| l <= key));
+                assert!(upper.map_or(true, |u| key < u));
+                if fail_at == 0 {
+                    assert_eq!((lower, upper), (None, None));
+                }
+            }
+        }
+
+        struct GoTo(usize);
+        impl DescendVisit<i64, i64, Count> for GoTo {
+            type Result = ();
+            type Error = Infallible;
+
+            fn visit_inner(
+                &mut self,
+                _keys: &[i64],
+                augmentations: &[Count],
+                _ctx: VisitContext<'_, i64>,
+            ) -> Result<DescendVisitResult<()>, Infallible> {
+                Ok(DescendVisitResult::GoDown(augmentations.len() - 1 + self.0))
+            }
+
+            fn visit_leaf(
+                &mut self,
+                _keys: &[i64],
+                _values: &[i64],
+                _ctx: VisitContext<'_, i64>,
+            ) -> Result<Option<()>, Infallible> {
+                Ok(Some(()))
+            }
+        }
+
+        assert_eq!(tree.descend_visit(GoTo(0)), Ok(Some(())));
+        let root_len = tree
+            .node_store
+            .get_inner(tree.root.inner_id().unwrap())
+            .len()
+            + 1;
+        assert_eq!(
+            tree.descend_visit(GoTo(1)),
+            Err(VisitError::InvalidChild {
+                depth: 0,
+                index: root_len,
+                len: root_len,
+            })
+        );
+    }
+}
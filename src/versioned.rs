@@ -0,0 +1,199 @@
+use std::{borrow::Borrow, collections::VecDeque, sync::Arc};
+
+use crate::{tree::Augmentation, BPlusTree, Key, NodeStoreArc};
+
+type Tree<K, V, A> = BPlusTree<NodeStoreArc<K, V, A>>;
+
+/// A B+ tree map keeps old versions readable.
+///
+/// Each `insert` and `remove` bumps the version by one, version 0 is the empty map. `view`
+/// opens any retained version as a read-only tree. Versions share unchanged nodes through
+/// `NodeStoreArc`, so each version costs only the nodes copied by its write.
+///
+/// Versions are retained until released by `release_before`. A released version's nodes are
+/// freed once all its views are dropped.
+///
+/// # Example
+/// ```rust
+/// use sweep_bptree::VersionedBPlusTreeMap;
+///
+/// let mut map = VersionedBPlusTreeMap::<u64, &str>::new();
+/// map.insert(1, "a");
+/// map.insert(2, "b");
+/// map.insert(1, "c");
+/// assert_eq!(map.version(), 3);
+///
+/// let v2 = map.view(2).unwrap();
+/// assert_eq!(v2.get(&1), Some(&"a"));
+/// assert_eq!(v2.range(1..).count(), 2);
+/// assert_eq!(map.get(&1), Some(&"c"));
+///
+/// map.release_before(3);
+/// assert!(map.view(2).is_none());
+/// // the view opened before release is still readable
+/// assert_eq!(v2.get(&2), Some(&"b"));
+/// ```
+pub struct VersionedBPlusTreeMap<K: Key, V: Clone, A: Augmentation<K> = ()> {
+    current: Tree<K, V, A>,
+    /// retained versions, oldest first. The last one is `current`'s snapshot
+    versions: VecDeque<Arc<Tree<K, V, A>>>,
+    /// version of `versions[0]`
+    oldest: u64,
+}
+
+impl<K: Key, V: Clone, A: Augmentation<K>> Default for VersionedBPlusTreeMap<K, V, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Key, V: Clone, A: Augmentation<K>> VersionedBPlusTreeMap<K, V, A> {
+    /// Create an empty map at version 0
+    pub fn new() -> Self {
+        let current = BPlusTree::new(NodeStoreArc::new());
+        Self {
+            versions: VecDeque::from([Arc::new(current.snapshot())]),
+            current,
+            oldest: 0,
+        }
+    }
+
+    /// Returns the current version
+    pub fn version(&self) -> u64 {
+        self.oldest + self.versions.len() as u64 - 1
+    }
+
+    /// Returns the oldest retained version
+    pub fn oldest_version(&self) -> u64 {
+        self.oldest
+    }
+
+    /// Returns item count of the current version
+    pub fn len(&self) -> usize {
+        self.current.len()
+    }
+
+    /// Returns true if the current version contains no item
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a reference to the value for `key` in the current version
+    pub fn get<Q: ?Sized + Ord>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.current.get(key)
+    }
+
+    /// Returns the root augmentation of the current version
+    pub fn root_augmentation(&self) -> &A {
+        self.current.root_augmentation()
+    }
+
+    /// Insert a key-value pair, creates a new version
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let old = self.current.insert(key, value);
+        self.commit();
+        old
+    }
+
+    /// Remove a key, creates a new version even if the key doesn't exist
+    pub fn remove<Q: ?Sized + Ord>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let old = self.current.remove(key);
+        self.commit();
+        old
+    }
+
+    /// Open a read-only view of `version`. Returns None if the version is released or not
+    /// created yet.
+    ///
+    /// The view provides all read methods of `BPlusTree`, e.g. `get`, `range` and
+    /// `root_augmentation`, and stays readable after the version is released.
+    pub fn view(&self, version: u64) -> Option<Arc<Tree<K, V, A>>> {
+        let idx = version.checked_sub(self.oldest)?;
+        self.versions.get(usize::try_from(idx).ok()?).cloned()
+    }
+
+    /// Release all versions before `version`, the current version is always retained.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use sweep_bptree::VersionedBPlusTreeMap;
+    ///
+    /// let mut map = VersionedBPlusTreeMap::<u64, u64>::new();
+    /// for i in 0..10 {
+    ///     map.insert(i, i);
+    /// }
+    ///
+    /// map.release_before(8);
+    /// assert_eq!(map.oldest_version(), 8);
+    /// assert_eq!(map.view(8).unwrap().len(), 8);
+    ///
+    /// map.release_before(100);
+    /// assert_eq!(map.oldest_version(), 10);
+    /// ```
+    pub fn release_before(&mut self, version: u64) {
+        let version = version.min(self.version());
+        while self.oldest < version {
+            self.versions.pop_front();
+            self.oldest += 1;
+        }
+    }
+
+    fn commit(&mut self) {
+        self.versions.push_back(Arc::new(self.current.snapshot()));
+    }
+}
+
+/// ensure VersionedBPlusTreeMap is send for send + sync k v
+fn _ensure_send<K: Key + Send + Sync, V: Clone + Send + Sync>() {
+    fn _assert_send<T: Send>() {}
+    _assert_send::<VersionedBPlusTreeMap<K, V>>();
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::augment::count::Count;
+
+    #[test]
+    fn test_versioned_views() {
+        let mut rng = rand::thread_rng();
+        let mut map = VersionedBPlusTreeMap::<u32, u32, Count>::new();
+        let mut expected = vec![std::collections::BTreeMap::new()];
+
+        for i in 0..3000 {
+            let mut next = expected.last().unwrap().clone();
+            let k = rng.gen_range(0..500);
+            if rng.gen_bool(0.7) {
+                assert_eq!(map.insert(k, i), next.insert(k, i));
+            } else {
+                assert_eq!(map.remove(&k), next.remove(&k));
+            }
+            expected.push(next);
+        }
+        assert_eq!(map.version(), 3000);
+
+        for _ in 0..50 {
+            let version = rng.gen_range(0..=map.version());
+            let view = map.view(version).unwrap();
+            let expected = &expected[version as usize];
+            assert!(view.iter().eq(expected.iter()));
+            assert_eq!(view.root_augmentation().count(), expected.len());
+            assert!(view.range(100..200).eq(expected.range(100..200)));
+        }
+
+        let pinned = map.view(1000).unwrap();
+        map.release_before(2000);
+        assert!(map.view(1999).is_none());
+        assert!(map.view(3001).is_none());
+        assert!(map.view(2000).unwrap().iter().eq(expected[2000].iter()));
+        assert!(pinned.iter().eq(expected[1000].iter()));
+    }
+}